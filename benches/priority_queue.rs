@@ -0,0 +1,123 @@
+/*
+ * Heap benchmarks, no harness or dependencies: `cargo bench --bench priority_queue [-- N]`
+ * For every element size, each heap gets the same N keys and is timed on
+ * push N - pushing every key into an empty heap
+ * pop N - popping the full heap empty
+ * hold - N pop + push pairs at full size, the steady state of an event simulation
+ *  where handling one event schedules the next
+ * PriorityQueue runs at arity 2, 4 and 8, PairingHeap and BinomialHeap through the same
+ * Heap trait so they line up against it. Times are ns per operation, best of a few runs
+ */
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use rust_dsa::binomial_heap::BinomialHeap;
+use rust_dsa::pairing_heap::PairingHeap;
+use rust_dsa::priority_queue::{Min, PriorityQueue};
+use rust_dsa::traits::Heap;
+
+const RUNS: usize = 3;
+
+/// n keys from a fixed seed, so every run and every heap gets the same input
+fn keys(n: usize) -> Vec<u64> {
+    let mut seed = 0x2545_f491_4f6c_dd1d_u64;
+    (0..n)
+        .map(|_| {
+            seed = seed
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            seed >> 16
+        })
+        .collect()
+}
+
+/// An item of K words ordered by its first, so bigger items cost more to move, not to compare
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+struct Item<const K: usize>([u64; K]);
+
+impl<const K: usize> Item<K> {
+    fn new(key: u64) -> Self {
+        let mut words = [0; K];
+        words[0] = key;
+        Item(words)
+    }
+}
+
+struct Timings {
+    push: Duration,
+    pop: Duration,
+    hold: Duration,
+}
+
+fn run<E: Copy, H: Heap<E> + Default>(items: &[E], later: &[E]) -> Timings {
+    let mut heap = H::default();
+    let start = Instant::now();
+    for &item in items {
+        heap.push(item);
+    }
+    let push = start.elapsed();
+
+    let start = Instant::now();
+    for &item in later {
+        black_box(heap.pop());
+        heap.push(item);
+    }
+    let hold = start.elapsed();
+
+    let start = Instant::now();
+    while let Some(item) = heap.pop() {
+        black_box(item);
+    }
+    let pop = start.elapsed();
+    Timings { push, pop, hold }
+}
+
+fn bench<E: Copy, H: Heap<E> + Default>(name: &str, items: &[E], later: &[E]) {
+    let mut best = run::<E, H>(items, later);
+    for _ in 1..RUNS {
+        let t = run::<E, H>(items, later);
+        best.push = best.push.min(t.push);
+        best.pop = best.pop.min(t.pop);
+        best.hold = best.hold.min(t.hold);
+    }
+    let per_op = |d: Duration| d.as_nanos() as f64 / items.len() as f64;
+    println!(
+        "  {name:<22} {:>9.1} {:>9.1} {:>9.1}",
+        per_op(best.push),
+        per_op(best.pop),
+        per_op(best.hold)
+    );
+}
+
+fn bench_size<const K: usize>(n: usize) {
+    let all = keys(2 * n);
+    // later keys are pushed during hold, shifted up so they land behind everything queued
+    // like events scheduled in the future
+    let items: Vec<Item<K>> = all[..n].iter().map(|&k| Item::new(k)).collect();
+    let later: Vec<Item<K>> = all[n..]
+        .iter()
+        .map(|&k| Item::new(k / 2 + u64::MAX / 4))
+        .collect();
+
+    println!(
+        "\n{} byte items, {n} of them",
+        std::mem::size_of::<Item<K>>()
+    );
+    println!("  {:<22} {:>9} {:>9} {:>9}", "ns/op", "push", "pop", "hold");
+    bench::<_, PriorityQueue<Item<K>, Min, 2>>("PriorityQueue d=2", &items, &later);
+    bench::<_, PriorityQueue<Item<K>, Min, 4>>("PriorityQueue d=4", &items, &later);
+    bench::<_, PriorityQueue<Item<K>, Min, 8>>("PriorityQueue d=8", &items, &later);
+    bench::<_, PairingHeap<Item<K>>>("PairingHeap", &items, &later);
+    bench::<_, BinomialHeap<Item<K>>>("BinomialHeap", &items, &later);
+}
+
+fn main() {
+    // cargo passes --bench along, the first number is the queue size
+    let n = std::env::args()
+        .skip(1)
+        .find_map(|arg| arg.parse().ok())
+        .unwrap_or(1_000_000);
+    bench_size::<1>(n);
+    bench_size::<4>(n);
+    bench_size::<16>(n);
+}
@@ -0,0 +1,561 @@
+/*
+Allocator:
+ * where MyVec, Stack and RingBuffer get their memory from, a stable stand-in for the
+   unstable std::alloc::Allocator with the same method names and contracts
+ * Global - the global allocator (std::alloc::alloc), the default for every collection
+ * Bump - an arena over one fixed chunk: allocating bumps an offset, freeing is a no-op
+   unless it was the last allocation, everything is given back at once when the arena goes
+ * Counting - wraps another allocator and counts live blocks and bytes, so tests can check
+   a collection frees exactly what it allocated
+ * collections borrow a Bump or Counting (`MyVec::new_in(&bump)`), &A is an Allocator too
+*/
+use std::alloc::{self, Layout};
+use std::cell::Cell;
+use std::fmt;
+use std::ptr::{self, NonNull};
+
+/// The allocator couldn't hand out the memory asked for
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AllocError;
+
+impl fmt::Display for AllocError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "memory allocation failed")
+    }
+}
+
+impl std::error::Error for AllocError {}
+
+/// # Safety
+/// Memory handed out must stay valid, and not be handed out again, until it's deallocated
+/// (or grown/shrunk away) or every copy of the allocator is gone.
+/// The callers' side of the contract is the usual one: only pass back pointers this
+/// allocator returned, with the layout they were allocated with
+pub unsafe trait Allocator {
+    /// A block of at least layout.size() bytes aligned to layout.align().
+    /// Zero sized layouts may get a dangling, well aligned pointer
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError>;
+
+    /// # Safety
+    /// ptr came from this allocator, allocated with layout
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout);
+
+    /// Moves ptr's block to one fitting new_layout, keeping the old bytes.
+    /// The default allocates, copies and frees
+    ///
+    /// # Safety
+    /// ptr came from this allocator with old_layout, and new_layout.size() >= old_layout.size()
+    unsafe fn grow(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        debug_assert!(new_layout.size() >= old_layout.size());
+        let new_ptr = self.allocate(new_layout)?;
+        unsafe {
+            ptr::copy_nonoverlapping(ptr.as_ptr(), new_ptr.as_ptr() as *mut u8, old_layout.size());
+            self.deallocate(ptr, old_layout);
+        }
+        Ok(new_ptr)
+    }
+
+    /// Moves ptr's block to one fitting new_layout, keeping the first new_layout.size() bytes
+    ///
+    /// # Safety
+    /// ptr came from this allocator with old_layout, and new_layout.size() <= old_layout.size()
+    unsafe fn shrink(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        debug_assert!(new_layout.size() <= old_layout.size());
+        let new_ptr = self.allocate(new_layout)?;
+        unsafe {
+            ptr::copy_nonoverlapping(ptr.as_ptr(), new_ptr.as_ptr() as *mut u8, new_layout.size());
+            self.deallocate(ptr, old_layout);
+        }
+        Ok(new_ptr)
+    }
+}
+
+unsafe impl<A: Allocator + ?Sized> Allocator for &A {
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        (**self).allocate(layout)
+    }
+
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        unsafe { (**self).deallocate(ptr, layout) }
+    }
+
+    unsafe fn grow(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        unsafe { (**self).grow(ptr, old_layout, new_layout) }
+    }
+
+    unsafe fn shrink(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        unsafe { (**self).shrink(ptr, old_layout, new_layout) }
+    }
+}
+
+/// a well aligned pointer for a zero sized block, nothing to allocate or free
+fn dangling(layout: Layout) -> NonNull<[u8]> {
+    let ptr = NonNull::new(ptr::without_provenance_mut(layout.align())).unwrap();
+    NonNull::slice_from_raw_parts(ptr, 0)
+}
+
+/// The global allocator, what Box and Vec use
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Global;
+
+unsafe impl Allocator for Global {
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        if layout.size() == 0 {
+            return Ok(dangling(layout));
+        }
+        let ptr = NonNull::new(unsafe { alloc::alloc(layout) }).ok_or(AllocError)?;
+        Ok(NonNull::slice_from_raw_parts(ptr, layout.size()))
+    }
+
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        if layout.size() != 0 {
+            unsafe { alloc::dealloc(ptr.as_ptr(), layout) }
+        }
+    }
+
+    unsafe fn grow(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        // realloc can't change alignment or start from nothing
+        if old_layout.size() == 0 || old_layout.align() != new_layout.align() {
+            let new_ptr = self.allocate(new_layout)?;
+            unsafe {
+                ptr::copy_nonoverlapping(
+                    ptr.as_ptr(),
+                    new_ptr.as_ptr() as *mut u8,
+                    old_layout.size(),
+                );
+                self.deallocate(ptr, old_layout);
+            }
+            return Ok(new_ptr);
+        }
+        let raw = unsafe { alloc::realloc(ptr.as_ptr(), old_layout, new_layout.size()) };
+        let ptr = NonNull::new(raw).ok_or(AllocError)?;
+        Ok(NonNull::slice_from_raw_parts(ptr, new_layout.size()))
+    }
+
+    unsafe fn shrink(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        if new_layout.size() == 0 || old_layout.align() != new_layout.align() {
+            let new_ptr = self.allocate(new_layout)?;
+            unsafe {
+                ptr::copy_nonoverlapping(
+                    ptr.as_ptr(),
+                    new_ptr.as_ptr() as *mut u8,
+                    new_layout.size(),
+                );
+                self.deallocate(ptr, old_layout);
+            }
+            return Ok(new_ptr);
+        }
+        let raw = unsafe { alloc::realloc(ptr.as_ptr(), old_layout, new_layout.size()) };
+        let ptr = NonNull::new(raw).ok_or(AllocError)?;
+        Ok(NonNull::slice_from_raw_parts(ptr, new_layout.size()))
+    }
+}
+
+/// alignment of a Bump's chunk, allocations aligned to more than this still work,
+/// they just might skip some bytes
+const CHUNK_ALIGN: usize = 16;
+
+/// Arena over a single fixed size chunk, not thread safe.
+/// Runs out (AllocError) rather than asking for more memory
+pub struct Bump {
+    chunk: NonNull<u8>,
+    size: usize,
+    /// bytes handed out so far, the next allocation starts at or after here
+    used: Cell<usize>,
+    /// where the most recent allocation starts, the only one that can be freed or resized in place
+    last: Cell<usize>,
+}
+
+impl Bump {
+    /// a chunk of size bytes, taken from the global allocator
+    pub fn with_capacity(size: usize) -> Self {
+        let chunk = if size == 0 {
+            NonNull::new(ptr::without_provenance_mut(CHUNK_ALIGN)).unwrap()
+        } else {
+            let layout = Layout::from_size_align(size, CHUNK_ALIGN).expect("arena too large");
+            match NonNull::new(unsafe { alloc::alloc(layout) }) {
+                Some(p) => p,
+                None => alloc::handle_alloc_error(layout),
+            }
+        };
+        Bump {
+            chunk,
+            size,
+            used: Cell::new(0),
+            last: Cell::new(0),
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.size
+    }
+
+    pub fn used(&self) -> usize {
+        self.used.get()
+    }
+
+    /// frees everything at once, &mut so nothing can still be pointing in
+    pub fn reset(&mut self) {
+        self.used.set(0);
+        self.last.set(0);
+    }
+
+    /// offset of the block ptr points at
+    fn offset_of(&self, ptr: NonNull<u8>) -> usize {
+        ptr.as_ptr() as usize - self.chunk.as_ptr() as usize
+    }
+
+    /// the first offset at or after from that's aligned to align in memory
+    fn align_up(&self, from: usize, align: usize) -> Option<usize> {
+        let addr = (self.chunk.as_ptr() as usize).checked_add(from)?;
+        let aligned = addr.checked_add(align - 1)? & !(align - 1);
+        Some(aligned - self.chunk.as_ptr() as usize)
+    }
+
+    fn block(&self, offset: usize, size: usize) -> NonNull<[u8]> {
+        let ptr = unsafe { NonNull::new_unchecked(self.chunk.as_ptr().add(offset)) };
+        NonNull::slice_from_raw_parts(ptr, size)
+    }
+}
+
+unsafe impl Allocator for Bump {
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        if layout.size() == 0 {
+            return Ok(dangling(layout));
+        }
+        let start = self
+            .align_up(self.used.get(), layout.align())
+            .ok_or(AllocError)?;
+        let end = start.checked_add(layout.size()).ok_or(AllocError)?;
+        if end > self.size {
+            return Err(AllocError);
+        }
+        self.last.set(start);
+        self.used.set(end);
+        Ok(self.block(start, layout.size()))
+    }
+
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        if layout.size() == 0 {
+            return;
+        }
+        // popping the last allocation gives its bytes back, anything else waits for the arena
+        let offset = self.offset_of(ptr);
+        if offset == self.last.get() && offset + layout.size() == self.used.get() {
+            self.used.set(offset);
+        }
+    }
+
+    unsafe fn grow(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        // the last allocation can grow in place if it's aligned enough and there's room after it
+        if old_layout.size() != 0 {
+            let offset = self.offset_of(ptr);
+            let is_last =
+                offset == self.last.get() && offset + old_layout.size() == self.used.get();
+            if is_last && (ptr.as_ptr() as usize).is_multiple_of(new_layout.align()) {
+                let end = offset.checked_add(new_layout.size()).ok_or(AllocError)?;
+                if end <= self.size {
+                    self.used.set(end);
+                    return Ok(self.block(offset, new_layout.size()));
+                }
+            }
+        }
+        let new_ptr = self.allocate(new_layout)?;
+        unsafe {
+            ptr::copy_nonoverlapping(ptr.as_ptr(), new_ptr.as_ptr() as *mut u8, old_layout.size());
+        }
+        Ok(new_ptr)
+    }
+
+    unsafe fn shrink(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        if new_layout.size() == 0 {
+            unsafe { self.deallocate(ptr, old_layout) };
+            return Ok(dangling(new_layout));
+        }
+        if !(ptr.as_ptr() as usize).is_multiple_of(new_layout.align()) {
+            return Err(AllocError);
+        }
+        // shrinking in place never moves anything, the last allocation also gives bytes back
+        let offset = self.offset_of(ptr);
+        if offset == self.last.get() && offset + old_layout.size() == self.used.get() {
+            self.used.set(offset + new_layout.size());
+        }
+        Ok(self.block(offset, new_layout.size()))
+    }
+}
+
+impl Drop for Bump {
+    fn drop(&mut self) {
+        if self.size != 0 {
+            let layout = Layout::from_size_align(self.size, CHUNK_ALIGN).unwrap();
+            unsafe { alloc::dealloc(self.chunk.as_ptr(), layout) };
+        }
+    }
+}
+
+impl fmt::Debug for Bump {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Bump")
+            .field("capacity", &self.size)
+            .field("used", &self.used.get())
+            .finish()
+    }
+}
+
+/// Counts what's allocated through it and not yet freed, not thread safe
+#[derive(Debug, Default)]
+pub struct Counting<A: Allocator = Global> {
+    inner: A,
+    blocks: Cell<usize>,
+    bytes: Cell<usize>,
+    allocations: Cell<usize>,
+}
+
+impl Counting {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<A: Allocator> Counting<A> {
+    pub fn wrapping(inner: A) -> Self {
+        Counting {
+            inner,
+            blocks: Cell::new(0),
+            bytes: Cell::new(0),
+            allocations: Cell::new(0),
+        }
+    }
+
+    /// blocks allocated and not yet freed
+    pub fn live_blocks(&self) -> usize {
+        self.blocks.get()
+    }
+
+    /// bytes allocated and not yet freed, zero sized blocks count as a block of no bytes
+    pub fn live_bytes(&self) -> usize {
+        self.bytes.get()
+    }
+
+    /// every allocate ever made, grows and shrinks not included
+    pub fn allocations(&self) -> usize {
+        self.allocations.get()
+    }
+
+    fn resized(&self, old_layout: Layout, new_layout: Layout) {
+        self.bytes
+            .set(self.bytes.get() - old_layout.size() + new_layout.size());
+    }
+}
+
+unsafe impl<A: Allocator> Allocator for Counting<A> {
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        let ptr = self.inner.allocate(layout)?;
+        self.blocks.set(self.blocks.get() + 1);
+        self.bytes.set(self.bytes.get() + layout.size());
+        self.allocations.set(self.allocations.get() + 1);
+        Ok(ptr)
+    }
+
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        unsafe { self.inner.deallocate(ptr, layout) };
+        self.blocks.set(self.blocks.get() - 1);
+        self.bytes.set(self.bytes.get() - layout.size());
+    }
+
+    unsafe fn grow(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        let ptr = unsafe { self.inner.grow(ptr, old_layout, new_layout)? };
+        self.resized(old_layout, new_layout);
+        Ok(ptr)
+    }
+
+    unsafe fn shrink(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        let ptr = unsafe { self.inner.shrink(ptr, old_layout, new_layout)? };
+        self.resized(old_layout, new_layout);
+        Ok(ptr)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ring_buffer::{Overflow, RingBuffer};
+    use crate::stack::Stack;
+    use crate::vec::{MyVec, TryReserveError};
+
+    #[test]
+    fn bump_hands_out_aligned_blocks() {
+        let bump = Bump::with_capacity(64);
+        let a = bump.allocate(Layout::new::<u8>()).unwrap();
+        let b = bump.allocate(Layout::new::<u64>()).unwrap();
+        assert_eq!(0, b.as_ptr() as *mut u8 as usize % 8);
+        assert!(b.as_ptr() as *mut u8 as usize > a.as_ptr() as *mut u8 as usize);
+        assert_eq!(16, bump.used());
+
+        // only the last block gives its bytes back
+        unsafe { bump.deallocate(a.cast(), Layout::new::<u8>()) };
+        assert_eq!(16, bump.used());
+        unsafe { bump.deallocate(b.cast(), Layout::new::<u64>()) };
+        assert_eq!(8, bump.used());
+
+        assert_eq!(Err(AllocError), bump.allocate(Layout::new::<[u8; 64]>()));
+        assert!(bump.allocate(Layout::new::<[u8; 56]>()).is_ok());
+        assert_eq!(64, bump.used());
+    }
+
+    #[test]
+    fn bump_grows_the_last_block_in_place() {
+        let bump = Bump::with_capacity(256);
+        let mut v = MyVec::new_in(&bump);
+        v.extend(0..4u32);
+        let before = v.as_ptr();
+        v.extend(4..16u32);
+        assert_eq!(before, v.as_ptr());
+        assert_eq!(64, bump.used());
+
+        // anything allocated after it pins it, so it has to move
+        let _pin = bump.allocate(Layout::new::<u8>()).unwrap();
+        v.push(16);
+        assert_ne!(before, v.as_ptr());
+        assert_eq!(
+            (0..17).collect::<Vec<u32>>(),
+            v.iter().copied().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn collections_run_out_of_arena() {
+        let bump = Bump::with_capacity(32);
+        let mut v = MyVec::<u64, _>::new_in(&bump);
+        assert_eq!(Ok(()), v.try_reserve_exact(4));
+        assert!(matches!(
+            v.try_reserve_exact(5),
+            Err(TryReserveError::AllocError { .. })
+        ));
+        assert_eq!(4, v.capacity());
+        drop(v);
+
+        let mut bump = bump;
+        bump.reset();
+        let mut r = RingBuffer::new_in(4, &bump);
+        for i in 0..10u64 {
+            r.push_front(i);
+        }
+        assert_eq!(
+            vec![6, 7, 8, 9],
+            r.iter().rev().copied().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn counting_tracks_live_memory() {
+        let c = Counting::new();
+        let block = c.allocate(Layout::new::<[u32; 4]>()).unwrap();
+        assert_eq!((1, 16), (c.live_blocks(), c.live_bytes()));
+        let block = unsafe {
+            c.grow(
+                block.cast(),
+                Layout::new::<[u32; 4]>(),
+                Layout::new::<[u32; 8]>(),
+            )
+        }
+        .unwrap();
+        assert_eq!((1, 32), (c.live_blocks(), c.live_bytes()));
+        unsafe { c.deallocate(block.cast(), Layout::new::<[u32; 8]>()) };
+        assert_eq!((0, 0), (c.live_blocks(), c.live_bytes()));
+        assert_eq!(1, c.allocations());
+    }
+
+    #[test]
+    fn collections_free_exactly_what_they_allocate() {
+        let c = Counting::new();
+
+        let mut v = MyVec::new_in(&c);
+        v.extend((0..100).map(|i| i.to_string()));
+        assert_eq!(1, c.live_blocks());
+        let tail = v.split_off(50);
+        let copy = v.clone();
+        assert_eq!(3, c.live_blocks());
+        drop(v.drain(10..20));
+        v.shrink_to_fit();
+        drop((v, tail, copy));
+        assert_eq!((0, 0), (c.live_blocks(), c.live_bytes()));
+
+        let mut v = MyVec::new_in(&c);
+        v.extend(0..10);
+        assert_eq!(Some(9), v.into_iter().next_back());
+        assert_eq!((0, 0), (c.live_blocks(), c.live_bytes()));
+
+        let mut s = Stack::new_in(&c);
+        s.extend(0..1000u16);
+        s.clear();
+        assert_eq!(1, c.live_blocks());
+        drop(s);
+        assert_eq!((0, 0), (c.live_blocks(), c.live_bytes()));
+
+        let mut r = RingBuffer::with_overflow_in(4, Overflow::Grow, &c);
+        r.extend((0..100).map(|i| vec![i; 3]));
+        assert_eq!(100, r.len());
+        drop(r.drain(..50));
+        drop(r);
+        assert_eq!((0, 0), (c.live_blocks(), c.live_bytes()));
+
+        // zero sized types never touch the allocator
+        let mut units = MyVec::new_in(&c);
+        units.extend(std::iter::repeat_n((), 10));
+        let _ = RingBuffer::<(), _>::new_in(8, &c);
+        assert_eq!(0, c.live_blocks());
+        drop(units);
+        assert!(c.allocations() > 0);
+    }
+}
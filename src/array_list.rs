@@ -19,17 +19,20 @@ export default class ArrayList<T> {
     removeAt(idx: number): T | undefined {}
 }
  */
-use std::fmt::Debug;
+use std::cmp::Ordering;
+use std::hash::{Hash, Hasher};
+
+use crate::traits::{Collection, Lifo, RandomAccess};
 // My Fake Array, we're pretending all we can do is get length and not grow this
 type Array<T> = Vec<T>;
 
 #[derive(Debug)]
-struct ArrayList<T> {
+pub struct ArrayList<T> {
     pub length: usize,
     inner: Array<T>,
 }
 
-impl<T: Debug + Default + Clone + PartialEq> ArrayList<T> {
+impl<T: Default + Clone + PartialEq> ArrayList<T> {
     pub fn new() -> ArrayList<T> {
         ArrayList {
             length: 0,
@@ -40,19 +43,32 @@ impl<T: Debug + Default + Clone + PartialEq> ArrayList<T> {
     fn grow_inner(&mut self) {
         let prev = &self.inner;
         let mut new = vec![T::default(); prev.len() * 2];
-        for i in 0..prev.len() {
-            new[i] = prev[i].clone();
-        }
+        new[..prev.len()].clone_from_slice(prev);
         self.inner = new;
     }
 
+    pub fn len(&self) -> usize {
+        self.length
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.length == 0
+    }
+
+    /// resets the used slots to T::default(), keeping the array
+    pub fn clear(&mut self) {
+        for slot in &mut self.inner[..self.length] {
+            *slot = T::default();
+        }
+        self.length = 0;
+    }
+
     pub fn append(&mut self, item: T) {
-        println!("appending {item:?} to {self:?}");
         if self.inner.len() == self.length {
             self.grow_inner();
         }
         self.inner[self.length] = item;
-        self.length = self.length + 1;
+        self.length += 1;
     }
 
     pub fn pop(&mut self) -> Option<T> {
@@ -61,7 +77,6 @@ impl<T: Debug + Default + Clone + PartialEq> ArrayList<T> {
         }
         let tail = self.length - 1;
         let item = self.inner[tail].clone();
-        println!("removing {item:?} from {self:?}");
         self.inner[tail] = T::default();
         self.length = tail;
         Some(item)
@@ -73,7 +88,7 @@ impl<T: Debug + Default + Clone + PartialEq> ArrayList<T> {
             for i in index..self.length {
                 self.inner[i] = self.inner[i + 1].clone();
             }
-            self.length = self.length - 1;
+            self.length -= 1;
             Some(item)
         } else {
             None
@@ -97,8 +112,15 @@ impl<T: Debug + Default + Clone + PartialEq> ArrayList<T> {
         }
     }
 
+    pub fn get_mut(&mut self, i: usize) -> Option<&mut T> {
+        if i < self.length {
+            Some(&mut self.inner[i])
+        } else {
+            None
+        }
+    }
+
     pub fn insert_at(&mut self, index: usize, item: T) {
-        println!("inserting {item:?} at {index:?} into {self:?}");
         if self.inner.len() == self.length {
             self.grow_inner();
         }
@@ -106,15 +128,100 @@ impl<T: Debug + Default + Clone + PartialEq> ArrayList<T> {
             self.inner[i] = self.inner[i - 1].clone();
         }
         self.inner[index] = item;
-        self.length = self.length + 1;
+        self.length += 1;
     }
 
     pub fn prepend(&mut self, item: T) {
-        println!("prepending {item:?} to {self:?}");
         self.insert_at(0, item);
     }
 }
 
+impl<T: Default + Clone + PartialEq> Collection for ArrayList<T> {
+    fn len(&self) -> usize {
+        self.length
+    }
+
+    fn clear(&mut self) {
+        ArrayList::clear(self)
+    }
+}
+
+// pushes and pops at the end, like append/pop
+impl<T: Default + Clone + PartialEq> Lifo<T> for ArrayList<T> {
+    fn push(&mut self, item: T) {
+        self.append(item)
+    }
+
+    fn pop(&mut self) -> Option<T> {
+        ArrayList::pop(self)
+    }
+}
+
+impl<T: Default + Clone + PartialEq> RandomAccess<T> for ArrayList<T> {
+    fn get(&self, index: usize) -> Option<&T> {
+        ArrayList::get(self, index)
+    }
+
+    fn get_mut(&mut self, index: usize) -> Option<&mut T> {
+        ArrayList::get_mut(self, index)
+    }
+}
+
+impl<T> ArrayList<T> {
+    /// the items in use, without the default padding at the end
+    fn as_slice(&self) -> &[T] {
+        &self.inner[..self.length]
+    }
+}
+
+impl<T: Default + Clone + PartialEq> Default for ArrayList<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Default + Clone + PartialEq> FromIterator<T> for ArrayList<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut l = ArrayList::new();
+        l.extend(iter);
+        l
+    }
+}
+
+impl<T: Default + Clone + PartialEq> Extend<T> for ArrayList<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for item in iter {
+            self.append(item);
+        }
+    }
+}
+
+impl<T: PartialEq> PartialEq for ArrayList<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_slice() == other.as_slice()
+    }
+}
+
+impl<T: Eq> Eq for ArrayList<T> {}
+
+impl<T: Hash> Hash for ArrayList<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.as_slice().hash(state);
+    }
+}
+
+impl<T: PartialOrd> PartialOrd for ArrayList<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        self.as_slice().partial_cmp(other.as_slice())
+    }
+}
+
+impl<T: Ord> Ord for ArrayList<T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.as_slice().cmp(other.as_slice())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -150,4 +257,17 @@ mod tests {
         assert_eq!(None, l.remove(&500));
         assert_eq!(5, l.length);
     }
+
+    #[test]
+    fn items_need_not_be_debug() {
+        #[derive(Default, Clone, PartialEq)]
+        struct Opaque(u8);
+
+        let mut l: ArrayList<Opaque> = (0..7).map(Opaque).collect();
+        l.extend([Opaque(7)]);
+        l.prepend(Opaque(9));
+        assert_eq!(9, l.len());
+        assert!(l.get(0) == Some(&Opaque(9)));
+        assert!(l.remove(&Opaque(3)) == Some(Opaque(3)));
+    }
 }
@@ -1,24 +1,44 @@
 #![allow(dead_code)]
+use std::borrow::Borrow;
+use std::cmp::Ordering;
+use std::collections::VecDeque;
 use std::fmt::Debug;
+use std::hash::{Hash, Hasher};
+use std::iter::{FusedIterator, Peekable};
+use std::mem;
+use std::ops::{Bound, RangeBounds};
+
+use crate::traits::{Collection, OrderedSet};
 
 /**
  * Binary Search Tree
  * sorted
- * Node.left.value < Node.value
- * Node.right.value >= Node.value
- * search, insertion & removal: O(log N)
+ * Node.left.value <= Node.value <= Node.right.value
+ * equal values are inserted to the right, rebalancing may rotate them to the left
+ * search, insertion & removal: O(h) where h is the height of the tree
+ * h is O(log N) for random input but O(N) for sorted input, unless the tree is AVL balanced
+ * none of the operations recurse, so even a chain of N nodes can't overflow the stack
+ * AVL: every Node tracks its height, insert & delete rotate nodes so that the heights of
+ * a Node's subtrees never differ by more than 1, which guarantees h = O(log N)
+ * Order statistics: every Node tracks the size of its subtree,
+ * so select (k-th smallest) & rank (number of values smaller) are O(h) too
+ * Set algebra: union, intersection, difference etc. merge the two in order sequences, O(N + M)
+ * equal values are paired up one to one, e.g. [1, 1, 2] - [1] = [1, 2]
  * Vec: input & output, impl From<Vec<T>>, Into<Vec<T>>,
 */
-#[derive(Clone)]
 pub struct BinarySearchTree<T> {
-    root: Option<Box<Node<T>>>,
+    root: Link<T>,
+    avl: bool,
 }
 
-#[derive(Clone, Debug)]
+type Link<T> = Option<Box<Node<T>>>;
+
 struct Node<T> {
     value: T,
-    left: Option<Box<Node<T>>>,
-    right: Option<Box<Node<T>>>,
+    left: Link<T>,
+    right: Link<T>,
+    height: usize,
+    size: usize,
 }
 
 /*
@@ -33,279 +53,1208 @@ R
 */
 impl<T: Debug + PartialOrd> Debug for BinarySearchTree<T> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        // keep track of what level we're at
-        // print `--` * level
-        let mut level = 0;
-        // need to keep track of level within traverse so general traverse doesn't work
-        self.preorder_traverse(&mut |v| {
-            let r = write!(f, "\n{:->level$}{v:?}", "");
-            level += 1;
-            r
-        });
+        // print `--` * depth of the node
+        let mut iter = self.preorder_iter();
+        while let Some((depth, v)) = iter.next_with_depth() {
+            write!(f, "\n{:->width$}{v:?}", "", width = depth * 2)?;
+        }
         Ok(())
     }
 }
 
-impl<T: PartialOrd> Node<T> {
-    fn insert_child(&mut self, node: Node<T>) {
-        let branch = if node.value < self.value {
-            &mut self.left
-        } else {
-            &mut self.right
-        };
-        if branch.is_none() {
-            branch.replace(Box::new(node));
-        } else {
-            branch.as_mut().unwrap().insert_child(node);
+fn height<T>(link: &Link<T>) -> usize {
+    link.as_ref().map_or(0, |n| n.height)
+}
+
+fn size<T>(link: &Link<T>) -> usize {
+    link.as_ref().map_or(0, |n| n.size)
+}
+
+impl<T> Node<T> {
+    fn new(value: T) -> Self {
+        Node {
+            value,
+            left: None,
+            right: None,
+            height: 1,
+            size: 1,
+        }
+    }
+
+    /// Recalculates height & size from the children
+    fn update(&mut self) {
+        self.height = 1 + height(&self.left).max(height(&self.right));
+        self.size = 1 + size(&self.left) + size(&self.right);
+    }
+
+    /// > 0 when the left subtree is taller, < 0 when the right subtree is
+    fn balance_factor(&self) -> isize {
+        height(&self.left) as isize - height(&self.right) as isize
+    }
+
+    /// Assumes that self.right.is_some()
+    fn rotate_left(mut self: Box<Self>) -> Box<Self> {
+        let mut r = self.right.take().unwrap();
+        self.right = r.left.take();
+        self.update();
+        r.left = Some(self);
+        r.update();
+        r
+    }
+
+    /// Assumes that self.left.is_some()
+    fn rotate_right(mut self: Box<Self>) -> Box<Self> {
+        let mut l = self.left.take().unwrap();
+        self.left = l.right.take();
+        self.update();
+        l.right = Some(self);
+        l.update();
+        l
+    }
+
+    /// Recalculates the height & size of self after one of its subtrees changed,
+    /// and if `avl` rotates self so that its subtrees' heights differ by at most 1.
+    /// Returns the new root of the subtree
+    fn rebalance(mut self: Box<Self>, avl: bool) -> Box<Self> {
+        self.update();
+        if !avl {
+            return self;
+        }
+        let bf = self.balance_factor();
+        if bf > 1 {
+            // left-right case, turn it into a left-left case first
+            if self.left.as_ref().unwrap().balance_factor() < 0 {
+                self.left = self.left.take().map(Node::rotate_left);
+            }
+            self.rotate_right()
+        } else if bf < -1 {
+            // right-left case, turn it into a right-right case first
+            if self.right.as_ref().unwrap().balance_factor() > 0 {
+                self.right = self.right.take().map(Node::rotate_right);
+            }
+            self.rotate_left()
+        } else {
+            self
+        }
+    }
+
+    fn leftmost<'a>(&'a self, stack: &mut Vec<&'a Node<T>>) -> &'a Self {
+        let mut n = self;
+        while let Some(l) = &n.left {
+            stack.push(n);
+            n = l;
+        }
+        n
+    }
+
+    fn rightmost<'a>(&'a self, stack: &mut Vec<&'a Node<T>>) -> &'a Self {
+        let mut n = self;
+        while let Some(r) = &n.right {
+            stack.push(n);
+            n = r;
+        }
+        n
+    }
+
+    /// Removes self from the tree, returns (node replacing self, self.value)
+    // if self has both children, its in order successor takes its place
+    fn unlink(mut self, avl: bool) -> (Link<T>, T) {
+        let replacement = match (self.left.take(), self.right.take()) {
+            (None, None) => None,
+            (Some(l), None) => Some(l),
+            (None, Some(r)) => Some(r),
+            (Some(l), Some(r)) => {
+                let mut right = Some(r);
+                let mut successor = take_min(&mut right, avl).unwrap();
+                successor.left = Some(l);
+                successor.right = right;
+                Some(successor.rebalance(avl))
+            }
+        };
+        (replacement, self.value)
+    }
+}
+
+/// Walks back up from the deepest link in `path` to the root, recalculating heights & sizes and
+/// rebalancing every node on the way, like returning from a recursive insert or delete would.
+/// Safety: every link in `path` must be some and point into the subtree of the link before it
+unsafe fn retrace<T>(path: Vec<*mut Link<T>>, avl: bool) {
+    for link in path.into_iter().rev() {
+        let n = (*link).take().unwrap();
+        *link = Some(n.rebalance(avl));
+    }
+}
+
+/// Removes the leftmost node below link
+fn take_min<T>(link: &mut Link<T>, avl: bool) -> Option<Box<Node<T>>> {
+    let mut path = Vec::new();
+    let mut link: *mut Link<T> = link;
+    unsafe {
+        while (*link).as_ref()?.left.is_some() {
+            path.push(link);
+            link = &mut (*link).as_mut().unwrap().left;
+        }
+        let mut min = (*link).take().unwrap();
+        *link = min.right.take();
+        retrace(path, avl);
+        Some(min)
+    }
+}
+
+/// Removes the rightmost node below link
+fn take_max<T>(link: &mut Link<T>, avl: bool) -> Option<Box<Node<T>>> {
+    let mut path = Vec::new();
+    let mut link: *mut Link<T> = link;
+    unsafe {
+        while (*link).as_ref()?.right.is_some() {
+            path.push(link);
+            link = &mut (*link).as_mut().unwrap().right;
+        }
+        let mut max = (*link).take().unwrap();
+        *link = max.left.take();
+        retrace(path, avl);
+        Some(max)
+    }
+}
+
+/// Frees a subtree without recursion, by rotating left children up until the top node has none
+fn drop_subtree<T>(mut link: Link<T>) {
+    while let Some(mut n) = link {
+        if let Some(mut l) = n.left.take() {
+            n.left = l.right.take();
+            l.right = Some(n);
+            link = Some(l);
+        } else {
+            link = n.right.take();
+        }
+    }
+}
+
+/// Builds a perfectly balanced (so also AVL balanced) tree from the next `n` values of a sorted iterator, O(n).
+/// Recurses, but only log n deep
+fn build_balanced<T, I: Iterator<Item = T>>(values: &mut I, n: usize) -> Link<T> {
+    if n == 0 {
+        return None;
+    }
+    let left = build_balanced(values, n / 2);
+    let mut node = Box::new(Node::new(values.next()?));
+    node.left = left;
+    node.right = build_balanced(values, n - n / 2 - 1);
+    node.update();
+    Some(node)
+}
+
+// the derived drop recurses once per level of the tree, which overflows the stack for long chains
+impl<T> Drop for BinarySearchTree<T> {
+    fn drop(&mut self) {
+        drop_subtree(self.root.take());
+    }
+}
+
+// copies node by node from the top down, the derived clone would recurse once per level as well
+impl<T: Clone> Clone for BinarySearchTree<T> {
+    fn clone(&self) -> Self {
+        let mut root = None;
+        // (node to copy, link to put the copy in)
+        let mut stack: Vec<(&Node<T>, *mut Link<T>)> = Vec::new();
+        if let Some(n) = &self.root {
+            stack.push((n, &mut root));
+        }
+        while let Some((n, link)) = stack.pop() {
+            let copy = Box::new(Node {
+                value: n.value.clone(),
+                left: None,
+                right: None,
+                height: n.height,
+                size: n.size,
+            });
+            // the copies never move on the heap, so links into them stay valid
+            let copy = unsafe { (*link).insert(copy) };
+            if let Some(l) = &n.left {
+                stack.push((l, &mut copy.left));
+            }
+            if let Some(r) = &n.right {
+                stack.push((r, &mut copy.right));
+            }
+        }
+        BinarySearchTree {
+            root,
+            avl: self.avl,
+        }
+    }
+}
+
+impl<T: PartialOrd> Default for BinarySearchTree<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: PartialOrd> BinarySearchTree<T> {
+    pub fn new() -> Self {
+        BinarySearchTree {
+            root: None,
+            avl: false,
+        }
+    }
+
+    /// A tree that rebalances itself on every insert & delete,
+    /// so search, insertion & removal are O(log N) even for sorted input
+    pub fn new_avl() -> Self {
+        BinarySearchTree {
+            root: None,
+            avl: true,
+        }
+    }
+
+    pub fn is_avl(&self) -> bool {
+        self.avl
+    }
+
+    pub fn len(&self) -> usize {
+        size(&self.root)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.root.is_none()
+    }
+
+    /// Removes every value, iteratively like drop
+    pub fn clear(&mut self) {
+        drop_subtree(self.root.take());
+    }
+
+    /// The k-th smallest value, starting at 0, O(h)
+    pub fn select(&self, k: usize) -> Option<&T> {
+        let mut k = k;
+        let mut link = &self.root;
+        while let Some(n) = link {
+            let left = size(&n.left);
+            if k < left {
+                link = &n.left;
+            } else if k == left {
+                return Some(&n.value);
+            } else {
+                k -= left + 1;
+                link = &n.right;
+            }
+        }
+        None
+    }
+
+    /// Number of values < `value`, O(h)
+    pub fn rank<Q>(&self, value: &Q) -> usize
+    where
+        T: Borrow<Q>,
+        Q: PartialOrd + ?Sized,
+    {
+        let mut rank = 0;
+        let mut link = &self.root;
+        while let Some(n) = link {
+            if value <= n.value.borrow() {
+                link = &n.left;
+            } else {
+                rank += size(&n.left) + 1;
+                link = &n.right;
+            }
+        }
+        rank
+    }
+
+    /// Number of nodes on the longest path from the root to a leaf, 0 for an empty tree
+    pub fn height(&self) -> usize {
+        height(&self.root)
+    }
+
+    pub fn insert(&mut self, value: T) {
+        // the links we pass on the way down, to rebalance on the way back up
+        let mut path = Vec::new();
+        let mut link: *mut Link<T> = &mut self.root;
+        unsafe {
+            while let Some(n) = (*link).as_mut() {
+                path.push(link);
+                link = if value < n.value {
+                    &mut n.left
+                } else {
+                    &mut n.right
+                };
+            }
+            *link = Some(Box::new(Node::new(value)));
+            retrace(path, self.avl);
+        }
+    }
+
+    pub fn search(&self, value: T) -> bool {
+        self.contains(&value)
+    }
+
+    pub fn contains<Q>(&self, value: &Q) -> bool
+    where
+        T: Borrow<Q>,
+        Q: PartialOrd + ?Sized,
+    {
+        self.get(value).is_some()
+    }
+
+    /// The stored value equal to `value`, e.g. a `&str` can be used to look up a `String`
+    pub fn get<Q>(&self, value: &Q) -> Option<&T>
+    where
+        T: Borrow<Q>,
+        Q: PartialOrd + ?Sized,
+    {
+        let mut link = &self.root;
+        while let Some(n) = link {
+            if value == n.value.borrow() {
+                return Some(&n.value);
+            }
+            link = if value < n.value.borrow() {
+                &n.left
+            } else {
+                &n.right
+            };
+        }
+        None
+    }
+
+    /// Mutable access to the stored value equal to `value`, for values that carry a payload next to
+    /// the fields they're ordered by. Changing how the value compares to the others is a logic error
+    /// that leaves the tree unsorted
+    pub fn get_mut<Q>(&mut self, value: &Q) -> Option<&mut T>
+    where
+        T: Borrow<Q>,
+        Q: PartialOrd + ?Sized,
+    {
+        let mut link = &mut self.root;
+        while let Some(n) = link {
+            if value == n.value.borrow() {
+                return Some(&mut n.value);
+            }
+            link = if value < n.value.borrow() {
+                &mut n.left
+            } else {
+                &mut n.right
+            };
+        }
+        None
+    }
+
+    /// Returns the stored value, which can differ from `value` if it doesn't compare all fields
+    pub fn delete(&mut self, value: T) -> Option<T> {
+        self.remove(&value)
+    }
+
+    // if deleted node doesn't have children, remove from parent and return value
+    // if it has children, take the node.right.left(most) node and replace it
+    pub fn remove<Q>(&mut self, value: &Q) -> Option<T>
+    where
+        T: Borrow<Q>,
+        Q: PartialOrd + ?Sized,
+    {
+        let mut path = Vec::new();
+        let mut link: *mut Link<T> = &mut self.root;
+        unsafe {
+            loop {
+                let n = (*link).as_mut()?;
+                if value == n.value.borrow() {
+                    break;
+                }
+                path.push(link);
+                link = if value < n.value.borrow() {
+                    &mut n.left
+                } else {
+                    &mut n.right
+                };
+            }
+            let (replacement, deleted) = (*link).take().unwrap().unlink(self.avl);
+            *link = replacement;
+            retrace(path, self.avl);
+            Some(deleted)
+        }
+    }
+
+    /// The smallest value. Trees of Ord values are Ord themselves, and `bst.min()` finds
+    /// Ord::min before this, so call it as `BinarySearchTree::min(&bst)`
+    pub fn min(&self) -> Option<&T> {
+        self.root
+            .as_ref()
+            .map(|n| &n.leftmost(&mut Vec::new()).value)
+    }
+
+    /// The largest value, `BinarySearchTree::max(&bst)` for the same reason as min
+    pub fn max(&self) -> Option<&T> {
+        self.root
+            .as_ref()
+            .map(|n| &n.rightmost(&mut Vec::new()).value)
+    }
+
+    pub fn pop_min(&mut self) -> Option<T> {
+        take_min(&mut self.root, self.avl).map(|n| n.value)
+    }
+
+    pub fn pop_max(&mut self) -> Option<T> {
+        take_max(&mut self.root, self.avl).map(|n| n.value)
+    }
+
+    /// Walks from the root to a leaf, `go_left(v)` tells whether to continue left or right of `v`.
+    /// Returns the last value we went left of, or right of when `candidate_on_left` is false
+    fn closest<F>(&self, go_left: F, candidate_on_left: bool) -> Option<&T>
+    where
+        F: Fn(&T) -> bool,
+    {
+        let mut candidate = None;
+        let mut link = &self.root;
+        while let Some(n) = link {
+            let left = go_left(&n.value);
+            if left == candidate_on_left {
+                candidate = Some(&n.value);
+            }
+            link = if left { &n.left } else { &n.right };
+        }
+        candidate
+    }
+
+    /// Largest value <= `value`
+    pub fn floor<Q>(&self, value: &Q) -> Option<&T>
+    where
+        T: Borrow<Q>,
+        Q: PartialOrd + ?Sized,
+    {
+        self.closest(|v| v.borrow() > value, false)
+    }
+
+    /// Smallest value >= `value`
+    pub fn ceiling<Q>(&self, value: &Q) -> Option<&T>
+    where
+        T: Borrow<Q>,
+        Q: PartialOrd + ?Sized,
+    {
+        self.closest(|v| v.borrow() >= value, true)
+    }
+
+    /// Largest value < `value`
+    pub fn predecessor<Q>(&self, value: &Q) -> Option<&T>
+    where
+        T: Borrow<Q>,
+        Q: PartialOrd + ?Sized,
+    {
+        self.closest(|v| v.borrow() >= value, false)
+    }
+
+    /// Smallest value > `value`
+    pub fn successor<Q>(&self, value: &Q) -> Option<&T>
+    where
+        T: Borrow<Q>,
+        Q: PartialOrd + ?Sized,
+    {
+        self.closest(|v| v.borrow() > value, true)
+    }
+
+    /// Same as `successor`, the smallest value > `value`
+    pub fn first_greater_than<Q>(&self, value: &Q) -> Option<&T>
+    where
+        T: Borrow<Q>,
+        Q: PartialOrd + ?Sized,
+    {
+        self.successor(value)
+    }
+
+    /// In order iterator over the values within `range`, e.g. `bst.range(&3..&7)` or `bst.range(3..)`
+    /// O(log N) to find both ends of the range, then O(1) amortized per value
+    pub fn range<Q, R>(&self, range: R) -> Range<'_, T>
+    where
+        T: Borrow<Q>,
+        Q: PartialOrd + ?Sized,
+        R: RangeBounds<Q>,
+    {
+        // front: top of stack is the smallest value within the range, rest of stack are its ancestors
+        // that are still to be produced, i.e. the nodes we went left at
+        let mut front = Vec::new();
+        let mut link = &self.root;
+        while let Some(n) = link {
+            let in_range = match range.start_bound() {
+                Bound::Included(start) => n.value.borrow() >= start,
+                Bound::Excluded(start) => n.value.borrow() > start,
+                Bound::Unbounded => true,
+            };
+            if in_range {
+                front.push(n.as_ref());
+                link = &n.left;
+            } else {
+                link = &n.right;
+            }
+        }
+        // back: same as front but mirrored
+        let mut back = Vec::new();
+        let mut link = &self.root;
+        while let Some(n) = link {
+            let in_range = match range.end_bound() {
+                Bound::Included(end) => n.value.borrow() <= end,
+                Bound::Excluded(end) => n.value.borrow() < end,
+                Bound::Unbounded => true,
+            };
+            if in_range {
+                back.push(n.as_ref());
+                link = &n.right;
+            } else {
+                link = &n.left;
+            }
+        }
+        // the range is empty if the smallest value past the start is larger than the largest value before the end
+        let done = match (front.last(), back.last()) {
+            (Some(f), Some(b)) => f.value > b.value,
+            _ => true,
+        };
+        Range { front, back, done }
+    }
+
+    /// In order, double ended
+    /// This is O(log N) because we're calculating the first & last nodes to produce in the `iter` method
+    pub fn iter(&self) -> BSTIterator<'_, T> {
+        let mut front = Vec::new();
+        let mut back = Vec::new();
+        if let Some(root) = self.root.as_ref() {
+            let first = root.leftmost(&mut front);
+            front.push(first);
+            let last = root.rightmost(&mut back);
+            back.push(last);
+        }
+        BSTIterator {
+            front,
+            back,
+            len: self.len(),
+        }
+    }
+
+    /// Node, then its left subtree, then its right subtree
+    pub fn preorder_iter(&self) -> PreorderIter<'_, T> {
+        PreorderIter {
+            stack: self.root.iter().map(|n| (0, n.as_ref())).collect(),
+            len: self.len(),
+        }
+    }
+
+    /// Left subtree, then right subtree, then the node, e.g. the order to free nodes in
+    pub fn postorder_iter(&self) -> PostorderIter<'_, T> {
+        PostorderIter {
+            stack: self.root.iter().map(|n| (n.as_ref(), false)).collect(),
+            len: self.len(),
+        }
+    }
+
+    /// Breadth first, level by level from left to right, yields (depth, value) with depth 0 for the root
+    pub fn level_order_iter(&self) -> LevelOrderIter<'_, T> {
+        LevelOrderIter {
+            queue: self.root.iter().map(|n| (0, n.as_ref())).collect(),
+            len: self.len(),
+        }
+    }
+}
+
+// Set algebra
+impl<T: PartialOrd> BinarySearchTree<T> {
+    fn from_sorted(values: Vec<T>, avl: bool) -> Self {
+        let n = values.len();
+        BinarySearchTree {
+            root: build_balanced(&mut values.into_iter(), n),
+            avl,
+        }
+    }
+
+    /// Values in either tree, in order
+    pub fn union<'a>(&'a self, other: &'a Self) -> Union<BSTIterator<'a, T>> {
+        Union(Merge::new(self.iter(), other.iter()))
+    }
+
+    /// Values in both trees, in order
+    pub fn intersection<'a>(&'a self, other: &'a Self) -> Intersection<BSTIterator<'a, T>> {
+        Intersection(Merge::new(self.iter(), other.iter()))
+    }
+
+    /// Values in self but not in other, in order
+    pub fn difference<'a>(&'a self, other: &'a Self) -> Difference<BSTIterator<'a, T>> {
+        Difference(Merge::new(self.iter(), other.iter()))
+    }
+
+    /// Values in exactly one of the trees, in order
+    pub fn symmetric_difference<'a>(
+        &'a self,
+        other: &'a Self,
+    ) -> SymmetricDifference<BSTIterator<'a, T>> {
+        SymmetricDifference(Merge::new(self.iter(), other.iter()))
+    }
+
+    pub fn into_union(self, other: Self) -> Self {
+        let avl = self.avl;
+        let values = Union(Merge::new(self.into_iter(), other.into_iter())).collect();
+        Self::from_sorted(values, avl)
+    }
+
+    pub fn into_intersection(self, other: Self) -> Self {
+        let avl = self.avl;
+        let values = Intersection(Merge::new(self.into_iter(), other.into_iter())).collect();
+        Self::from_sorted(values, avl)
+    }
+
+    pub fn into_difference(self, other: Self) -> Self {
+        let avl = self.avl;
+        let values = Difference(Merge::new(self.into_iter(), other.into_iter())).collect();
+        Self::from_sorted(values, avl)
+    }
+
+    pub fn into_symmetric_difference(self, other: Self) -> Self {
+        let avl = self.avl;
+        let values = SymmetricDifference(Merge::new(self.into_iter(), other.into_iter())).collect();
+        Self::from_sorted(values, avl)
+    }
+
+    pub fn is_subset(&self, other: &Self) -> bool {
+        self.len() <= other.len() && self.difference(other).next().is_none()
+    }
+
+    pub fn is_superset(&self, other: &Self) -> bool {
+        other.is_subset(self)
+    }
+
+    pub fn is_disjoint(&self, other: &Self) -> bool {
+        self.intersection(other).next().is_none()
+    }
+
+    /// Moves every value >= `value` into the returned tree, O(N)
+    pub fn split_off<Q>(&mut self, value: &Q) -> Self
+    where
+        T: Borrow<Q>,
+        Q: PartialOrd + ?Sized,
+    {
+        let mut values: Vec<T> = self.take_iter().collect();
+        let at = values.partition_point(|v| v.borrow() < value);
+        let split = values.split_off(at);
+        *self = Self::from_sorted(values, self.avl);
+        Self::from_sorted(split, self.avl)
+    }
+
+    /// Moves every value of other into self, leaving other empty, O(N + M)
+    pub fn append(&mut self, other: &mut Self) {
+        let mut merge = Merge::new(self.take_iter(), other.take_iter());
+        let mut values = Vec::with_capacity(merge.a.len() + merge.b.len());
+        while let Some(pair) = merge.next() {
+            match pair {
+                Pair::Left(a) => values.push(a),
+                Pair::Right(b) => values.push(b),
+                Pair::Both(a, b) => values.extend([a, b]),
+            }
+        }
+        *self = Self::from_sorted(values, self.avl);
+    }
+
+    /// Empties self into a consuming iterator, leaving an empty tree in the same mode behind
+    fn take_iter(&mut self) -> IntoIter<T> {
+        let avl = self.avl;
+        mem::replace(self, BinarySearchTree { root: None, avl }).into_iter()
+    }
+}
+
+/// Where the smaller head of two sorted iterators came from
+enum Pair<T> {
+    Left(T),
+    Right(T),
+    Both(T, T),
+}
+
+/// Walks two sorted iterators side by side, the base of the set operations
+struct Merge<I: Iterator> {
+    a: Peekable<I>,
+    b: Peekable<I>,
+}
+
+impl<I: Iterator> Merge<I>
+where
+    I::Item: PartialOrd,
+{
+    fn new(a: I, b: I) -> Self {
+        Merge {
+            a: a.peekable(),
+            b: b.peekable(),
+        }
+    }
+
+    fn next(&mut self) -> Option<Pair<I::Item>> {
+        let order = match (self.a.peek(), self.b.peek()) {
+            (None, None) => return None,
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (Some(a), Some(b)) => a.partial_cmp(b).unwrap_or(Ordering::Equal),
+        };
+        Some(match order {
+            Ordering::Less => Pair::Left(self.a.next()?),
+            Ordering::Greater => Pair::Right(self.b.next()?),
+            Ordering::Equal => Pair::Both(self.a.next()?, self.b.next()?),
+        })
+    }
+}
+
+pub struct Union<I: Iterator>(Merge<I>);
+
+impl<I: Iterator> Iterator for Union<I>
+where
+    I::Item: PartialOrd,
+{
+    type Item = I::Item;
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.0.next()? {
+            Pair::Left(v) | Pair::Right(v) | Pair::Both(v, _) => Some(v),
+        }
+    }
+}
+
+impl<I: FusedIterator> FusedIterator for Union<I> where I::Item: PartialOrd {}
+
+pub struct Intersection<I: Iterator>(Merge<I>);
+
+impl<I: Iterator> Iterator for Intersection<I>
+where
+    I::Item: PartialOrd,
+{
+    type Item = I::Item;
+    fn next(&mut self) -> Option<Self::Item> {
+        // nothing left in common once either side runs out
+        while self.0.a.peek().is_some() && self.0.b.peek().is_some() {
+            if let Some(Pair::Both(v, _)) = self.0.next() {
+                return Some(v);
+            }
+        }
+        None
+    }
+}
+
+impl<I: FusedIterator> FusedIterator for Intersection<I> where I::Item: PartialOrd {}
+
+pub struct Difference<I: Iterator>(Merge<I>);
+
+impl<I: Iterator> Iterator for Difference<I>
+where
+    I::Item: PartialOrd,
+{
+    type Item = I::Item;
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.0.a.peek().is_some() {
+            if let Some(Pair::Left(v)) = self.0.next() {
+                return Some(v);
+            }
+        }
+        None
+    }
+}
+
+impl<I: FusedIterator> FusedIterator for Difference<I> where I::Item: PartialOrd {}
+
+pub struct SymmetricDifference<I: Iterator>(Merge<I>);
+
+impl<I: Iterator> Iterator for SymmetricDifference<I>
+where
+    I::Item: PartialOrd,
+{
+    type Item = I::Item;
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match self.0.next()? {
+                Pair::Left(v) | Pair::Right(v) => return Some(v),
+                Pair::Both(..) => {}
+            }
+        }
+    }
+}
+
+impl<I: FusedIterator> FusedIterator for SymmetricDifference<I> where I::Item: PartialOrd {}
+
+/// The front & back stacks each hold the next node to produce on their end at the top,
+/// `len` counts the values left so we know when the two ends meet
+pub struct BSTIterator<'a, T> {
+    front: Vec<&'a Node<T>>,
+    back: Vec<&'a Node<T>>,
+    len: usize,
+}
+
+impl<'a, T> Iterator for BSTIterator<'a, T> {
+    type Item = &'a T;
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.len == 0 {
+            return None;
+        }
+        self.len -= 1;
+        let n = self.front.pop()?;
+        // check if right, then right.leftmost, else the next node is already on the stack
+        if let Some(r) = n.right.as_ref() {
+            let next = r.leftmost(&mut self.front);
+            self.front.push(next);
+        }
+        Some(&n.value)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<'a, T> DoubleEndedIterator for BSTIterator<'a, T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.len == 0 {
+            return None;
+        }
+        self.len -= 1;
+        let n = self.back.pop()?;
+        if let Some(l) = n.left.as_ref() {
+            let next = l.rightmost(&mut self.back);
+            self.back.push(next);
+        }
+        Some(&n.value)
+    }
+}
+
+impl<'a, T> ExactSizeIterator for BSTIterator<'a, T> {}
+
+impl<'a, T> FusedIterator for BSTIterator<'a, T> {}
+
+pub struct PreorderIter<'a, T> {
+    stack: Vec<(usize, &'a Node<T>)>,
+    len: usize,
+}
+
+impl<'a, T> PreorderIter<'a, T> {
+    fn next_with_depth(&mut self) -> Option<(usize, &'a T)> {
+        let (depth, n) = self.stack.pop()?;
+        self.len -= 1;
+        // right first so left comes off the stack first
+        if let Some(r) = n.right.as_ref() {
+            self.stack.push((depth + 1, r));
+        }
+        if let Some(l) = n.left.as_ref() {
+            self.stack.push((depth + 1, l));
+        }
+        Some((depth, &n.value))
+    }
+}
+
+impl<'a, T> Iterator for PreorderIter<'a, T> {
+    type Item = &'a T;
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_with_depth().map(|(_, v)| v)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<'a, T> ExactSizeIterator for PreorderIter<'a, T> {}
+
+impl<'a, T> FusedIterator for PreorderIter<'a, T> {}
+
+/// Nodes on the stack are flagged once their children have been pushed on top of them
+pub struct PostorderIter<'a, T> {
+    stack: Vec<(&'a Node<T>, bool)>,
+    len: usize,
+}
+
+impl<'a, T> Iterator for PostorderIter<'a, T> {
+    type Item = &'a T;
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (n, children_pushed) = self.stack.pop()?;
+            if children_pushed {
+                self.len -= 1;
+                return Some(&n.value);
+            }
+            self.stack.push((n, true));
+            if let Some(r) = n.right.as_ref() {
+                self.stack.push((r, false));
+            }
+            if let Some(l) = n.left.as_ref() {
+                self.stack.push((l, false));
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<'a, T> ExactSizeIterator for PostorderIter<'a, T> {}
+
+impl<'a, T> FusedIterator for PostorderIter<'a, T> {}
+
+pub struct LevelOrderIter<'a, T> {
+    queue: VecDeque<(usize, &'a Node<T>)>,
+    len: usize,
+}
+
+impl<'a, T> Iterator for LevelOrderIter<'a, T> {
+    type Item = (usize, &'a T);
+    fn next(&mut self) -> Option<Self::Item> {
+        let (depth, n) = self.queue.pop_front()?;
+        self.len -= 1;
+        if let Some(l) = n.left.as_ref() {
+            self.queue.push_back((depth + 1, l));
+        }
+        if let Some(r) = n.right.as_ref() {
+            self.queue.push_back((depth + 1, r));
+        }
+        Some((depth, &n.value))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<'a, T> ExactSizeIterator for LevelOrderIter<'a, T> {}
+
+impl<'a, T> FusedIterator for LevelOrderIter<'a, T> {}
+
+/// Consuming in order iterator, every node on the stack still has to be produced, followed by its right subtree
+pub struct IntoIter<T> {
+    stack: Vec<Box<Node<T>>>,
+    len: usize,
+}
+
+impl<T> IntoIter<T> {
+    fn push_left(&mut self, mut link: Link<T>) {
+        while let Some(mut n) = link {
+            link = n.left.take();
+            self.stack.push(n);
         }
     }
+}
 
-    /// Assumes that self.list.is_some()
-    fn replace_left_with_left_child(&mut self) -> Option<Box<Self>> {
-        if self.left.as_mut().unwrap().left.is_some() {
-            let l = self.left.as_mut().unwrap().left.take().unwrap();
-            self.left.replace(l)
-        } else {
-            self.left.take()
-        }
+impl<T> Iterator for IntoIter<T> {
+    type Item = T;
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut n = self.stack.pop()?;
+        self.len -= 1;
+        self.push_left(n.right.take());
+        Some(n.value)
     }
 
-    /// Assumes that self.right.is_some()
-    fn replace_right_with_child(&mut self) -> Option<Box<Self>> {
-        if let Some(left_child) = self.right.as_mut().unwrap().left.take() {
-            self.right.replace(left_child)
-        } else if let Some(right_child) = self.right.as_mut().unwrap().right.take() {
-            self.right.replace(right_child)
-        } else {
-            self.right.take()
-        }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
     }
+}
 
-    /// bad name, not what it does really
-    fn recur_right(&mut self) -> Option<Box<Self>> {
-        if let Some(n) = &mut self.right {
-            if n.right.is_some() {
-                n.recur_right()
-            } else {
-                self.replace_right_with_child()
-            }
-        } else {
-            None
+impl<T> Drop for IntoIter<T> {
+    fn drop(&mut self) {
+        for mut n in self.stack.drain(..) {
+            drop_subtree(n.right.take());
         }
     }
+}
 
-    fn leftmost<'a>(&'a self, stack: &mut Vec<&'a Node<T>>) -> &Self {
-        if let Some(n) = &self.left {
-            stack.push(self);
-            n.leftmost(stack)
-        } else {
-            self
-        }
-    }
+impl<T> ExactSizeIterator for IntoIter<T> {}
 
-    fn swap_node(&mut self) -> Option<&mut Self> {
-        let swap_node = if let Some(left) = &mut self.left {
-            let rightmost = left.recur_right();
-            if rightmost.is_some() {
-                rightmost
-            } else {
-                self.replace_left_with_left_child()
-            }
-        } else if self.right.is_some() {
-            self.replace_right_with_child()
-        } else {
-            None
-        };
+impl<T> FusedIterator for IntoIter<T> {}
 
-        if let Some(swap_n) = swap_node {
-            self.value = swap_n.value;
-            Some(self)
-        } else {
-            None
-        }
+impl<T: PartialOrd> IntoIterator for BinarySearchTree<T> {
+    type Item = T;
+    type IntoIter = IntoIter<T>;
+    fn into_iter(mut self) -> Self::IntoIter {
+        let mut iter = IntoIter {
+            stack: Vec::new(),
+            len: self.len(),
+        };
+        iter.push_left(self.root.take());
+        iter
     }
+}
 
-    // this doesn't work for deleting root node
-    fn delete_from(&mut self, value: T) -> Option<T> {
-        if value < self.value {
-            match self.left.as_mut() {
-                None => None,
-                Some(n) if value == n.value => {
-                    if n.swap_node().is_none() {
-                        self.left = None;
-                    }
-                    Some(value)
-                },
-                Some(n) => n.delete_from(value),
-            }
-        } else {
-            match self.right.as_mut() {
-                None => None,
-                Some(n) if value == n.value => {
-                    if n.swap_node().is_none() {
-                        self.right = None;
-                    }
-                    Some(value)
-                }
-                Some(n) => n.delete_from(value),
-            }
-        }
+impl<'a, T: PartialOrd> IntoIterator for &'a BinarySearchTree<T> {
+    type Item = &'a T;
+    type IntoIter = BSTIterator<'a, T>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
     }
+}
 
-    fn search(&self, value: T) -> Option<&Self> {
-        if value == self.value {
-            Some(self)
-        } else if value < self.value {
-            self.left.as_ref().and_then(|n| n.search(value))
-        } else {
-            self.right.as_ref().and_then(|n| n.search(value))
+/// Double ended, the front & back stacks each hold the next node to produce on their end at the top
+pub struct Range<'a, T> {
+    front: Vec<&'a Node<T>>,
+    back: Vec<&'a Node<T>>,
+    done: bool,
+}
+
+impl<'a, T> Iterator for Range<'a, T> {
+    type Item = &'a T;
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let n = self.front.pop()?;
+        // front & back met at the last node in the range
+        if std::ptr::eq(n, *self.back.last().unwrap()) {
+            self.done = true;
+        } else if let Some(r) = n.right.as_ref() {
+            let next = r.leftmost(&mut self.front);
+            self.front.push(next);
         }
+        Some(&n.value)
     }
+}
 
-    /// In order traversal applying f to self.value, consuming self
-    fn traverse<F>(mut self, f: &mut F)
-    where F: FnMut(T),
-    {
-        if let Some(l) = self.left.take() {
-            l.traverse(f);
-        }
-        let right = self.right.take();
-        f(self.value);
+impl<'a, T> FusedIterator for Range<'a, T> {}
 
-        if let Some(r) = right {
-            r.traverse(f);
+impl<'a, T> DoubleEndedIterator for Range<'a, T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let n = self.back.pop()?;
+        if std::ptr::eq(n, *self.front.last().unwrap()) {
+            self.done = true;
+        } else if let Some(l) = n.left.as_ref() {
+            let next = l.rightmost(&mut self.back);
+            self.back.push(next);
         }
+        Some(&n.value)
     }
+}
 
-    fn preorder_traverse<F, K>(&self, f: &mut F)
-    where F: FnMut(&T) -> K,
-    {
-        f(&self.value);
+impl<T: PartialOrd> From<BinarySearchTree<T>> for Vec<T> {
+    fn from(bst: BinarySearchTree<T>) -> Self {
+        bst.into_iter().collect()
+    }
+}
 
-        if let Some(l) = self.left.as_ref() {
-            l.preorder_traverse(f);
+// balanced, since the vec may well be sorted
+impl<T: PartialOrd + Debug> From<Vec<T>> for BinarySearchTree<T> {
+    fn from(vec: Vec<T>) -> Self {
+        let mut bst = BinarySearchTree::<T>::new_avl();
+        for v in vec {
+            bst.insert(v);
         }
+        bst
+    }
+}
 
-        if let Some(r) = self.right.as_ref() {
-            r.preorder_traverse(f);
-        }
+impl<T: PartialOrd> Collection for BinarySearchTree<T> {
+    fn len(&self) -> usize {
+        BinarySearchTree::len(self)
+    }
+
+    fn clear(&mut self) {
+        BinarySearchTree::clear(self)
     }
 }
 
-impl<T: PartialOrd> BinarySearchTree<T> {
-    pub fn new() -> Self {
-        BinarySearchTree { root: None }
+impl<T: PartialOrd> OrderedSet<T> for BinarySearchTree<T> {
+    fn insert(&mut self, value: T) {
+        BinarySearchTree::insert(self, value)
     }
 
-    pub fn insert(&mut self, value: T) {
-        let node = Node {
-            value,
-            left: None,
-            right: None,
-        };
+    fn contains(&self, value: &T) -> bool {
+        BinarySearchTree::contains(self, value)
+    }
 
-        if self.root.is_none() {
-            self.root = Some(Box::new(node));
-        } else {
-            self.root.as_mut().unwrap().insert_child(node);
-        }
+    fn remove(&mut self, value: &T) -> Option<T> {
+        BinarySearchTree::remove(self, value)
     }
 
-    pub fn search(&self, value: T) -> bool {
-        self.root.as_ref().and_then(|n| n.search(value)).is_some()
+    fn min(&self) -> Option<&T> {
+        BinarySearchTree::min(self)
     }
 
-    // if deleted node doesn't have children, remove from parent and return value
-    // if it has children, take the node.left.right(most) node and replace it
-    pub fn delete(&mut self, value: T) -> Option<T> {
-        match self.root.as_mut() {
-            None => None,
-            Some(n) if value == n.value => {
-                if n.swap_node().is_none() {
-                    self.root = None;
-                }
-                Some(value)
-            },
-            Some(n) => n.delete_from(value),
-        }
+    fn max(&self) -> Option<&T> {
+        BinarySearchTree::max(self)
     }
 
-    /// In order traversal applying `f` to each `T` consuming `self`
-    fn traverse<F>(mut self, f: &mut F)
-    where F: FnMut(T),
-    {
-        if let Some(n) = self.root.take() {
-            n.traverse(f);
-        }
+    fn pop_min(&mut self) -> Option<T> {
+        BinarySearchTree::pop_min(self)
     }
 
-    fn preorder_traverse<F, K>(&self, f: &mut F)
-    where F: FnMut(&T) -> K,
-    {
-        if let Some(n) = self.root.as_ref() {
-            n.preorder_traverse(f);
-        }
+    fn pop_max(&mut self) -> Option<T> {
+        BinarySearchTree::pop_max(self)
     }
+}
 
-    /* This is O(log N) because we're calculating the first node to produce in the `iter` method
-     * If we wanted to make this more efficient, we could add `root` and `initial` fields
-     * to our BSTIterator and caclulate the first `current` in the first call of `next`
-     */
-    pub fn iter<'a>(&'a self) -> BSTIterator<'a, T> {
-        let mut stack = Vec::new();
-        let current = self.root.as_ref().map(|n| n.leftmost(&mut stack));
-        BSTIterator {
-            stack,
-            current,
+impl<T: PartialOrd> FromIterator<T> for BinarySearchTree<T> {
+    /// balanced, like `From<Vec<T>>`
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut bst = BinarySearchTree::new_avl();
+        bst.extend(iter);
+        bst
+    }
+}
+
+impl<T: PartialOrd> Extend<T> for BinarySearchTree<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for v in iter {
+            self.insert(v);
         }
     }
 }
 
-pub struct BSTIterator<'a, T> {
-    stack: Vec<&'a Node<T>>,
-    current: Option<&'a Node<T>>,
+// comparisons go in order, so trees holding the same values are equal whatever their shape or mode
+impl<T: PartialOrd> PartialEq for BinarySearchTree<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len() && self.iter().eq(other.iter())
+    }
 }
 
-impl<'a, T: PartialOrd> Iterator for BSTIterator<'a, T> {
-    type Item = &'a T;
-    fn next(&mut self) -> Option<Self::Item> {
-        if let Some(n) = self.current {
-            let v = &n.value;
-            // check if right, then right.leftmost, else right
-            if let Some(r) = n.right.as_ref() {
-                self.current = Some(r.leftmost(&mut self.stack));
-            } else {
-                // go up stack
-                self.current = self.stack.pop();
-            }
-            Some(v)
-        } else {
-            None
+impl<T: Ord> Eq for BinarySearchTree<T> {}
+
+impl<T: PartialOrd + Hash> Hash for BinarySearchTree<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.len().hash(state);
+        for v in self.iter() {
+            v.hash(state);
         }
     }
 }
 
-impl<T: PartialOrd> From<BinarySearchTree<T>> for Vec<T> {
-    fn from(bst: BinarySearchTree<T>) -> Self {
-        let mut vec = Vec::new();
-        bst.traverse(&mut |v: T| vec.push(v));
-        vec
+impl<T: PartialOrd> PartialOrd for BinarySearchTree<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        self.iter().partial_cmp(other.iter())
     }
 }
 
-impl<T: PartialOrd + Debug> From<Vec<T>> for BinarySearchTree<T> {
-    fn from(vec: Vec<T>) -> Self {
-        let mut bst = BinarySearchTree::<T>::new();
-        for v in vec {
-            bst.insert(v);
-        }
-        bst
+impl<T: Ord> Ord for BinarySearchTree<T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.iter().cmp(other.iter())
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_util::Rng;
+    use std::collections::BTreeSet;
+
+    /// Asserts sizes are correct, returns the size of the node
+    fn check_sizes<T>(link: &Link<T>) -> usize {
+        match link {
+            None => 0,
+            Some(n) => {
+                assert_eq!(n.size, 1 + check_sizes(&n.left) + check_sizes(&n.right));
+                n.size
+            }
+        }
+    }
+
+    /// Asserts heights are correct & every node is balanced, returns the height of the node
+    fn check_avl<T: PartialOrd>(link: &Link<T>) -> usize {
+        match link {
+            None => 0,
+            Some(n) => {
+                if let Some(l) = &n.left {
+                    assert!(l.value <= n.value);
+                }
+                if let Some(r) = &n.right {
+                    assert!(r.value >= n.value);
+                }
+                let (l, r) = (check_avl(&n.left), check_avl(&n.right));
+                assert!(l.abs_diff(r) <= 1, "unbalanced node");
+                assert_eq!(n.height, 1 + l.max(r));
+                n.height
+            }
+        }
+    }
 
     #[test]
     fn iterator_works() {
@@ -390,4 +1339,614 @@ mod tests {
         assert_eq!(Some(10), bst.delete(10));
         dbg!(&bst);
     }
+
+    #[test]
+    fn avl_sorted_input_stays_balanced() {
+        let n = if cfg!(miri) { 1_000 } else { 300_000 };
+        let mut bst = BinarySearchTree::<usize>::new_avl();
+        for i in 0..n {
+            bst.insert(i);
+        }
+        // an AVL tree is at most ~1.44 * log2(N) high
+        assert!(bst.height() as f64 <= 1.45 * (n as f64).log2());
+        check_avl(&bst.root);
+        assert!(bst.search(0));
+        assert!(bst.search(n - 1));
+        for i in (0..n).step_by(2) {
+            assert_eq!(Some(i), bst.delete(i));
+        }
+        check_avl(&bst.root);
+        assert!(bst.iter().copied().eq((1..n).step_by(2)));
+
+        let bst = BinarySearchTree::from((0..n).rev().collect::<Vec<_>>());
+        assert!(bst.is_avl());
+        check_avl(&bst.root);
+    }
+
+    #[test]
+    fn avl_matches_btreeset() {
+        let mut rng = Rng(0x2545F4914F6CDD1D);
+        let mut bst = BinarySearchTree::<u64>::new_avl();
+        let mut set = BTreeSet::new();
+        for round in 0..if cfg!(miri) { 500 } else { 20_000usize } {
+            let v = rng.next() % 1000;
+            if rng.next().is_multiple_of(3) {
+                assert_eq!(set.take(&v), bst.delete(v));
+            } else if set.insert(v) {
+                bst.insert(v);
+            }
+            assert_eq!(set.contains(&v), bst.search(v));
+            if round.is_multiple_of(500) {
+                check_avl(&bst.root);
+                assert!(bst.iter().eq(set.iter()));
+            }
+        }
+        check_avl(&bst.root);
+        assert!(bst.iter().eq(set.iter()));
+        assert!(bst.height() as f64 <= 1.45 * (set.len() as f64).log2() + 2.0);
+    }
+
+    #[test]
+    fn avl_duplicates_work() {
+        let mut rng = Rng(88172645463325252);
+        let mut bst = BinarySearchTree::<u64>::new_avl();
+        let mut expected = Vec::new();
+        let n = if cfg!(miri) { 250 } else { 5000 };
+        for _ in 0..n {
+            let v = rng.next() % 50;
+            bst.insert(v);
+            expected.push(v);
+        }
+        for _ in 0..n * 2 / 5 {
+            let v = rng.next() % 60;
+            let i = expected.iter().position(|&e| e == v);
+            assert_eq!(i.map(|i| expected.swap_remove(i)), bst.delete(v));
+        }
+        check_avl(&bst.root);
+        expected.sort();
+        assert_eq!(expected, Vec::from(bst));
+    }
+
+    #[test]
+    fn range_works() {
+        for bst in [
+            BinarySearchTree::from(vec![9, 5, 4, 1, 4, 6, 8, 17, 900, 800]),
+            {
+                let mut bst = BinarySearchTree::new();
+                for v in [9, 5, 4, 1, 4, 6, 8, 17, 900, 800] {
+                    bst.insert(v);
+                }
+                bst
+            },
+        ] {
+            let sorted: Vec<usize> = bst.iter().copied().collect();
+            let check = |r: (Bound<usize>, Bound<usize>)| {
+                let expected: Vec<&usize> = sorted.iter().filter(|v| r.contains(v)).collect();
+                assert_eq!(expected, bst.range(r).collect::<Vec<_>>());
+                let mut rev: Vec<&usize> = bst.range(r).rev().collect();
+                rev.reverse();
+                assert_eq!(expected, rev);
+            };
+            for a in 0..20 {
+                for b in a..20 {
+                    check((Bound::Included(a), Bound::Excluded(b)));
+                    check((Bound::Excluded(a), Bound::Included(b)));
+                }
+                check((Bound::Included(a), Bound::Unbounded));
+                check((Bound::Unbounded, Bound::Included(a)));
+            }
+            check((Bound::Unbounded, Bound::Unbounded));
+            check((Bound::Included(10), Bound::Included(2)));
+
+            assert!(bst.range(4..=800).copied().eq([4, 4, 5, 6, 8, 9, 17, 800]));
+            let mut r = bst.range(2..=17);
+            assert_eq!(Some(&4), r.next());
+            assert_eq!(Some(&17), r.next_back());
+            assert_eq!(Some(&9), r.next_back());
+            assert_eq!(Some(&4), r.next());
+            assert_eq!(Some(&5), r.next());
+            assert_eq!(Some(&8), r.next_back());
+            assert_eq!(Some(&6), r.next());
+            assert_eq!(None, r.next());
+            assert_eq!(None, r.next_back());
+        }
+
+        let empty = BinarySearchTree::<usize>::new();
+        assert_eq!(None, empty.range(..).next());
+        assert_eq!(None, empty.range(..).next_back());
+    }
+
+    #[test]
+    fn navigation_works() {
+        let mut bst = BinarySearchTree::new();
+        assert_eq!(None, BinarySearchTree::min(&bst));
+        assert_eq!(None, bst.floor(&10));
+        assert_eq!(None, bst.pop_max());
+        for v in [50, 20, 80, 20, 10, 60, 90, 70] {
+            bst.insert(v);
+        }
+        assert_eq!(Some(&10), BinarySearchTree::min(&bst));
+        assert_eq!(Some(&90), BinarySearchTree::max(&bst));
+
+        assert_eq!(Some(&20), bst.floor(&20));
+        assert_eq!(Some(&20), bst.floor(&49));
+        assert_eq!(None, bst.floor(&9));
+        assert_eq!(Some(&20), bst.ceiling(&20));
+        assert_eq!(Some(&50), bst.ceiling(&21));
+        assert_eq!(None, bst.ceiling(&91));
+
+        assert_eq!(Some(&10), bst.predecessor(&20));
+        assert_eq!(Some(&80), bst.predecessor(&90));
+        assert_eq!(None, bst.predecessor(&10));
+        assert_eq!(Some(&50), bst.successor(&20));
+        assert_eq!(Some(&60), bst.first_greater_than(&50));
+        assert_eq!(None, bst.successor(&90));
+
+        assert_eq!(Some(10), bst.pop_min());
+        assert_eq!(Some(20), bst.pop_min());
+        assert_eq!(Some(90), bst.pop_max());
+        assert_eq!(Some(80), bst.pop_max());
+        assert_eq!(vec![20, 50, 60, 70], Vec::from(bst));
+    }
+
+    #[test]
+    fn avl_pop_min_max_stay_balanced() {
+        let mut bst = BinarySearchTree::new_avl();
+        for v in 0..1000 {
+            bst.insert(v);
+        }
+        for v in 0..300 {
+            assert_eq!(Some(v), bst.pop_min());
+            assert_eq!(Some(999 - v), bst.pop_max());
+            check_avl(&bst.root);
+        }
+        assert!(bst.range(..).copied().eq(300..700));
+    }
+
+    #[test]
+    fn select_rank_work() {
+        let mut bst = BinarySearchTree::new();
+        assert_eq!(0, bst.len());
+        assert!(bst.is_empty());
+        assert_eq!(None, bst.select(0));
+        assert_eq!(0, bst.rank(&5));
+        for v in [9, 5, 4, 1, 4, 6, 8, 17, 900, 800, 4] {
+            bst.insert(v);
+        }
+        // 1, 4, 4, 4, 5, 6, 8, 9, 17, 800, 900
+        assert_eq!(11, bst.len());
+        assert_eq!(Some(&1), bst.select(0));
+        assert_eq!(Some(&4), bst.select(1));
+        assert_eq!(Some(&4), bst.select(3));
+        assert_eq!(Some(&5), bst.select(4));
+        assert_eq!(Some(&900), bst.select(10));
+        assert_eq!(None, bst.select(11));
+        assert_eq!(0, bst.rank(&1));
+        assert_eq!(1, bst.rank(&4));
+        assert_eq!(4, bst.rank(&5));
+        assert_eq!(11, bst.rank(&1000));
+        bst.delete(4);
+        assert_eq!(10, bst.len());
+        assert_eq!(Some(&4), bst.select(2));
+        assert_eq!(Some(&5), bst.select(3));
+        assert_eq!(3, bst.rank(&5));
+        check_sizes(&bst.root);
+    }
+
+    #[test]
+    fn select_rank_match_sorted_vec() {
+        let mut rng = Rng(0x853C49E6748FEA9B);
+        for mut bst in [BinarySearchTree::new(), BinarySearchTree::new_avl()] {
+            let mut expected: Vec<u64> = Vec::new();
+            for round in 0..if cfg!(miri) { 300 } else { 5000usize } {
+                let v = rng.next() % 200;
+                match rng.next() % 4 {
+                    0 => {
+                        let i = expected.iter().position(|&e| e == v);
+                        assert_eq!(i.map(|i| expected.remove(i)), bst.delete(v));
+                    }
+                    1 => assert_eq!(
+                        (!expected.is_empty()).then(|| expected.remove(0)),
+                        bst.pop_min()
+                    ),
+                    _ => {
+                        let i = expected.partition_point(|&e| e <= v);
+                        expected.insert(i, v);
+                        bst.insert(v);
+                    }
+                }
+                assert_eq!(expected.len(), bst.len());
+                let k = (rng.next() % 250) as usize;
+                assert_eq!(expected.get(k), bst.select(k));
+                assert_eq!(expected.partition_point(|&e| e < v), bst.rank(&v));
+                if round.is_multiple_of(250) {
+                    check_sizes(&bst.root);
+                }
+            }
+            check_sizes(&bst.root);
+        }
+    }
+
+    /// Ordered by id only, so the payload can be looked up & changed through the tree
+    #[derive(Debug)]
+    struct Task {
+        id: u32,
+        name: &'static str,
+    }
+
+    impl PartialEq for Task {
+        fn eq(&self, other: &Self) -> bool {
+            self.id == other.id
+        }
+    }
+
+    impl PartialOrd for Task {
+        fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+            self.id.partial_cmp(&other.id)
+        }
+    }
+
+    impl Borrow<u32> for Task {
+        fn borrow(&self) -> &u32 {
+            &self.id
+        }
+    }
+
+    #[test]
+    fn borrowed_lookups_work() {
+        let mut bst = BinarySearchTree::new_avl();
+        for s in ["hello", "hey", "hola", "gutentag", "ohaiyo"] {
+            bst.insert(s.to_string());
+        }
+        assert_eq!(Some(&"hey".to_string()), bst.get("hey"));
+        assert!(bst.contains("hola"));
+        assert!(!bst.contains("sup"));
+        assert_eq!(Some("hola".to_string()), bst.remove("hola"));
+        assert_eq!(None, bst.remove("hola"));
+        assert_eq!(Some(&"hey".to_string()), bst.floor("hi"));
+        assert_eq!(1, bst.rank("hello"));
+        assert!(bst
+            .range::<str, _>((Bound::Included("h"), Bound::Excluded("hf")))
+            .eq(["hello", "hey"]));
+    }
+
+    #[test]
+    fn get_mut_and_delete_return_stored_values() {
+        let mut bst = BinarySearchTree::new();
+        bst.insert(Task {
+            id: 2,
+            name: "write",
+        });
+        bst.insert(Task {
+            id: 1,
+            name: "plan",
+        });
+        bst.insert(Task {
+            id: 3,
+            name: "ship",
+        });
+        assert_eq!("plan", bst.get(&1).unwrap().name);
+        bst.get_mut(&3).unwrap().name = "release";
+        assert_eq!(None, bst.get_mut(&4).map(|t| t.name));
+
+        let deleted = bst.delete(Task { id: 3, name: "" }).unwrap();
+        assert_eq!("release", deleted.name);
+        assert_eq!("write", bst.remove(&2).unwrap().name);
+        assert!(bst.iter().map(|t| t.id).eq([1]));
+    }
+
+    /// ```text
+    ///        50
+    ///    30      70
+    ///  20  40  60  80
+    ///        45
+    /// ```
+    fn example() -> BinarySearchTree<usize> {
+        let mut bst = BinarySearchTree::new();
+        for v in [50, 30, 70, 20, 40, 60, 80, 45] {
+            bst.insert(v);
+        }
+        bst
+    }
+
+    #[test]
+    fn traversal_orders_work() {
+        let bst = example();
+        assert!(bst
+            .preorder_iter()
+            .copied()
+            .eq([50, 30, 20, 40, 45, 70, 60, 80]));
+        assert!(bst
+            .postorder_iter()
+            .copied()
+            .eq([20, 45, 40, 30, 60, 80, 70, 50]));
+        let levels: Vec<(usize, usize)> = bst.level_order_iter().map(|(d, v)| (d, *v)).collect();
+        assert_eq!(
+            vec![
+                (0, 50),
+                (1, 30),
+                (1, 70),
+                (2, 20),
+                (2, 40),
+                (2, 60),
+                (2, 80),
+                (3, 45)
+            ],
+            levels
+        );
+        assert_eq!(
+            "\n50\n--30\n----20\n----40\n------45\n--70\n----60\n----80",
+            format!("{bst:?}")
+        );
+
+        let mut pre = bst.preorder_iter();
+        let mut post = bst.postorder_iter();
+        let mut level = bst.level_order_iter();
+        assert_eq!((8, 8, 8), (pre.len(), post.len(), level.len()));
+        pre.nth(2);
+        post.nth(6);
+        level.nth(7);
+        assert_eq!((5, 1, 0), (pre.len(), post.len(), level.len()));
+        assert_eq!(Some(&50), post.next());
+        assert_eq!((None, None), (post.next(), level.next()));
+
+        let empty = BinarySearchTree::<usize>::new();
+        assert_eq!(None, empty.preorder_iter().next());
+        assert_eq!(None, empty.postorder_iter().next());
+        assert_eq!(None, empty.level_order_iter().next());
+        assert_eq!("", format!("{empty:?}"));
+    }
+
+    #[test]
+    fn double_ended_iter_works() {
+        let bst = example();
+        assert!(bst
+            .iter()
+            .rev()
+            .copied()
+            .eq([80, 70, 60, 50, 45, 40, 30, 20]));
+        let mut iter = bst.iter();
+        assert_eq!(8, iter.len());
+        assert_eq!(Some(&20), iter.next());
+        assert_eq!(Some(&80), iter.next_back());
+        assert_eq!(Some(&70), iter.next_back());
+        assert_eq!(Some(&30), iter.next());
+        assert_eq!(4, iter.len());
+        assert!(iter.by_ref().copied().eq([40, 45, 50, 60]));
+        assert_eq!(None, iter.next());
+        assert_eq!(None, iter.next_back());
+
+        let mut rng = Rng(0xDEADBEEF);
+        let mut bst = BinarySearchTree::new_avl();
+        let mut expected: Vec<u64> = (0..500).map(|_| rng.next() % 100).collect();
+        for v in &expected {
+            bst.insert(*v);
+        }
+        expected.sort();
+        let mut iter = bst.iter();
+        let (mut front, mut back) = (0, expected.len());
+        while front < back {
+            if rng.next().is_multiple_of(2) {
+                assert_eq!(Some(&expected[front]), iter.next());
+                front += 1;
+            } else {
+                back -= 1;
+                assert_eq!(Some(&expected[back]), iter.next_back());
+            }
+            assert_eq!(back - front, iter.len());
+        }
+        assert_eq!(None, iter.next());
+    }
+
+    #[test]
+    fn into_iter_works() {
+        let bst = example();
+        let mut iter = bst.clone().into_iter();
+        assert_eq!(8, iter.len());
+        assert_eq!(Some(20), iter.next());
+        assert_eq!(Some(30), iter.next());
+        assert_eq!(6, iter.len());
+        // drop with nodes left
+        drop(iter);
+
+        let mut v = Vec::new();
+        for x in &bst {
+            v.push(*x);
+        }
+        assert!(bst.into_iter().eq(v));
+
+        let strings =
+            BinarySearchTree::from(vec!["b".to_string(), "c".to_string(), "a".to_string()]);
+        assert_eq!(vec!["a", "b", "c"], strings.into_iter().collect::<Vec<_>>());
+    }
+
+    /// The degenerate tree inserting 0..n in order builds, without the O(N^2) inserts.
+    /// `descending` builds the tree inserting n-1..=0 would, a chain of left children
+    fn chain(n: usize, descending: bool) -> BinarySearchTree<usize> {
+        let mut root = None;
+        for i in 0..n {
+            let v = if descending { i } else { n - 1 - i };
+            let mut node = Box::new(Node::new(v));
+            if descending {
+                node.left = root;
+            } else {
+                node.right = root;
+            }
+            node.update();
+            root = Some(node);
+        }
+        BinarySearchTree { root, avl: false }
+    }
+
+    #[test]
+    fn long_chain_operations_do_not_recurse() {
+        // Miri is far too slow for a million nodes, a thousand still walks the chain
+        let n = if cfg!(miri) { 1_000 } else { 1_000_000 };
+        let mut bst = chain(n, false);
+        assert_eq!(n, bst.height());
+        bst.insert(n);
+        assert!(bst.search(n));
+        assert!(bst.contains(&(n / 2)));
+        assert_eq!(Some(&n), BinarySearchTree::max(&bst));
+        assert_eq!(Some(&(n - 1)), bst.predecessor(&n));
+        assert_eq!(n, bst.rank(&n));
+        assert_eq!(Some(&(n - 1)), bst.select(n - 1));
+        assert_eq!(Some(n / 2), bst.delete(n / 2));
+        assert_eq!(Some(n), bst.pop_max());
+        assert_eq!(Some(0), bst.pop_min());
+        assert_eq!(n - 2, bst.len());
+        assert_eq!(n - 2, bst.iter().rev().count());
+        assert_eq!(n - 2, bst.range(1..n).count());
+        assert_eq!(n - 2, bst.preorder_iter().count());
+        assert_eq!(n - 2, bst.postorder_iter().count());
+        assert_eq!(n - 2, bst.level_order_iter().count());
+
+        let copy = bst.clone();
+        assert_eq!(n - 2, copy.height());
+        let mut iter = copy.into_iter();
+        assert_eq!(Some(1), iter.next());
+        drop(iter);
+
+        let mut bst = chain(n, true);
+        bst.insert(0);
+        assert_eq!(Some(0), bst.delete(0));
+        assert_eq!(Some(n - 1), bst.delete(n - 1));
+        assert_eq!(Some(&1), bst.iter().nth(1));
+        let v: Vec<usize> = bst.into_iter().collect();
+        assert_eq!(n - 1, v.len());
+    }
+
+    #[test]
+    fn dropping_10m_node_chain_does_not_overflow() {
+        let n = if cfg!(miri) { 1_000 } else { 10_000_000 };
+        let bst = chain(n, false);
+        assert_eq!(n, bst.height());
+        drop(bst);
+        drop(chain(n / 10, true));
+    }
+
+    #[test]
+    fn clone_works() {
+        let mut bst = BinarySearchTree::from(vec![5, 3, 8, 1, 4]);
+        let copy = bst.clone();
+        bst.delete(3);
+        assert!(copy.is_avl());
+        assert_eq!(vec![1, 3, 4, 5, 8], Vec::from(copy.clone()));
+        assert_eq!(format!("{:?}", copy), format!("{:?}", copy.clone()));
+        check_avl(&copy.clone().root);
+        check_sizes(&copy.clone().root);
+    }
+
+    #[test]
+    fn trees_are_ordered_by_their_values() {
+        let shallow = BinarySearchTree::from(vec![1, 2, 3]);
+        let chain: BinarySearchTree<i32> = [1, 2, 3].into_iter().collect();
+        let bigger = BinarySearchTree::from(vec![1, 2, 4]);
+        assert_eq!(Ordering::Equal, shallow.cmp(&chain));
+        assert_eq!(Ordering::Less, chain.cmp(&bigger));
+        assert!(BinarySearchTree::from(vec![1, 2]) < shallow);
+        let mut trees = [bigger.clone(), chain, BinarySearchTree::new()];
+        trees.sort();
+        assert_eq!(
+            vec![0, 3, 3],
+            trees.iter().map(|t| t.len()).collect::<Vec<_>>()
+        );
+
+        // Ord::max is the bigger tree, the inherent max is the biggest value
+        let top = Ord::max(shallow, bigger);
+        assert_eq!(Some(&4), BinarySearchTree::max(&top));
+    }
+
+    #[test]
+    fn set_operations_match_btreeset() {
+        let mut rng = Rng(0x123456789ABCDEF);
+        for _ in 0..50 {
+            let a: BTreeSet<u64> = (0..rng.next() % 60).map(|_| rng.next() % 100).collect();
+            let b: BTreeSet<u64> = (0..rng.next() % 60).map(|_| rng.next() % 100).collect();
+            let mut ta = BinarySearchTree::new();
+            for v in &a {
+                ta.insert(*v);
+            }
+            let tb = BinarySearchTree::from(b.iter().copied().collect::<Vec<_>>());
+
+            assert!(ta.union(&tb).eq(a.union(&b)));
+            assert!(ta.intersection(&tb).eq(a.intersection(&b)));
+            assert!(ta.difference(&tb).eq(a.difference(&b)));
+            assert!(tb.difference(&ta).eq(b.difference(&a)));
+            assert!(ta.symmetric_difference(&tb).eq(a.symmetric_difference(&b)));
+            assert_eq!(a.is_subset(&b), ta.is_subset(&tb));
+            assert_eq!(a.is_superset(&b), ta.is_superset(&tb));
+            assert_eq!(a.is_disjoint(&b), ta.is_disjoint(&tb));
+
+            let union = ta.clone().into_union(tb.clone());
+            assert!(!union.is_avl());
+            check_avl(&union.root);
+            check_sizes(&union.root);
+            assert!(union.iter().eq(a.union(&b)));
+            assert!(ta
+                .clone()
+                .into_intersection(tb.clone())
+                .iter()
+                .eq(a.intersection(&b)));
+            assert!(ta
+                .clone()
+                .into_difference(tb.clone())
+                .iter()
+                .eq(a.difference(&b)));
+            assert!(tb
+                .clone()
+                .into_symmetric_difference(ta.clone())
+                .iter()
+                .eq(b.symmetric_difference(&a)));
+        }
+    }
+
+    #[test]
+    fn set_operations_pair_up_duplicates() {
+        let a = BinarySearchTree::from(vec![1, 1, 1, 2, 3, 3]);
+        let b = BinarySearchTree::from(vec![1, 3, 3, 3, 4]);
+        assert!(a.union(&b).copied().eq([1, 1, 1, 2, 3, 3, 3, 4]));
+        assert!(a.intersection(&b).copied().eq([1, 3, 3]));
+        assert!(a.difference(&b).copied().eq([1, 1, 2]));
+        assert!(a.symmetric_difference(&b).copied().eq([1, 1, 2, 3, 4]));
+        assert!(BinarySearchTree::from(vec![1, 1]).is_subset(&a));
+        assert!(!BinarySearchTree::from(vec![2, 2]).is_subset(&a));
+        assert!(BinarySearchTree::<i32>::new().is_subset(&a));
+        assert!(a.is_disjoint(&BinarySearchTree::from(vec![0, 5])));
+        assert!(!a.is_disjoint(&b));
+    }
+
+    #[test]
+    fn split_off_and_append_work() {
+        let mut a = BinarySearchTree::new_avl();
+        for v in 0..100 {
+            a.insert(v % 50);
+        }
+        let mut b = a.split_off(&30);
+        assert!(b.is_avl());
+        assert_eq!(60, a.len());
+        assert_eq!(40, b.len());
+        assert_eq!(Some(&29), BinarySearchTree::max(&a));
+        assert_eq!(Some(&30), BinarySearchTree::min(&b));
+        check_avl(&a.root);
+        check_avl(&b.root);
+
+        let mut c = a.split_off(&100);
+        assert!(c.is_empty());
+        c.append(&mut b);
+        assert!(b.is_empty());
+        assert!(b.is_avl());
+        assert_eq!(40, c.len());
+
+        let mut d = BinarySearchTree::from(vec![10, 35, 35]);
+        a.append(&mut d);
+        a.append(&mut c);
+        assert!(d.is_empty());
+        assert_eq!(103, a.len());
+        check_avl(&a.root);
+        check_sizes(&a.root);
+        let mut expected: Vec<i32> = (0..100).map(|v| v % 50).chain([10, 35, 35]).collect();
+        expected.sort();
+        assert_eq!(expected, Vec::from(a));
+    }
 }
@@ -0,0 +1,514 @@
+use std::cmp::Ordering;
+use std::fmt;
+use std::marker::PhantomData;
+use std::mem;
+use std::ptr::NonNull;
+use std::sync::Arc;
+
+use crate::heap_handle::{free_all, take_node, Link, Node, Owner};
+pub use crate::heap_handle::{Handle, Iter};
+use crate::priority_queue::{ByKey, Compare, Max, Min};
+use crate::traits::{Collection, Heap, MergeableHeap};
+
+/**
+ * Binomial Heap
+ * A list of heap ordered trees, at most one of each order k; a tree of order k has 2^k items,
+ *  a root with children of order k - 1 down to 0. Which orders are there follows the bits of len
+ * meld O(log N): adding two binary numbers, two trees of order k link into one of order k + 1
+ *  and carry
+ * push O(1) amortized (adding one), pop O(log N): the top root's children are melded back in
+ * peek O(log N), the top is whichever root goes first
+ * decrease_key/remove O(log N): the item swaps places with its parents until it fits,
+ *  trees are only log N deep
+ * push_with_handle hands back a Handle for decrease_key/remove, it goes stale once the item
+ *  leaves and follows it if its heap is melded into another
+ * Same Compare strategies as PriorityQueue, Min unless a constructor says otherwise
+ */
+pub struct BinomialHeap<T, C = Min> {
+    /// trees[k] is the tree of order k, if there is one. Never ends in None
+    trees: Vec<Link<T>>,
+    len: usize,
+    owner: Arc<Owner>,
+    order: C,
+    marker: PhantomData<Box<Node<T>>>,
+}
+
+impl<T: Ord> BinomialHeap<T> {
+    /// a min heap
+    pub fn new() -> Self {
+        Self::with_comparator(Min)
+    }
+}
+
+impl<T: Ord> BinomialHeap<T, Max> {
+    pub fn new_max() -> Self {
+        Self::with_comparator(Max)
+    }
+}
+
+impl<T, K: Ord, F: Fn(&T) -> K> BinomialHeap<T, ByKey<F>> {
+    pub fn by_key(key: F) -> Self {
+        Self::with_comparator(ByKey(key))
+    }
+}
+
+impl<T, C: Compare<T>> BinomialHeap<T, C> {
+    pub fn with_comparator(order: C) -> Self {
+        BinomialHeap {
+            trees: Vec::new(),
+            len: 0,
+            owner: Owner::new(),
+            order,
+            marker: PhantomData,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// every handle handed out so far goes stale
+    pub fn clear(&mut self) {
+        free_all(self.trees.drain(..).flatten());
+        self.len = 0;
+    }
+
+    pub fn push(&mut self, value: T) {
+        self.push_node(value);
+    }
+
+    /// push, keeping a way to find the item again
+    pub fn push_with_handle(&mut self, value: T) -> Handle<T> {
+        let node = self.push_node(value);
+        unsafe { Handle::attach(node, &self.owner) }
+    }
+
+    pub fn peek(&self) -> Option<&T> {
+        let k = self.top_order()?;
+        Some(unsafe { &(*self.trees[k].unwrap().as_ptr()).value })
+    }
+
+    pub fn pop(&mut self) -> Option<T> {
+        let k = self.top_order()?;
+        Some(unsafe { self.remove_root(k) })
+    }
+
+    /// Moves every item of other in here in O(log N), leaving other empty.
+    /// other's handles now work with self
+    pub fn meld(&mut self, other: &mut Self) {
+        if other.is_empty() {
+            return;
+        }
+        let theirs = mem::take(&mut other.trees);
+        unsafe { self.add_trees(theirs) };
+        self.len += mem::take(&mut other.len);
+        Owner::merge_into(&other.owner, &self.owner);
+        other.owner = Owner::new();
+    }
+
+    /// false once the item has been popped or removed, or if it's in another heap
+    pub fn contains(&self, handle: &Handle<T>) -> bool {
+        handle.node_in(&self.owner).is_some()
+    }
+
+    pub fn get(&self, handle: &Handle<T>) -> Option<&T> {
+        let node = handle.node_in(&self.owner)?;
+        Some(unsafe { &(*node.as_ptr()).value })
+    }
+
+    /// Replaces the item with new only if new would leave the heap sooner.
+    /// True if it was replaced, false if the handle is stale or new isn't sooner
+    pub fn decrease_key(&mut self, handle: &Handle<T>, new: T) -> bool {
+        let Some(node) = handle.node_in(&self.owner) else {
+            return false;
+        };
+        unsafe {
+            if self.order.compare(&new, &(*node.as_ptr()).value) != Ordering::Less {
+                return false;
+            }
+            (*node.as_ptr()).value = new;
+            self.sift_up(node, false);
+        }
+        true
+    }
+
+    /// Takes the item out wherever it is in the heap, None if it's already gone
+    pub fn remove(&mut self, handle: &Handle<T>) -> Option<T> {
+        let node = handle.node_in(&self.owner)?;
+        unsafe {
+            // float it all the way up, then it goes like a pop
+            let root = Some(self.sift_up(node, true));
+            let k = self.trees.iter().position(|&tree| tree == root).unwrap();
+            Some(self.remove_root(k))
+        }
+    }
+
+    /// every item, in no particular order
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter::new(self.trees.iter().flatten().copied(), self.len)
+    }
+
+    fn push_node(&mut self, value: T) -> NonNull<Node<T>> {
+        let node = Node::alloc(value);
+        unsafe { self.add_trees([Some(node)]) };
+        self.len += 1;
+        node
+    }
+
+    /// the order of the tree whose root goes first
+    fn top_order(&self) -> Option<usize> {
+        let mut top: Option<(usize, NonNull<Node<T>>)> = None;
+        for (k, &tree) in self.trees.iter().enumerate() {
+            let Some(root) = tree else { continue };
+            let first = match top {
+                None => true,
+                Some((_, best)) => unsafe { self.before(root, best) },
+            };
+            if first {
+                top = Some((k, root));
+            }
+        }
+        top.map(|(k, _)| k)
+    }
+
+    unsafe fn before(&self, a: NonNull<Node<T>>, b: NonNull<Node<T>>) -> bool {
+        self.order
+            .compare(&(*a.as_ptr()).value, &(*b.as_ptr()).value)
+            == Ordering::Less
+    }
+
+    /// Adds theirs[k] (a tree of order k, or None) for each k like binary addition:
+    /// where two trees of the same order meet they link and carry one order up.
+    /// Stops as soon as theirs runs out with nothing carried, so adding one tree is O(1) amortized
+    unsafe fn add_trees(&mut self, theirs: impl IntoIterator<Item = Link<T>>) {
+        let mut theirs = theirs.into_iter();
+        let mut carry: Link<T> = None;
+        let mut k = 0;
+        loop {
+            let tree = theirs.next();
+            if tree.is_none() && carry.is_none() {
+                break;
+            }
+            if k == self.trees.len() {
+                self.trees.push(None);
+            }
+            let mut same_order = [self.trees[k].take(), tree.flatten(), carry.take()]
+                .into_iter()
+                .flatten();
+            match (same_order.next(), same_order.next()) {
+                (Some(a), Some(b)) => {
+                    self.trees[k] = same_order.next();
+                    carry = Some(self.link(a, b));
+                }
+                (a, _) => self.trees[k] = a,
+            }
+            k += 1;
+        }
+        self.trim();
+    }
+
+    /// Joins two trees of the same order into one of the next order up,
+    /// whichever root goes later (b on a tie) becomes the other's highest child
+    unsafe fn link(&self, a: NonNull<Node<T>>, b: NonNull<Node<T>>) -> NonNull<Node<T>> {
+        let (top, under) = if self.before(b, a) { (b, a) } else { (a, b) };
+        let (t, u) = (top.as_ptr(), under.as_ptr());
+        (*u).up = Some(top);
+        (*u).next = (*t).child;
+        (*t).child = Some(under);
+        top
+    }
+
+    /// Takes out the root of trees[k], its children (orders k - 1 down to 0) go back in
+    unsafe fn remove_root(&mut self, k: usize) -> T {
+        let root = self.trees[k].take().unwrap();
+        self.trim();
+        let mut children: [Link<T>; usize::BITS as usize] = [None; usize::BITS as usize];
+        let mut child = (*root.as_ptr()).child;
+        for order in (0..k).rev() {
+            let c = child.unwrap();
+            child = (*c.as_ptr()).next;
+            (*c.as_ptr()).up = None;
+            (*c.as_ptr()).next = None;
+            children[order] = Some(c);
+        }
+        self.add_trees(children[..k].iter().copied());
+        self.len -= 1;
+        take_node(root)
+    }
+
+    /// Moves node's item up by swapping with its parent while it goes first,
+    /// or all the way to the root with to_root. Returns where the item ended up
+    unsafe fn sift_up(&mut self, mut node: NonNull<Node<T>>, to_root: bool) -> NonNull<Node<T>> {
+        while let Some(parent) = (*node.as_ptr()).up {
+            if !to_root && !self.before(node, parent) {
+                break;
+            }
+            swap_items(node, parent);
+            node = parent;
+        }
+        node
+    }
+
+    fn trim(&mut self) {
+        while let Some(None) = self.trees.last() {
+            self.trees.pop();
+        }
+    }
+}
+
+/// swaps two nodes' items, telling their handles where they went
+unsafe fn swap_items<T>(a: NonNull<Node<T>>, b: NonNull<Node<T>>) {
+    let (a_ref, b_ref) = (&mut *a.as_ptr(), &mut *b.as_ptr());
+    mem::swap(&mut a_ref.value, &mut b_ref.value);
+    mem::swap(&mut a_ref.tag, &mut b_ref.tag);
+    if let Some(tag) = &a_ref.tag {
+        tag.moved_to(a);
+    }
+    if let Some(tag) = &b_ref.tag {
+        tag.moved_to(b);
+    }
+}
+
+// owns its nodes like a Box would, the raw pointers are what stop this being automatic
+unsafe impl<T: Send, C: Send> Send for BinomialHeap<T, C> {}
+unsafe impl<T: Sync, C: Sync> Sync for BinomialHeap<T, C> {}
+
+impl<T, C> Drop for BinomialHeap<T, C> {
+    fn drop(&mut self) {
+        free_all(self.trees.drain(..).flatten());
+    }
+}
+
+impl<T: fmt::Debug, C: Compare<T>> fmt::Debug for BinomialHeap<T, C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<T, C: Compare<T>> Collection for BinomialHeap<T, C> {
+    fn len(&self) -> usize {
+        BinomialHeap::len(self)
+    }
+
+    fn clear(&mut self) {
+        BinomialHeap::clear(self)
+    }
+}
+
+impl<T, C: Compare<T>> Heap<T> for BinomialHeap<T, C> {
+    fn push(&mut self, item: T) {
+        BinomialHeap::push(self, item)
+    }
+
+    fn pop(&mut self) -> Option<T> {
+        BinomialHeap::pop(self)
+    }
+
+    fn peek(&self) -> Option<&T> {
+        BinomialHeap::peek(self)
+    }
+}
+
+impl<T, C: Compare<T>> MergeableHeap<T> for BinomialHeap<T, C> {
+    fn meld(&mut self, other: &mut Self) {
+        BinomialHeap::meld(self, other)
+    }
+}
+
+impl<T, C: Compare<T> + Default> Default for BinomialHeap<T, C> {
+    fn default() -> Self {
+        Self::with_comparator(C::default())
+    }
+}
+
+impl<T, C: Compare<T>> Extend<T> for BinomialHeap<T, C> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for value in iter {
+            self.push(value);
+        }
+    }
+}
+
+impl<T, C: Compare<T> + Default> FromIterator<T> for BinomialHeap<T, C> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut heap = Self::default();
+        heap.extend(iter);
+        heap
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{self, HandleHeap};
+
+    /// checks every tree's shape and heap condition, the parent links and the count
+    fn assert_valid<T, C: Compare<T>>(heap: &BinomialHeap<T, C>) {
+        /// the size of the tree at node, which should be of the given order
+        fn check<T, C: Compare<T>>(
+            heap: &BinomialHeap<T, C>,
+            node: NonNull<Node<T>>,
+            order: usize,
+        ) -> usize {
+            let mut size = 1;
+            let mut child = unsafe { (*node.as_ptr()).child };
+            for k in (0..order).rev() {
+                let c = child.expect("a child is missing");
+                assert_eq!(Some(node), unsafe { (*c.as_ptr()).up });
+                assert!(
+                    !unsafe { heap.before(c, node) },
+                    "a child goes before its parent"
+                );
+                size += check(heap, c, k);
+                child = unsafe { (*c.as_ptr()).next };
+            }
+            assert!(child.is_none(), "too many children");
+            assert_eq!(1 << order, size);
+            size
+        }
+
+        assert!(!matches!(heap.trees.last(), Some(None)));
+        let mut count = 0;
+        for (k, tree) in heap.trees.iter().enumerate() {
+            if let Some(root) = *tree {
+                unsafe {
+                    assert!((*root.as_ptr()).up.is_none());
+                    assert!((*root.as_ptr()).next.is_none());
+                }
+                count += check(heap, root, k);
+            }
+        }
+        assert_eq!(heap.len(), count);
+    }
+
+    fn pop_all<T, C: Compare<T>>(mut heap: BinomialHeap<T, C>) -> Vec<T> {
+        std::iter::from_fn(|| heap.pop()).collect()
+    }
+
+    #[test]
+    fn binomial_heap_works() {
+        let mut heap = BinomialHeap::new();
+        assert_eq!(None, heap.pop());
+        assert_eq!(None, heap.peek());
+        for v in [5, 3, 8, 1, 9, 2, 7, 3] {
+            heap.push(v);
+            assert_valid(&heap);
+        }
+        // 8 items, a single tree of order 3
+        assert_eq!(4, heap.trees.len());
+        assert_eq!(Some(&1), heap.peek());
+        assert_eq!(Some(1), heap.pop());
+        assert_valid(&heap);
+        assert_eq!(vec![2, 3, 3, 5, 7, 8, 9], pop_all(heap));
+
+        let max: BinomialHeap<i32, Max> = (0..100).map(|i| (i * 37) % 101).collect();
+        let mut expected: Vec<i32> = max.iter().copied().collect();
+        expected.sort_by(|a, b| b.cmp(a));
+        assert_eq!(expected, pop_all(max));
+
+        let mut words = BinomialHeap::by_key(|w: &&str| w.len());
+        words.extend(["pear", "fig", "banana"]);
+        assert_eq!(Some("fig"), words.pop());
+        assert_eq!(2, format!("{words:?}").matches('"').count() / 2);
+    }
+
+    #[test]
+    fn meld() {
+        for (a, b) in [(0, 0), (0, 5), (7, 0), (7, 9), (16, 16), (100, 27)] {
+            let mut x: BinomialHeap<i32> = (0..a).map(|i| (i * 7) % 13).collect();
+            let mut y: BinomialHeap<i32> = (0..b).map(|i| (i * 5) % 17).collect();
+            let mut expected: Vec<i32> = x.iter().chain(y.iter()).copied().collect();
+            expected.sort();
+            x.meld(&mut y);
+            assert_valid(&x);
+            assert!(y.is_empty());
+            assert_eq!(expected, pop_all(x));
+        }
+
+        let mut a: BinomialHeap<i32> = [4, 9, 1].into_iter().collect();
+        let mut b = BinomialHeap::new();
+        let h = b.push_with_handle(6);
+        a.meld(&mut b);
+        // the handle followed its item
+        assert!(!b.contains(&h));
+        assert!(a.decrease_key(&h, -1));
+        let h2 = b.push_with_handle(3);
+        assert!(!a.contains(&h2));
+        assert_eq!(vec![-1, 1, 4, 9], pop_all(a));
+        assert_eq!(Some(&3), b.get(&h2));
+    }
+
+    #[test]
+    fn handles() {
+        let mut heap = BinomialHeap::new();
+        let handles: Vec<_> = (0..20).map(|i| heap.push_with_handle(i * 10)).collect();
+        assert!(heap.decrease_key(&handles[15], 5));
+        assert!(!heap.decrease_key(&handles[15], 5));
+        assert!(!heap.decrease_key(&handles[3], 40));
+        assert_valid(&heap);
+        // items moved by the sift are still found
+        for (i, h) in handles.iter().enumerate() {
+            let expected = if i == 15 { 5 } else { i * 10 };
+            assert_eq!(Some(&expected), heap.get(h));
+        }
+        assert_eq!(Some(0), heap.pop());
+        assert!(!heap.contains(&handles[0]));
+        assert!(!heap.decrease_key(&handles[0], 0));
+        assert_eq!(None, heap.remove(&handles[0]));
+
+        assert_eq!(Some(5), heap.remove(&handles[15]));
+        assert_eq!(Some(190), heap.remove(&handles[19]));
+        assert_valid(&heap);
+        for (i, h) in handles.iter().enumerate() {
+            assert_eq!(![0, 15, 19].contains(&i), heap.contains(h));
+        }
+
+        let other = BinomialHeap::new();
+        assert!(!other.contains(&handles[2]));
+        heap.clear();
+        assert!(!heap.contains(&handles[2]));
+    }
+
+    impl HandleHeap for BinomialHeap<i64> {
+        fn push_with_handle(&mut self, value: i64) -> Handle<i64> {
+            BinomialHeap::push_with_handle(self, value)
+        }
+
+        fn pop(&mut self) -> Option<i64> {
+            BinomialHeap::pop(self)
+        }
+
+        fn len(&self) -> usize {
+            BinomialHeap::len(self)
+        }
+
+        fn contains(&self, handle: &Handle<i64>) -> bool {
+            BinomialHeap::contains(self, handle)
+        }
+
+        fn get(&self, handle: &Handle<i64>) -> Option<&i64> {
+            BinomialHeap::get(self, handle)
+        }
+
+        fn decrease_key(&mut self, handle: &Handle<i64>, new: i64) -> bool {
+            BinomialHeap::decrease_key(self, handle, new)
+        }
+
+        fn remove(&mut self, handle: &Handle<i64>) -> Option<i64> {
+            BinomialHeap::remove(self, handle)
+        }
+
+        fn assert_valid(&self) {
+            assert_valid(self)
+        }
+    }
+
+    #[test]
+    fn matches_a_sorted_model() {
+        test_util::matches_a_sorted_model::<BinomialHeap<i64>>(0x2545_F491_4F6C_DD1D);
+    }
+}
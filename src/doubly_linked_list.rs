@@ -11,10 +11,14 @@
  * get
  */
 use std::cell::RefCell;
+use std::cmp::Ordering;
 use std::fmt;
+use std::hash::{Hash, Hasher};
 use std::ops::Deref;
 use std::rc::Rc;
 
+use crate::traits::{Collection, Deque, Fifo, Lifo};
+
 type Link<T> = Rc<RefCell<Node<T>>>;
 
 pub struct DubLinkedList<T> {
@@ -42,7 +46,19 @@ impl<T: fmt::Debug> fmt::Debug for Node<T> {
     }
 }
 
-impl<T: fmt::Debug> DubLinkedList<T> {
+impl<T> Default for DubLinkedList<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Drop for DubLinkedList<T> {
+    fn drop(&mut self) {
+        self.clear();
+    }
+}
+
+impl<T> DubLinkedList<T> {
     pub fn new() -> DubLinkedList<T> {
         DubLinkedList {
             head: None,
@@ -51,6 +67,19 @@ impl<T: fmt::Debug> DubLinkedList<T> {
         }
     }
 
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// unlinks node by node, the Rc cycles between neighbours would otherwise leak
+    pub fn clear(&mut self) {
+        while self.pop_front().is_some() {}
+    }
+
     pub fn push_front(&mut self, val: T) {
         if self.head.is_none() {
             let n = Node {
@@ -153,6 +182,11 @@ impl<T: fmt::Debug> DubLinkedList<T> {
         Some(v)
     }
 
+    /// head to tail, cloning the Rcs to walk the list
+    fn nodes(&self) -> impl Iterator<Item = Link<T>> {
+        std::iter::successors(self.head.clone(), |n| n.borrow().next.clone())
+    }
+
     pub fn get(&self, index: usize) -> Option<T>
     where
         T: Clone,
@@ -171,6 +205,117 @@ impl<T: fmt::Debug> DubLinkedList<T> {
     }
 }
 
+impl<T> Collection for DubLinkedList<T> {
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn clear(&mut self) {
+        DubLinkedList::clear(self)
+    }
+}
+
+impl<T> Deque<T> for DubLinkedList<T> {
+    fn push_front(&mut self, item: T) {
+        DubLinkedList::push_front(self, item)
+    }
+
+    fn push_back(&mut self, item: T) {
+        DubLinkedList::push_back(self, item)
+    }
+
+    fn pop_front(&mut self) -> Option<T> {
+        DubLinkedList::pop_front(self)
+    }
+
+    fn pop_back(&mut self) -> Option<T> {
+        DubLinkedList::pop_back(self)
+    }
+}
+
+// pushes and pops at the back
+impl<T> Lifo<T> for DubLinkedList<T> {
+    fn push(&mut self, item: T) {
+        self.push_back(item)
+    }
+
+    fn pop(&mut self) -> Option<T> {
+        self.pop_back()
+    }
+}
+
+// in at the back, out at the front, like Queue
+impl<T> Fifo<T> for DubLinkedList<T> {
+    fn enqueue(&mut self, item: T) {
+        self.push_back(item)
+    }
+
+    fn dequeue(&mut self) -> Option<T> {
+        self.pop_front()
+    }
+}
+
+impl<T> FromIterator<T> for DubLinkedList<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut l = DubLinkedList::new();
+        l.extend(iter);
+        l
+    }
+}
+
+impl<T> Extend<T> for DubLinkedList<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for val in iter {
+            self.push_back(val);
+        }
+    }
+}
+
+impl<T: PartialEq> PartialEq for DubLinkedList<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len == other.len
+            && self
+                .nodes()
+                .zip(other.nodes())
+                .all(|(a, b)| a.borrow().val == b.borrow().val)
+    }
+}
+
+impl<T: Eq> Eq for DubLinkedList<T> {}
+
+impl<T: Hash> Hash for DubLinkedList<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.len.hash(state);
+        for n in self.nodes() {
+            n.borrow().val.hash(state);
+        }
+    }
+}
+
+impl<T: PartialOrd> PartialOrd for DubLinkedList<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        for (a, b) in self.nodes().zip(other.nodes()) {
+            match a.borrow().val.partial_cmp(&b.borrow().val) {
+                Some(Ordering::Equal) => {}
+                non_eq => return non_eq,
+            }
+        }
+        self.len.partial_cmp(&other.len)
+    }
+}
+
+impl<T: Ord> Ord for DubLinkedList<T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        for (a, b) in self.nodes().zip(other.nodes()) {
+            match a.borrow().val.cmp(&b.borrow().val) {
+                Ordering::Equal => {}
+                non_eq => return non_eq,
+            }
+        }
+        self.len.cmp(&other.len)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -206,4 +351,17 @@ mod tests {
         l.pop_back();
         assert_eq!(0, l.len);
     }
+
+    #[test]
+    fn dropping_frees_every_node() {
+        let counter = Rc::new(());
+        let mut l = DubLinkedList::new();
+        for _ in 0..5 {
+            l.push_back(Rc::clone(&counter));
+        }
+        l.push_front(Rc::clone(&counter));
+        assert_eq!(7, Rc::strong_count(&counter));
+        drop(l);
+        assert_eq!(1, Rc::strong_count(&counter));
+    }
 }
@@ -0,0 +1,328 @@
+/*
+Heap nodes and handles:
+ * what PairingHeap and BinomialHeap share: both keep their trees as nodes linked to their
+   first child and next sibling, and hand out Handles so a queued item can be found again
+   for decrease_key/remove
+ * a handle and its node share a Tag: the node's address while it's queued,
+   null once the item is popped, removed or dropped with its heap
+ * meld moves a whole heap's nodes over without visiting them, so which heap a node
+   belongs to can't be written on the node. Every heap has an Owner instead, meld points
+   the emptied heap's Owner at the survivor's (union-find with path compression) and a
+   tag's node is only touched by the heap its Owner chain ends at
+ * a live heap's Owner is always the end of its chain, meld gives the emptied heap a fresh one
+ * Owners and Tags are shared through Arc, with a Mutex for the Owner links and an atomic
+   node pointer, so a heap can move to another thread like any other collection and a
+   Handle can be checked against a heap on another thread without a data race
+*/
+use std::fmt;
+use std::iter::FusedIterator;
+use std::marker::PhantomData;
+use std::ptr::{self, NonNull};
+use std::sync::atomic::{AtomicPtr, Ordering};
+use std::sync::{Arc, Mutex};
+
+pub(crate) type Link<T> = Option<NonNull<Node<T>>>;
+
+pub(crate) struct Node<T> {
+    pub(crate) value: T,
+    /// the first child: leftmost in a PairingHeap, of the highest order in a BinomialHeap
+    pub(crate) child: Link<T>,
+    /// the next sibling: to the right in a PairingHeap, an order down in a BinomialHeap
+    pub(crate) next: Link<T>,
+    /// PairingHeap: the parent for a leftmost child, the sibling to the left for the rest.
+    /// BinomialHeap: the parent
+    pub(crate) up: Link<T>,
+    /// only there if the item was pushed with a handle
+    pub(crate) tag: Option<Arc<Tag<T>>>,
+}
+
+impl<T> Node<T> {
+    /// a node on its own, owned by whoever holds the pointer
+    pub(crate) fn alloc(value: T) -> NonNull<Node<T>> {
+        NonNull::from(Box::leak(Box::new(Node {
+            value,
+            child: None,
+            next: None,
+            up: None,
+            tag: None,
+        })))
+    }
+}
+
+/// frees a node that's been unhooked from everything, handing back its value
+pub(crate) unsafe fn take_node<T>(node: NonNull<Node<T>>) -> T {
+    let node = Box::from_raw(node.as_ptr());
+    if let Some(tag) = &node.tag {
+        tag.release();
+    }
+    node.value
+}
+
+/// frees whole trees without recursing, deep trees and long sibling lists are common
+pub(crate) fn free_all<T>(roots: impl IntoIterator<Item = NonNull<Node<T>>>) {
+    let mut stack: Vec<NonNull<Node<T>>> = roots.into_iter().collect();
+    while let Some(node) = stack.pop() {
+        let node = unsafe { Box::from_raw(node.as_ptr()) };
+        stack.extend(node.child);
+        stack.extend(node.next);
+        if let Some(tag) = &node.tag {
+            tag.release();
+        }
+    }
+}
+
+/// Finds one pushed item again until it's popped or removed
+pub struct Handle<T>(Arc<Tag<T>>);
+
+impl<T> Handle<T> {
+    /// tags node as belonging to owner's heap and hands back a handle to it
+    pub(crate) unsafe fn attach(node: NonNull<Node<T>>, owner: &Arc<Owner>) -> Handle<T> {
+        let tag = Arc::new(Tag {
+            node: AtomicPtr::new(node.as_ptr()),
+            owner: owner.clone(),
+        });
+        (*node.as_ptr()).tag = Some(tag.clone());
+        Handle(tag)
+    }
+
+    /// the node, if it's still queued in the heap owned by owner
+    pub(crate) fn node_in(&self, owner: &Arc<Owner>) -> Option<NonNull<Node<T>>> {
+        self.0.node_in(owner)
+    }
+}
+
+impl<T> Clone for Handle<T> {
+    fn clone(&self) -> Self {
+        Handle(self.0.clone())
+    }
+}
+
+impl<T> fmt::Debug for Handle<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Handle").finish_non_exhaustive()
+    }
+}
+
+/// Every item of a heap, in no particular order
+pub struct Iter<'a, T> {
+    stack: Vec<NonNull<Node<T>>>,
+    remaining: usize,
+    marker: PhantomData<&'a T>,
+}
+
+impl<T> Iter<'_, T> {
+    /// walks the trees under roots, which hold len items between them
+    pub(crate) fn new(roots: impl IntoIterator<Item = NonNull<Node<T>>>, len: usize) -> Self {
+        Iter {
+            stack: roots.into_iter().collect(),
+            remaining: len,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<&'a T> {
+        let node = unsafe { &*self.stack.pop()?.as_ptr() };
+        self.stack.extend(node.child);
+        self.stack.extend(node.next);
+        self.remaining -= 1;
+        Some(&node.value)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+// it only hands out &T, like a slice iterator
+unsafe impl<T: Sync> Send for Iter<'_, T> {}
+unsafe impl<T: Sync> Sync for Iter<'_, T> {}
+
+impl<T> ExactSizeIterator for Iter<'_, T> {}
+
+impl<T> FusedIterator for Iter<'_, T> {}
+
+pub(crate) struct Owner {
+    /// only ever locked one at a time, so there's no lock order to get wrong
+    merged_into: Mutex<Option<Arc<Owner>>>,
+}
+
+impl Owner {
+    pub(crate) fn new() -> Arc<Owner> {
+        Arc::new(Owner {
+            merged_into: Mutex::new(None),
+        })
+    }
+
+    /// everything this (live, unmerged) owner had now belongs to into
+    pub(crate) fn merge_into(this: &Arc<Owner>, into: &Arc<Owner>) {
+        let mut merged_into = this.merged_into.lock().unwrap();
+        debug_assert!(merged_into.is_none());
+        *merged_into = Some(into.clone());
+    }
+
+    fn next(&self) -> Option<Arc<Owner>> {
+        self.merged_into.lock().unwrap().clone()
+    }
+
+    /// the owner at the end of this's chain, pointing everything on the way straight at it
+    fn find(this: &Arc<Owner>) -> Arc<Owner> {
+        let mut root = this.clone();
+        while let Some(next) = root.next() {
+            root = next;
+        }
+        let mut cur = this.clone();
+        while !Arc::ptr_eq(&cur, &root) {
+            let next = cur.merged_into.lock().unwrap().replace(root.clone());
+            cur = next.unwrap();
+        }
+        root
+    }
+}
+
+impl Drop for Owner {
+    // a long chain would otherwise be dropped recursively
+    fn drop(&mut self) {
+        let mut next = self.merged_into.get_mut().unwrap().take();
+        while let Some(owner) = next {
+            next = match Arc::try_unwrap(owner) {
+                Ok(mut owner) => owner.merged_into.get_mut().unwrap().take(),
+                Err(_) => None,
+            };
+        }
+    }
+}
+
+pub(crate) struct Tag<T> {
+    /// null once the item has left its heap
+    node: AtomicPtr<Node<T>>,
+    owner: Arc<Owner>,
+}
+
+impl<T> Tag<T> {
+    /// the tagged item moved to another node of the same heap
+    pub(crate) fn moved_to(&self, node: NonNull<Node<T>>) {
+        self.node.store(node.as_ptr(), Ordering::Release);
+    }
+
+    /// the tagged item left its heap
+    pub(crate) fn release(&self) {
+        self.node.store(ptr::null_mut(), Ordering::Release);
+    }
+
+    /// the node, if it's still queued in the heap owned by owner
+    fn node_in(&self, owner: &Arc<Owner>) -> Option<NonNull<Node<T>>> {
+        let node = NonNull::new(self.node.load(Ordering::Acquire))?;
+        Arc::ptr_eq(&Owner::find(&self.owner), owner).then_some(node)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::binomial_heap::BinomialHeap;
+    use crate::pairing_heap::PairingHeap;
+    use crate::traits::MergeableHeap;
+    use std::thread;
+
+    // sharded heaps have to be able to move to worker threads and back
+    const _: fn() = || {
+        fn send<S: Send>() {}
+        fn sync<S: Sync>() {}
+        send::<PairingHeap<Box<u32>>>();
+        send::<BinomialHeap<Box<u32>>>();
+        send::<Handle<std::cell::Cell<u8>>>();
+        sync::<PairingHeap<u32>>();
+        sync::<BinomialHeap<u32>>();
+    };
+
+    fn sharded<H: MergeableHeap<u32> + Default + Send + 'static>() -> Vec<u32> {
+        let workers: Vec<_> = (0..4)
+            .map(|shard| {
+                thread::spawn(move || {
+                    let mut heap = H::default();
+                    for i in 0..50 {
+                        heap.push(i * 4 + shard);
+                    }
+                    heap
+                })
+            })
+            .collect();
+        let mut all = H::default();
+        for worker in workers {
+            all.meld(&mut worker.join().unwrap());
+        }
+        std::iter::from_fn(|| all.pop()).collect()
+    }
+
+    #[test]
+    fn heaps_move_between_threads() {
+        assert!(sharded::<PairingHeap<u32>>().into_iter().eq(0..200));
+        assert!(sharded::<BinomialHeap<u32>>().into_iter().eq(0..200));
+
+        // a handle can go along with its heap
+        let mut heap = PairingHeap::new();
+        let h = heap.push_with_handle(5);
+        let (heap, h) = thread::spawn(move || {
+            assert!(heap.decrease_key(&h, 1));
+            (heap, h)
+        })
+        .join()
+        .unwrap();
+        assert_eq!(Some(&1), heap.get(&h));
+    }
+
+    #[test]
+    fn melded_owners_lead_to_the_survivor() {
+        let (a, b, c) = (Owner::new(), Owner::new(), Owner::new());
+        let node = Node::alloc(0u8);
+        let handle = unsafe { Handle::attach(node, &a) };
+        assert!(handle.node_in(&a).is_some());
+        assert!(handle.node_in(&b).is_none());
+
+        Owner::merge_into(&a, &b);
+        Owner::merge_into(&b, &c);
+        assert!(handle.node_in(&a).is_none());
+        assert!(handle.node_in(&c).is_some());
+        // the lookup pointed a straight at c
+        assert!(Arc::ptr_eq(&c, &a.next().unwrap()));
+
+        assert_eq!(0, unsafe { take_node(node) });
+        assert!(handle.node_in(&c).is_none());
+    }
+
+    #[test]
+    fn long_chains_drop() {
+        let first = Owner::new();
+        let mut last = first.clone();
+        for _ in 0..if cfg!(miri) { 1_000 } else { 200_000 } {
+            let next = Owner::new();
+            Owner::merge_into(&last, &next);
+            last = next;
+        }
+        drop(last);
+        drop(first);
+    }
+
+    #[test]
+    fn iter_and_free_all_walk_children_and_siblings() {
+        // 0 with children 1 and 2, 1 with child 3
+        let nodes: Vec<_> = (0..4).map(Node::alloc).collect();
+        unsafe {
+            (*nodes[0].as_ptr()).child = Some(nodes[1]);
+            (*nodes[1].as_ptr()).next = Some(nodes[2]);
+            (*nodes[1].as_ptr()).child = Some(nodes[3]);
+        }
+        let owner = Owner::new();
+        let handle = unsafe { Handle::attach(nodes[3], &owner) };
+        let mut seen: Vec<i32> = Iter::new([nodes[0]], 4).copied().collect();
+        seen.sort();
+        assert_eq!(vec![0, 1, 2, 3], seen);
+        assert_eq!(4, Iter::new([nodes[0]], 4).len());
+        free_all([nodes[0]]);
+        assert!(handle.node_in(&owner).is_none());
+    }
+}
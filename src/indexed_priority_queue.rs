@@ -0,0 +1,428 @@
+use std::cmp::Ordering;
+use std::fmt;
+
+use crate::priority_queue::{ByKey, Compare, Max, Min};
+use crate::traits::Collection;
+
+/**
+ * Indexed Priority Queue
+ * The same binary heap as PriorityQueue, but push hands back a Handle to the item,
+ * so it can be looked at, re-prioritised or removed while it's still queued
+ * push/pop/remove/change_priority/decrease_key O(log N), contains/get/peek O(1)
+ * Items live in slots that never move; the heap holds slot numbers and each slot
+ * remembers where in the heap it sits (the position map), kept in step by every swap
+ * Freed slots are reused, a generation per slot makes stale Handles miss instead of
+ * finding whatever took the slot over
+ */
+pub struct IndexedPriorityQueue<T, C = Min> {
+    /// slot numbers in heap order, children of i are 2i + 1 and 2i + 2
+    heap: Vec<usize>,
+    slots: Vec<Slot<T>>,
+    /// empty slots, reused before new ones are made
+    free: Vec<usize>,
+    order: C,
+}
+
+struct Slot<T> {
+    /// the item and its index in heap, None while the slot is free
+    entry: Option<(T, usize)>,
+    /// bumped every time the slot is freed, 64 bits so it never comes back around
+    generation: u64,
+}
+
+/// Names one pushed item until it's popped or removed
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Handle {
+    slot: usize,
+    generation: u64,
+}
+
+impl<T: Ord> IndexedPriorityQueue<T> {
+    /// a min heap
+    pub fn new() -> Self {
+        Self::with_comparator(Min)
+    }
+}
+
+impl<T: Ord> IndexedPriorityQueue<T, Max> {
+    pub fn new_max() -> Self {
+        Self::with_comparator(Max)
+    }
+}
+
+impl<T, K: Ord, F: Fn(&T) -> K> IndexedPriorityQueue<T, ByKey<F>> {
+    pub fn by_key(key: F) -> Self {
+        Self::with_comparator(ByKey(key))
+    }
+}
+
+impl<T, C: Compare<T>> IndexedPriorityQueue<T, C> {
+    pub fn with_comparator(order: C) -> Self {
+        IndexedPriorityQueue {
+            heap: Vec::new(),
+            slots: Vec::new(),
+            free: Vec::new(),
+            order,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.heap.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.heap.is_empty()
+    }
+
+    /// every handle handed out so far goes stale
+    pub fn clear(&mut self) {
+        for &slot in &self.heap {
+            self.slots[slot].entry = None;
+            self.slots[slot].generation += 1;
+            self.free.push(slot);
+        }
+        self.heap.clear();
+    }
+
+    pub fn push(&mut self, value: T) -> Handle {
+        let i = self.heap.len();
+        let slot = match self.free.pop() {
+            Some(slot) => {
+                self.slots[slot].entry = Some((value, i));
+                slot
+            }
+            None => {
+                self.slots.push(Slot {
+                    entry: Some((value, i)),
+                    generation: 0,
+                });
+                self.slots.len() - 1
+            }
+        };
+        self.heap.push(slot);
+        self.heapify_up(i);
+        Handle {
+            slot,
+            generation: self.slots[slot].generation,
+        }
+    }
+
+    pub fn peek(&self) -> Option<&T> {
+        self.heap.first().map(|&slot| self.value(slot))
+    }
+
+    /// the handle of the item peek would show
+    pub fn peek_handle(&self) -> Option<Handle> {
+        self.heap.first().map(|&slot| Handle {
+            slot,
+            generation: self.slots[slot].generation,
+        })
+    }
+
+    pub fn pop(&mut self) -> Option<T> {
+        if self.is_empty() {
+            None
+        } else {
+            Some(self.remove_at(0))
+        }
+    }
+
+    /// false once the item has been popped or removed
+    pub fn contains(&self, handle: Handle) -> bool {
+        self.position(handle).is_some()
+    }
+
+    pub fn get(&self, handle: Handle) -> Option<&T> {
+        self.position(handle).map(|_| self.value(handle.slot))
+    }
+
+    /// Takes the item out wherever it is in the heap, None if it's already gone
+    pub fn remove(&mut self, handle: Handle) -> Option<T> {
+        let i = self.position(handle)?;
+        Some(self.remove_at(i))
+    }
+
+    /// Replaces the item with new, moving it whichever way it needs to go,
+    /// and hands back the old one. None (and new is dropped) if the handle is stale
+    pub fn change_priority(&mut self, handle: Handle, new: T) -> Option<T> {
+        let i = self.position(handle)?;
+        let old = std::mem::replace(self.value_mut(handle.slot), new);
+        self.heapify_up(i);
+        self.heapify_down(self.position(handle).unwrap());
+        Some(old)
+    }
+
+    /// Replaces the item with new only if new would leave the queue sooner, as in Dijkstra's
+    /// relax step. True if it was replaced, false if the handle is stale or new isn't sooner
+    pub fn decrease_key(&mut self, handle: Handle, new: T) -> bool {
+        let Some(i) = self.position(handle) else {
+            return false;
+        };
+        if self.order.compare(&new, self.value(handle.slot)) != Ordering::Less {
+            return false;
+        }
+        *self.value_mut(handle.slot) = new;
+        self.heapify_up(i);
+        true
+    }
+
+    /// where handle's item sits in the heap, if it's still queued
+    fn position(&self, handle: Handle) -> Option<usize> {
+        let slot = self.slots.get(handle.slot)?;
+        if slot.generation != handle.generation {
+            return None;
+        }
+        slot.entry.as_ref().map(|(_, i)| *i)
+    }
+
+    fn value(&self, slot: usize) -> &T {
+        &self.slots[slot].entry.as_ref().unwrap().0
+    }
+
+    fn value_mut(&mut self, slot: usize) -> &mut T {
+        &mut self.slots[slot].entry.as_mut().unwrap().0
+    }
+
+    /// Panics if i is out of bounds
+    fn remove_at(&mut self, i: usize) -> T {
+        let last = self.heap.len() - 1;
+        self.swap(i, last);
+        let slot = self.heap.pop().unwrap();
+        let (value, _) = self.slots[slot].entry.take().unwrap();
+        self.slots[slot].generation += 1;
+        self.free.push(slot);
+        // the item moved into i came from the bottom, it may belong above or below
+        if i < self.heap.len() {
+            let moved = self.heap[i];
+            self.heapify_up(i);
+            let i = self.slots[moved].entry.as_ref().unwrap().1;
+            self.heapify_down(i);
+        }
+        value
+    }
+
+    /// swaps two heap entries and tells their slots where they went
+    fn swap(&mut self, i: usize, j: usize) {
+        self.heap.swap(i, j);
+        self.slots[self.heap[i]].entry.as_mut().unwrap().1 = i;
+        self.slots[self.heap[j]].entry.as_mut().unwrap().1 = j;
+    }
+
+    /// true if the item at heap index i should leave the queue before the one at j
+    fn before(&self, i: usize, j: usize) -> bool {
+        let (a, b) = (self.value(self.heap[i]), self.value(self.heap[j]));
+        self.order.compare(a, b) == Ordering::Less
+    }
+
+    /// the child that would leave the queue first
+    fn first_child(&self, i: usize) -> Option<usize> {
+        let (l_i, r_i) = ((i * 2) + 1, (i * 2) + 2);
+        if r_i < self.heap.len() && self.before(r_i, l_i) {
+            Some(r_i)
+        } else if l_i < self.heap.len() {
+            Some(l_i)
+        } else {
+            None
+        }
+    }
+
+    /// same loops as PriorityQueue's, but every swap goes through swap to keep the slots in step
+    fn heapify_down(&mut self, mut i: usize) {
+        // while the first child goes before it, swap with that child
+        while let Some(c_i) = self.first_child(i) {
+            if !self.before(c_i, i) {
+                break;
+            }
+            self.swap(c_i, i);
+            i = c_i;
+        }
+    }
+
+    fn heapify_up(&mut self, mut i: usize) {
+        // while it goes before its parent, swap with the parent
+        while i != 0 {
+            let parent_i = (i - 1) / 2;
+            if !self.before(i, parent_i) {
+                break;
+            }
+            self.swap(i, parent_i);
+            i = parent_i;
+        }
+    }
+}
+
+impl<T: fmt::Debug, C> fmt::Debug for IndexedPriorityQueue<T, C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list()
+            .entries(
+                self.heap
+                    .iter()
+                    .map(|&slot| &self.slots[slot].entry.as_ref().unwrap().0),
+            )
+            .finish()
+    }
+}
+
+impl<T, C: Compare<T>> Collection for IndexedPriorityQueue<T, C> {
+    fn len(&self) -> usize {
+        IndexedPriorityQueue::len(self)
+    }
+
+    fn clear(&mut self) {
+        IndexedPriorityQueue::clear(self)
+    }
+}
+
+impl<T, C: Compare<T> + Default> Default for IndexedPriorityQueue<T, C> {
+    fn default() -> Self {
+        Self::with_comparator(C::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// checks the heap condition and that every slot knows where it is
+    fn assert_valid<T, C: Compare<T>>(pq: &IndexedPriorityQueue<T, C>) {
+        for (i, &slot) in pq.heap.iter().enumerate() {
+            assert_eq!(i, pq.slots[slot].entry.as_ref().unwrap().1);
+            if i > 0 {
+                assert!(!pq.before(i, (i - 1) / 2), "heap condition broken at {i}");
+            }
+        }
+        let live = pq.slots.iter().filter(|s| s.entry.is_some()).count();
+        assert_eq!(pq.len(), live);
+        assert_eq!(pq.slots.len(), live + pq.free.len());
+    }
+
+    #[test]
+    fn indexed_pq_works() {
+        let mut pq = IndexedPriorityQueue::new();
+        assert_eq!(None, pq.pop());
+        let a = pq.push(50);
+        let b = pq.push(75);
+        let c = pq.push(100);
+        assert_eq!(Some(&50), pq.peek());
+        assert_eq!(Some(a), pq.peek_handle());
+        assert_eq!(Some(&75), pq.get(b));
+
+        assert!(pq.decrease_key(c, 10));
+        assert_eq!(Some(c), pq.peek_handle());
+        // not sooner, so nothing changes
+        assert!(!pq.decrease_key(b, 80));
+        assert_eq!(Some(&75), pq.get(b));
+
+        assert_eq!(Some(10), pq.change_priority(c, 90));
+        assert_eq!(Some(&50), pq.peek());
+        assert_eq!(Some(50), pq.remove(a));
+        assert!(!pq.contains(a));
+        assert_eq!(None, pq.remove(a));
+        assert_valid(&pq);
+
+        assert_eq!(Some(75), pq.pop());
+        assert_eq!(Some(90), pq.pop());
+        assert_eq!(None, pq.pop());
+        assert!(!pq.contains(c));
+    }
+
+    #[test]
+    fn stale_handles_miss_reused_slots() {
+        let mut pq = IndexedPriorityQueue::new();
+        let old = pq.push(1);
+        assert_eq!(Some(1), pq.pop());
+        let new = pq.push(2);
+        assert_eq!(old.slot, new.slot);
+        assert!(!pq.contains(old));
+        assert_eq!(None, pq.get(old));
+        assert_eq!(None, pq.change_priority(old, 0));
+        assert!(!pq.decrease_key(old, 0));
+        assert_eq!(Some(&2), pq.get(new));
+
+        pq.clear();
+        assert!(!pq.contains(new));
+        assert!(pq.is_empty());
+        let h = pq.push(3);
+        assert_eq!(Some(&3), pq.get(h));
+    }
+
+    #[test]
+    fn matches_a_sorted_model() {
+        // a deterministic shuffle of pushes, removes and priority changes
+        let mut pq = IndexedPriorityQueue::new_max();
+        let mut model: Vec<(Handle, i64)> = vec![];
+        let mut x: i64 = 17;
+        for step in 0..if cfg!(miri) { 300 } else { 2000 } {
+            x = (x * 1103515245 + 12345) % 2147483648;
+            let v = x % 1000;
+            match step % 5 {
+                0 | 1 => model.push((pq.push(v), v)),
+                2 if !model.is_empty() => {
+                    let (h, old) = model.remove(x as usize % model.len());
+                    assert_eq!(Some(old), pq.remove(h));
+                }
+                3 if !model.is_empty() => {
+                    let k = x as usize % model.len();
+                    assert_eq!(Some(model[k].1), pq.change_priority(model[k].0, v));
+                    model[k].1 = v;
+                }
+                _ => {
+                    let top = model.iter().map(|&(_, v)| v).max();
+                    let popped = pq.pop();
+                    assert_eq!(top, popped);
+                    if popped.is_some() {
+                        // ties may pop either handle, drop whichever one went
+                        let k = model.iter().position(|&(h, _)| !pq.contains(h)).unwrap();
+                        assert_eq!(popped, Some(model.remove(k).1));
+                    }
+                }
+            }
+            assert_eq!(model.len(), pq.len());
+        }
+        assert_valid(&pq);
+    }
+
+    #[test]
+    fn dijkstra() {
+        // (to, weight) per node
+        let graph: Vec<Vec<(usize, u32)>> = vec![
+            vec![(1, 7), (2, 9), (5, 14)],
+            vec![(0, 7), (2, 10), (3, 15)],
+            vec![(0, 9), (1, 10), (3, 11), (5, 2)],
+            vec![(1, 15), (2, 11), (4, 6)],
+            vec![(3, 6), (5, 9)],
+            vec![(0, 14), (2, 2), (4, 9)],
+        ];
+        let mut dist = vec![u32::MAX; graph.len()];
+        let mut handles: Vec<Option<Handle>> = vec![None; graph.len()];
+        // (distance, node), nearest first
+        let mut pq = IndexedPriorityQueue::new();
+        dist[0] = 0;
+        handles[0] = Some(pq.push((0, 0)));
+        while let Some((d, u)) = pq.pop() {
+            for &(v, w) in &graph[u] {
+                if d + w < dist[v] {
+                    dist[v] = d + w;
+                    match handles[v] {
+                        Some(h) if pq.contains(h) => assert!(pq.decrease_key(h, (d + w, v))),
+                        _ => handles[v] = Some(pq.push((d + w, v))),
+                    }
+                }
+            }
+        }
+        assert_eq!(vec![0, 7, 9, 20, 20, 11], dist);
+    }
+
+    #[test]
+    fn by_key_and_collection() {
+        let mut pq = IndexedPriorityQueue::by_key(|s: &&str| s.len());
+        let long = pq.push("longest");
+        pq.push("mid");
+        assert!(pq.decrease_key(long, "a"));
+        assert_eq!(Some("a"), pq.pop());
+        assert_eq!(1, Collection::len(&pq));
+        Collection::clear(&mut pq);
+        assert!(Collection::is_empty(&pq));
+        assert_eq!("[]", format!("{:?}", pq));
+    }
+}
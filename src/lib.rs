@@ -1,11 +1,22 @@
+pub mod allocator;
 pub mod array_list;
 pub mod binary_search_tree;
+pub mod binomial_heap;
 pub mod doubly_linked_list;
+mod heap_handle;
+pub mod indexed_priority_queue;
 pub mod linked_list;
+pub mod pairing_heap;
 pub mod priority_queue;
 pub mod queue;
+mod raw_buf;
 pub mod ring_buffer;
+pub mod spsc_ring_buffer;
 pub mod stack;
+#[cfg(test)]
+mod test_util;
+pub mod traits;
+pub mod tree_map;
 // rustonomicon reference implementation
 pub mod vec;
 
@@ -9,6 +9,10 @@
  * pop_front
  */
 use std::boxed::Box;
+use std::cmp::Ordering;
+use std::hash::{Hash, Hasher};
+
+use crate::traits::{Collection, Lifo, RandomAccess};
 
 pub struct LinkedList<T> {
     head: Option<Box<Node<T>>>,
@@ -20,17 +24,32 @@ struct Node<T> {
     next: Option<Box<Node<T>>>,
 }
 
+impl<T> Default for LinkedList<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl<T> LinkedList<T> {
     pub fn new() -> LinkedList<T> {
         LinkedList { head: None, len: 0 }
     }
 
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// pops one node at a time, so a long list doesn't blow the stack dropping its boxes
+    pub fn clear(&mut self) {
+        while self.pop_front().is_some() {}
+    }
+
     pub fn push_front(&mut self, val: T) {
-        let prev_head = if let Some(node) = self.head.take() {
-            Some(node)
-        } else {
-            None
-        };
+        let prev_head = self.head.take();
         let n = Node {
             val,
             next: prev_head,
@@ -44,17 +63,18 @@ impl<T> LinkedList<T> {
             return None;
         }
         let mut head = self.head.take().unwrap();
-        let new_head = if let Some(node) = head.next.take() {
-            Some(node)
-        } else {
-            None
-        };
+        let new_head = head.next.take();
 
         self.len -= 1;
         self.head = new_head;
         Some(head.val)
     }
 
+    /// head to tail
+    fn iter(&self) -> impl Iterator<Item = &T> {
+        std::iter::successors(self.head.as_deref(), |n| n.next.as_deref()).map(|n| &n.val)
+    }
+
     pub fn get(&self, index: usize) -> Option<&T> {
         if index >= self.len {
             return None;
@@ -66,6 +86,101 @@ impl<T> LinkedList<T> {
         }
         Some(&cell.val)
     }
+
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut T> {
+        if index >= self.len {
+            return None;
+        }
+        let mut cell = self.head.as_mut().unwrap();
+        for _ in 1..=index {
+            cell = cell.next.as_mut().unwrap();
+        }
+        Some(&mut cell.val)
+    }
+}
+
+impl<T> Collection for LinkedList<T> {
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn clear(&mut self) {
+        LinkedList::clear(self)
+    }
+}
+
+// pushes and pops at the head
+impl<T> Lifo<T> for LinkedList<T> {
+    fn push(&mut self, item: T) {
+        self.push_front(item)
+    }
+
+    fn pop(&mut self) -> Option<T> {
+        self.pop_front()
+    }
+}
+
+impl<T> RandomAccess<T> for LinkedList<T> {
+    fn get(&self, index: usize) -> Option<&T> {
+        LinkedList::get(self, index)
+    }
+
+    fn get_mut(&mut self, index: usize) -> Option<&mut T> {
+        LinkedList::get_mut(self, index)
+    }
+}
+
+impl<T> FromIterator<T> for LinkedList<T> {
+    /// keeps the order, the first item ends up at the head
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut l = LinkedList::new();
+        l.extend(iter);
+        l
+    }
+}
+
+impl<T> Extend<T> for LinkedList<T> {
+    /// appends to the tail, O(N) to find the tail once
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        let mut link = &mut self.head;
+        while let Some(n) = link {
+            link = &mut n.next;
+        }
+        for val in iter {
+            let n = link.insert(Box::new(Node { val, next: None }));
+            self.len += 1;
+            link = &mut n.next;
+        }
+    }
+}
+
+impl<T: PartialEq> PartialEq for LinkedList<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len == other.len && self.iter().eq(other.iter())
+    }
+}
+
+impl<T: Eq> Eq for LinkedList<T> {}
+
+impl<T: Hash> Hash for LinkedList<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.len.hash(state);
+        for v in self.iter() {
+            v.hash(state);
+        }
+    }
+}
+
+impl<T: PartialOrd> PartialOrd for LinkedList<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        self.iter().partial_cmp(other.iter())
+    }
+}
+
+impl<T: Ord> Ord for LinkedList<T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.iter().cmp(other.iter())
+    }
 }
 
 #[cfg(test)]
@@ -0,0 +1,491 @@
+use std::cmp::Ordering;
+use std::fmt;
+use std::marker::PhantomData;
+use std::mem;
+use std::ptr::NonNull;
+use std::sync::Arc;
+
+use crate::heap_handle::{free_all, take_node, Link, Node, Owner};
+pub use crate::heap_handle::{Handle, Iter};
+use crate::priority_queue::{ByKey, Compare, Max, Min};
+use crate::traits::{Collection, Heap, MergeableHeap};
+
+/**
+ * Pairing Heap
+ * A heap ordered tree where a node can have any number of children, kept as a linked list
+ * push/meld O(1): of two roots, the one that goes later becomes the first child of the other
+ * pop O(log N) amortized: the root's children are linked in pairs left to right,
+ *  then the pairs are folded together right to left
+ * decrease_key o(log N) amortized: the node is cut out with its subtree and linked to the root
+ * remove O(log N) amortized, peek O(1)
+ * push_with_handle hands back a Handle for decrease_key/remove, it goes stale once the item
+ *  leaves and follows it if its heap is melded into another
+ * Same Compare strategies as PriorityQueue, Min unless a constructor says otherwise
+ */
+pub struct PairingHeap<T, C = Min> {
+    root: Link<T>,
+    len: usize,
+    owner: Arc<Owner>,
+    order: C,
+    marker: PhantomData<Box<Node<T>>>,
+}
+
+impl<T: Ord> PairingHeap<T> {
+    /// a min heap
+    pub fn new() -> Self {
+        Self::with_comparator(Min)
+    }
+}
+
+impl<T: Ord> PairingHeap<T, Max> {
+    pub fn new_max() -> Self {
+        Self::with_comparator(Max)
+    }
+}
+
+impl<T, K: Ord, F: Fn(&T) -> K> PairingHeap<T, ByKey<F>> {
+    pub fn by_key(key: F) -> Self {
+        Self::with_comparator(ByKey(key))
+    }
+}
+
+impl<T, C: Compare<T>> PairingHeap<T, C> {
+    pub fn with_comparator(order: C) -> Self {
+        PairingHeap {
+            root: None,
+            len: 0,
+            owner: Owner::new(),
+            order,
+            marker: PhantomData,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// every handle handed out so far goes stale
+    pub fn clear(&mut self) {
+        free_all(self.root.take());
+        self.len = 0;
+    }
+
+    pub fn push(&mut self, value: T) {
+        self.push_node(value);
+    }
+
+    /// push, keeping a way to find the item again
+    pub fn push_with_handle(&mut self, value: T) -> Handle<T> {
+        let node = self.push_node(value);
+        unsafe { Handle::attach(node, &self.owner) }
+    }
+
+    pub fn peek(&self) -> Option<&T> {
+        self.root.map(|root| unsafe { &(*root.as_ptr()).value })
+    }
+
+    pub fn pop(&mut self) -> Option<T> {
+        let root = self.root?;
+        unsafe {
+            self.root = self.merge_pairs((*root.as_ptr()).child);
+            self.len -= 1;
+            Some(take_node(root))
+        }
+    }
+
+    /// Moves every item of other in here in O(1), leaving other empty.
+    /// other's handles now work with self
+    pub fn meld(&mut self, other: &mut Self) {
+        let Some(theirs) = other.root.take() else {
+            return;
+        };
+        self.root = Some(match self.root {
+            Some(mine) => unsafe { self.link(mine, theirs) },
+            None => theirs,
+        });
+        self.len += mem::take(&mut other.len);
+        Owner::merge_into(&other.owner, &self.owner);
+        other.owner = Owner::new();
+    }
+
+    /// false once the item has been popped or removed, or if it's in another heap
+    pub fn contains(&self, handle: &Handle<T>) -> bool {
+        handle.node_in(&self.owner).is_some()
+    }
+
+    pub fn get(&self, handle: &Handle<T>) -> Option<&T> {
+        let node = handle.node_in(&self.owner)?;
+        Some(unsafe { &(*node.as_ptr()).value })
+    }
+
+    /// Replaces the item with new only if new would leave the heap sooner.
+    /// True if it was replaced, false if the handle is stale or new isn't sooner
+    pub fn decrease_key(&mut self, handle: &Handle<T>, new: T) -> bool {
+        let Some(node) = handle.node_in(&self.owner) else {
+            return false;
+        };
+        unsafe {
+            if self.order.compare(&new, &(*node.as_ptr()).value) != Ordering::Less {
+                return false;
+            }
+            (*node.as_ptr()).value = new;
+            // a root has nowhere to go, anything else is cut out and raced against the root
+            let root = self.root.unwrap();
+            if root != node {
+                cut(node);
+                self.root = Some(self.link(root, node));
+            }
+        }
+        true
+    }
+
+    /// Takes the item out wherever it is in the heap, None if it's already gone
+    pub fn remove(&mut self, handle: &Handle<T>) -> Option<T> {
+        let node = handle.node_in(&self.owner)?;
+        let root = self.root.unwrap();
+        if root == node {
+            return self.pop();
+        }
+        unsafe {
+            cut(node);
+            if let Some(rest) = self.merge_pairs((*node.as_ptr()).child) {
+                self.root = Some(self.link(root, rest));
+            }
+            self.len -= 1;
+            Some(take_node(node))
+        }
+    }
+
+    /// every item, in no particular order
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter::new(self.root, self.len)
+    }
+
+    fn push_node(&mut self, value: T) -> NonNull<Node<T>> {
+        let node = Node::alloc(value);
+        self.root = Some(match self.root {
+            Some(root) => unsafe { self.link(root, node) },
+            None => node,
+        });
+        self.len += 1;
+        node
+    }
+
+    /// Makes whichever of a and b goes later the leftmost child of the other, a on a tie,
+    /// and returns the one on top. Both must be roots: no parent, no siblings
+    unsafe fn link(&self, a: NonNull<Node<T>>, b: NonNull<Node<T>>) -> NonNull<Node<T>> {
+        let (top, under) = if self
+            .order
+            .compare(&(*b.as_ptr()).value, &(*a.as_ptr()).value)
+            == Ordering::Less
+        {
+            (b, a)
+        } else {
+            (a, b)
+        };
+        let (t, u) = (top.as_ptr(), under.as_ptr());
+        (*u).next = (*t).child;
+        if let Some(child) = (*t).child {
+            (*child.as_ptr()).up = Some(under);
+        }
+        (*u).up = Some(top);
+        (*t).child = Some(under);
+        top
+    }
+
+    /// The two pass merge behind pop: links first and its siblings in pairs left to right,
+    /// then folds the pairs together right to left. Returns the root of the lot
+    unsafe fn merge_pairs(&self, first: Link<T>) -> Link<T> {
+        // the pairs are chained through next in reverse, so the fold can walk them from the right
+        let mut pairs: Link<T> = None;
+        let mut cur = first;
+        while let Some(a) = cur {
+            let b = (*a.as_ptr()).next;
+            cur = b.and_then(|b| (*b.as_ptr()).next);
+            detach(a);
+            let pair = match b {
+                Some(b) => {
+                    detach(b);
+                    self.link(a, b)
+                }
+                None => a,
+            };
+            (*pair.as_ptr()).next = pairs;
+            pairs = Some(pair);
+        }
+
+        let mut root = None;
+        while let Some(pair) = pairs {
+            pairs = (*pair.as_ptr()).next;
+            (*pair.as_ptr()).next = None;
+            root = Some(match root {
+                Some(root) => self.link(pair, root),
+                None => pair,
+            });
+        }
+        root
+    }
+}
+
+unsafe fn detach<T>(node: NonNull<Node<T>>) {
+    (*node.as_ptr()).up = None;
+    (*node.as_ptr()).next = None;
+}
+
+/// Unhooks node (with its subtree) from its parent and siblings, node must not be the root
+unsafe fn cut<T>(node: NonNull<Node<T>>) {
+    let n = node.as_ptr();
+    let prev = (*n).up.unwrap().as_ptr();
+    let next = (*n).next;
+    if (*prev).child == Some(node) {
+        (*prev).child = next;
+    } else {
+        (*prev).next = next;
+    }
+    if let Some(next) = next {
+        (*next.as_ptr()).up = (*n).up;
+    }
+    detach(node);
+}
+
+// owns its nodes like a Box would, the raw pointers are what stop this being automatic
+unsafe impl<T: Send, C: Send> Send for PairingHeap<T, C> {}
+unsafe impl<T: Sync, C: Sync> Sync for PairingHeap<T, C> {}
+
+impl<T, C> Drop for PairingHeap<T, C> {
+    fn drop(&mut self) {
+        free_all(self.root.take());
+    }
+}
+
+impl<T: fmt::Debug, C: Compare<T>> fmt::Debug for PairingHeap<T, C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<T, C: Compare<T>> Collection for PairingHeap<T, C> {
+    fn len(&self) -> usize {
+        PairingHeap::len(self)
+    }
+
+    fn clear(&mut self) {
+        PairingHeap::clear(self)
+    }
+}
+
+impl<T, C: Compare<T>> Heap<T> for PairingHeap<T, C> {
+    fn push(&mut self, item: T) {
+        PairingHeap::push(self, item)
+    }
+
+    fn pop(&mut self) -> Option<T> {
+        PairingHeap::pop(self)
+    }
+
+    fn peek(&self) -> Option<&T> {
+        PairingHeap::peek(self)
+    }
+}
+
+impl<T, C: Compare<T>> MergeableHeap<T> for PairingHeap<T, C> {
+    fn meld(&mut self, other: &mut Self) {
+        PairingHeap::meld(self, other)
+    }
+}
+
+impl<T, C: Compare<T> + Default> Default for PairingHeap<T, C> {
+    fn default() -> Self {
+        Self::with_comparator(C::default())
+    }
+}
+
+impl<T, C: Compare<T>> Extend<T> for PairingHeap<T, C> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for value in iter {
+            self.push(value);
+        }
+    }
+}
+
+impl<T, C: Compare<T> + Default> FromIterator<T> for PairingHeap<T, C> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut heap = Self::default();
+        heap.extend(iter);
+        heap
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{self, HandleHeap};
+
+    /// checks the heap condition, the back links and the count
+    fn assert_valid<T, C: Compare<T>>(heap: &PairingHeap<T, C>) {
+        let mut count = 0;
+        let mut stack: Vec<NonNull<Node<T>>> = heap.root.into_iter().collect();
+        if let Some(root) = heap.root {
+            unsafe {
+                assert!((*root.as_ptr()).up.is_none());
+                assert!((*root.as_ptr()).next.is_none());
+            }
+        }
+        while let Some(node) = stack.pop() {
+            count += 1;
+            let n = unsafe { &*node.as_ptr() };
+            let mut prev = node;
+            let mut child = n.child;
+            while let Some(c) = child {
+                let c_ref = unsafe { &*c.as_ptr() };
+                assert_eq!(Some(prev), c_ref.up);
+                assert_ne!(
+                    Ordering::Less,
+                    heap.order.compare(&c_ref.value, &n.value),
+                    "a child goes before its parent"
+                );
+                stack.push(c);
+                prev = c;
+                child = c_ref.next;
+            }
+        }
+        assert_eq!(heap.len(), count);
+    }
+
+    fn pop_all<T, C: Compare<T>>(mut heap: PairingHeap<T, C>) -> Vec<T> {
+        std::iter::from_fn(|| heap.pop()).collect()
+    }
+
+    #[test]
+    fn pairing_heap_works() {
+        let mut heap = PairingHeap::new();
+        assert_eq!(None, heap.pop());
+        assert_eq!(None, heap.peek());
+        for v in [5, 3, 8, 1, 9, 2, 7, 3] {
+            heap.push(v);
+            assert_valid(&heap);
+        }
+        assert_eq!(8, heap.len());
+        assert_eq!(Some(&1), heap.peek());
+        assert_eq!(Some(1), heap.pop());
+        assert_valid(&heap);
+        assert_eq!(vec![2, 3, 3, 5, 7, 8, 9], pop_all(heap));
+
+        let max: PairingHeap<i32, Max> = (0..100).map(|i| (i * 37) % 101).collect();
+        let mut expected: Vec<i32> = max.iter().copied().collect();
+        expected.sort_by(|a, b| b.cmp(a));
+        assert_eq!(expected, pop_all(max));
+
+        let mut words = PairingHeap::by_key(|w: &&str| w.len());
+        words.extend(["pear", "fig", "banana"]);
+        assert_eq!(Some("fig"), words.pop());
+        assert_eq!("[\"pear\", \"banana\"]", format!("{words:?}"));
+    }
+
+    #[test]
+    fn meld() {
+        let mut a: PairingHeap<i32> = [4, 9, 1].into_iter().collect();
+        let mut b = PairingHeap::new();
+        let h = b.push_with_handle(6);
+        b.extend([0, 12]);
+        a.meld(&mut b);
+        assert_valid(&a);
+        assert!(b.is_empty());
+        assert_eq!(None, b.pop());
+        // the handle followed its item
+        assert!(!b.contains(&h));
+        assert_eq!(Some(&6), a.get(&h));
+
+        // b is usable again and its new items stay its own
+        let h2 = b.push_with_handle(3);
+        assert!(!a.contains(&h2));
+        a.meld(&mut PairingHeap::new());
+        assert_eq!(6, a.len());
+        assert!(a.decrease_key(&h, -1));
+        assert_eq!(vec![-1, 0, 1, 4, 9, 12], pop_all(a));
+        assert_eq!(Some(&3), b.get(&h2));
+    }
+
+    #[test]
+    fn handles() {
+        let mut heap = PairingHeap::new();
+        let handles: Vec<_> = (0..20).map(|i| heap.push_with_handle(i * 10)).collect();
+        assert!(heap.decrease_key(&handles[15], 5));
+        assert!(!heap.decrease_key(&handles[15], 5));
+        assert!(!heap.decrease_key(&handles[3], 40));
+        assert_valid(&heap);
+        assert_eq!(Some(0), heap.pop());
+        assert!(!heap.contains(&handles[0]));
+        assert!(!heap.decrease_key(&handles[0], -5));
+        assert_eq!(None, heap.remove(&handles[0]));
+
+        assert_eq!(Some(5), heap.remove(&handles[15]));
+        assert_eq!(Some(90), heap.remove(&handles[9]));
+        assert_valid(&heap);
+        // removing the root
+        assert_eq!(Some(10), heap.remove(&handles[1]));
+        assert_eq!(Some(&20), heap.peek());
+
+        let other = PairingHeap::new();
+        assert!(!other.contains(&handles[2]));
+        assert_eq!(None, other.get(&handles[2]));
+
+        heap.clear();
+        assert!(heap.is_empty());
+        assert!(!heap.contains(&handles[2]));
+    }
+
+    impl HandleHeap for PairingHeap<i64> {
+        fn push_with_handle(&mut self, value: i64) -> Handle<i64> {
+            PairingHeap::push_with_handle(self, value)
+        }
+
+        fn pop(&mut self) -> Option<i64> {
+            PairingHeap::pop(self)
+        }
+
+        fn len(&self) -> usize {
+            PairingHeap::len(self)
+        }
+
+        fn contains(&self, handle: &Handle<i64>) -> bool {
+            PairingHeap::contains(self, handle)
+        }
+
+        fn get(&self, handle: &Handle<i64>) -> Option<&i64> {
+            PairingHeap::get(self, handle)
+        }
+
+        fn decrease_key(&mut self, handle: &Handle<i64>, new: i64) -> bool {
+            PairingHeap::decrease_key(self, handle, new)
+        }
+
+        fn remove(&mut self, handle: &Handle<i64>) -> Option<i64> {
+            PairingHeap::remove(self, handle)
+        }
+
+        fn assert_valid(&self) {
+            assert_valid(self)
+        }
+    }
+
+    #[test]
+    fn matches_a_sorted_model() {
+        test_util::matches_a_sorted_model::<PairingHeap<i64>>(0x9E37_79B9_7F4A_7C15);
+    }
+
+    #[test]
+    fn long_lists_drop() {
+        // pushing in order hangs every item off the root as one long sibling list
+        let n = if cfg!(miri) { 1_000 } else { 200_000 };
+        let mut heap: PairingHeap<u32> = (0..n).collect();
+        assert_eq!(Some(0), heap.pop());
+        drop(heap);
+        let heap: PairingHeap<Box<u32>> = (0..1000).map(Box::new).collect();
+        drop(heap);
+    }
+}
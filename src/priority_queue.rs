@@ -1,124 +1,489 @@
 #![allow(dead_code)]
-use std::collections::VecDeque;
+use std::cmp::Ordering;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::iter::FusedIterator;
+use std::ops::{Deref, DerefMut};
+
+use crate::traits::{Collection, Heap, MergeableHeap};
 
 /**
- * Priority Queue aka MinHeap
- * Binary Tree-like data structure
+ * Priority Queue aka Heap
+ * Tree-like data structure, binary unless told otherwise
  * Balanced: at most the height difference of nodes will be 1
  * Has heap condition:
  * in MinHeap: every Node under current Node is larger
  * in MaxHeap: every Node under current Node is smaller
- *  the root O(log N)
+ * Pop the root O(log N)
  * Push to the bottom - bubbles up to find correct place, O(log N)
+ * What comes out first is up to a Compare strategy:
+ * Min (`new`) - smallest first, the default
+ * Max (`new_max`) - largest first
+ * ByKey (`by_key`) - smallest key first
+ * any Fn(&T, &T) -> Ordering (`with_comparator`) - whatever it orders Less first,
+ *  e.g. `|a: &f64, b: &f64| a.total_cmp(b)` for floats, which aren't Ord
+ * Stored 0-indexed in a Vec, D children per node (the arity, 2 by default):
+ *  children of i are Di + 1 ..= Di + D, parent is (i - 1) / D
+ * D = 4 or 8 make the tree shallower, so a pop walks fewer levels and the children it compares
+ *  sit next to each other in memory; each level costs D - 1 more comparisons.
+ *  `PriorityQueue::<T, Min, 4>::default()` or `::d_ary(order)` for a 4-ary heap,
+ *  see benches/priority_queue.rs
+ * Sifting up and down is a loop, no recursion
+ * from_vec/collect build the heap bottom up in O(N) (Floyd), heapsort sorts a slice in place
+ *  with the same heapify_down
  */
-#[derive(Debug)]
-struct PriorityQueue<T> {
-    queue: VecDeque<T>,
+pub struct PriorityQueue<T, C = Min, const D: usize = 2> {
+    queue: Vec<T>,
+    order: C,
+}
+
+/// Decides which of two items leaves the queue first: the one ordered Less
+pub trait Compare<T> {
+    fn compare(&self, a: &T, b: &T) -> Ordering;
+}
+
+/// smallest first
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Min;
+
+/// largest first
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Max;
+
+/// smallest key first, wrap the key in `std::cmp::Reverse` for largest first
+#[derive(Clone, Copy, Debug)]
+pub struct ByKey<F>(pub F);
+
+impl<T: Ord> Compare<T> for Min {
+    fn compare(&self, a: &T, b: &T) -> Ordering {
+        a.cmp(b)
+    }
+}
+
+impl<T: Ord> Compare<T> for Max {
+    fn compare(&self, a: &T, b: &T) -> Ordering {
+        b.cmp(a)
+    }
+}
+
+impl<T, K: Ord, F: Fn(&T) -> K> Compare<T> for ByKey<F> {
+    fn compare(&self, a: &T, b: &T) -> Ordering {
+        (self.0)(a).cmp(&(self.0)(b))
+    }
+}
+
+impl<T, F: Fn(&T, &T) -> Ordering> Compare<T> for F {
+    fn compare(&self, a: &T, b: &T) -> Ordering {
+        self(a, b)
+    }
 }
 
-impl<T: Default + PartialOrd> PriorityQueue<T> {
+impl<T: Ord> PriorityQueue<T> {
+    /// a min heap
     pub fn new() -> Self {
-        let mut queue = VecDeque::with_capacity(1);
-        queue.push_back(T::default());
+        Self::with_comparator(Min)
+    }
+}
+
+impl<T: Ord> PriorityQueue<T, Max> {
+    pub fn new_max() -> Self {
+        Self::with_comparator(Max)
+    }
+}
+
+impl<T, K: Ord, F: Fn(&T) -> K> PriorityQueue<T, ByKey<F>> {
+    pub fn by_key(key: F) -> Self {
+        Self::with_comparator(ByKey(key))
+    }
+}
+
+impl<T: Ord> PriorityQueue<T> {
+    /// a min heap of vec's items, built bottom up in O(N)
+    pub fn from_vec(vec: Vec<T>) -> Self {
+        Self::from_vec_with_comparator(vec, Min)
+    }
+}
+
+impl<T, C: Compare<T>> PriorityQueue<T, C> {
+    pub fn with_comparator(order: C) -> Self {
+        Self::d_ary(order)
+    }
+
+    /// Floyd's heapify: every parent, last to first, sifts down into the heap below it.
+    /// O(N) against O(N log N) for pushing one at a time
+    pub fn from_vec_with_comparator(vec: Vec<T>, order: C) -> Self {
+        Self::from_vec_d_ary(vec, order)
+    }
+}
+
+impl<T, C: Compare<T>, const D: usize> PriorityQueue<T, C, D> {
+    const ARITY: () = assert!(D >= 2, "a heap needs at least 2 children per node");
+
+    /// with_comparator for any arity, e.g. `PriorityQueue::<u64, Max, 4>::d_ary(Max)`
+    pub fn d_ary(order: C) -> Self {
+        let () = Self::ARITY;
         PriorityQueue {
-            queue,
+            queue: Vec::new(),
+            order,
         }
     }
 
+    /// from_vec_with_comparator for any arity, O(N) the same way
+    pub fn from_vec_d_ary(vec: Vec<T>, order: C) -> Self {
+        let mut pq = Self::d_ary(order);
+        pq.queue = vec;
+        build_heap::<T, C, D>(&mut pq.queue, &pq.order);
+        pq
+    }
+
+    pub fn comparator(&self) -> &C {
+        &self.order
+    }
+
     pub fn len(&self) -> usize {
-        self.queue.len() - 1
+        self.queue.len()
     }
 
-    pub fn peak(&self) -> Option<&T> {
-        if self.len() > 0 {
-            Some(&self.queue[1])
-        } else {
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.queue.clear();
+    }
+
+    pub fn peek(&self) -> Option<&T> {
+        self.queue.first()
+    }
+
+    /// The front item, mutably. The heap is put back in order when the PeekMut is dropped
+    pub fn peek_mut(&mut self) -> Option<PeekMut<'_, T, C, D>> {
+        if self.is_empty() {
             None
+        } else {
+            Some(PeekMut { pq: self })
         }
     }
 
     pub fn pop(&mut self) -> Option<T> {
-        if self.len() == 0 {
+        if self.is_empty() {
             None
-        } else if self.len() == 1 {
-            self.queue.remove(1)
         } else {
-            self.queue.swap(1, self.len());
-            let v = self.queue.pop_back();
-            self.heapify_down(1);
-            v
+            // the last item takes the root's place and sinks
+            let v = self.queue.swap_remove(0);
+            heapify_down::<T, C, D>(&mut self.queue, 0, &self.order);
+            Some(v)
         }
     }
 
     pub fn push(&mut self, value: T) {
         let i = self.queue.len();
-        self.queue.push_back(value);
-        self.heapify_up(i);
+        self.queue.push(value);
+        heapify_up::<T, C, D>(&mut self.queue, i, &self.order);
+    }
+
+    /// Moves every item out of other into self, leaving other empty.
+    /// Small batches are pushed up one by one, big ones rebuild the whole heap in O(N)
+    pub fn append(&mut self, other: &mut Self) {
+        let start = self.queue.len();
+        let added = other.queue.len();
+        self.queue.append(&mut other.queue);
+        // pushing costs about added * log2(len), rebuilding about 2 * len
+        let len = self.queue.len();
+        if added * (usize::BITS - len.leading_zeros()) as usize > 2 * len {
+            build_heap::<T, C, D>(&mut self.queue, &self.order);
+        } else {
+            for i in start..len {
+                heapify_up::<T, C, D>(&mut self.queue, i, &self.order);
+            }
+        }
     }
 
-    fn left(&self, i: usize) -> (usize, Option<&T>) {
-        let l_i = i * 2;
-        (l_i, self.queue.get(l_i))
+    /// every item, in no particular order
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.queue.iter()
     }
 
-    fn right(&self, i: usize) -> (usize, Option<&T>) {
-        let r_i = (i * 2) + 1;
-        (r_i, self.queue.get(r_i))
+    /// the items in heap order, no sorting done
+    pub fn into_vec(self) -> Vec<T> {
+        self.queue
     }
 
-    fn min_child (&self, i: usize) -> (usize, Option<&T>) {
-        let (l_i, l) = self.left(i);
-        let (r_i, r) = self.right(i);
+    /// the items in the order pop would hand them out, sorted in place
+    pub fn into_sorted_vec(mut self) -> Vec<T> {
+        // sorting the heap leaves the first item out at the back
+        sort_heap::<T, C, D>(&mut self.queue, &self.order);
+        self.queue.reverse();
+        self.queue
+    }
 
-        match (l, r) {
-            (Some(l), Some(r)) if l > r => (r_i, Some(r)),
-            (Some(l), _) => (l_i, Some(l)),
-            _ => (0, None),
+    /// Pops items in order as it's iterated, dropping the DrainSorted empties the queue
+    pub fn drain_sorted(&mut self) -> DrainSorted<'_, T, C, D> {
+        DrainSorted { pq: self }
+    }
+}
+
+/// true if a should leave the queue before b
+fn before<T, C: Compare<T>>(a: &T, b: &T, order: &C) -> bool {
+    order.compare(a, b) == Ordering::Less
+}
+
+/// the child of i that would leave the queue first
+fn first_child<T, C: Compare<T>, const D: usize>(heap: &[T], i: usize, order: &C) -> Option<usize> {
+    let first = i.checked_mul(D)?.checked_add(1)?;
+    if first >= heap.len() {
+        return None;
+    }
+    let last = heap.len().min(first.saturating_add(D));
+    let mut best = first;
+    for c_i in first + 1..last {
+        if before(&heap[c_i], &heap[best], order) {
+            best = c_i;
         }
     }
+    Some(best)
+}
 
-    /// Panics if i is out of bounds
-    fn heapify_down(&mut self, i: usize) {
-        let v = &self.queue[i];
-        // if min child is < v, swap with min child
-        if let (c_i, Some(child)) = self.min_child(i) {
-            if child < v {
-                self.queue.swap(c_i, i);
-                self.heapify_down(c_i);
-            }
+/// Sinks the item at i until its children don't go before it.
+/// Shared by the queue and heapsort, so it works on any slice
+fn heapify_down<T, C: Compare<T>, const D: usize>(heap: &mut [T], mut i: usize, order: &C) {
+    // while the first child goes before it, swap with that child
+    while let Some(c_i) = first_child::<T, C, D>(heap, i, order) {
+        if !before(&heap[c_i], &heap[i], order) {
+            break;
+        }
+        heap.swap(c_i, i);
+        i = c_i;
+    }
+}
+
+fn heapify_up<T, C: Compare<T>, const D: usize>(heap: &mut [T], mut i: usize, order: &C) {
+    // while it goes before its parent, swap with the parent
+    while i != 0 {
+        let parent_i = (i - 1) / D;
+        if !before(&heap[i], &heap[parent_i], order) {
+            break;
         }
+        heap.swap(i, parent_i);
+        i = parent_i;
     }
+}
 
-    fn heapify_up(&mut self, i: usize) {
-        let parent_i = i/2;
-        if parent_i != 0 {
-            // compare to parent recursively and (maybe) swap
-            if self.queue[i] < self.queue[parent_i] {
-                self.queue.swap(i, parent_i);
-                self.heapify_up(parent_i);
-            }
+/// turns any slice into a heap, only the first ceil((len - 1) / D) items have children
+fn build_heap<T, C: Compare<T>, const D: usize>(heap: &mut [T], order: &C) {
+    for i in (0..heap.len().saturating_sub(1).div_ceil(D)).rev() {
+        heapify_down::<T, C, D>(heap, i, order);
+    }
+}
+
+/// Heapsort: builds a heap, then keeps swapping its front to the end of the shrinking heap,
+/// so the slice ends up in reverse pop order
+fn sort_heap<T, C: Compare<T>, const D: usize>(v: &mut [T], order: &C) {
+    build_heap::<T, C, D>(v, order);
+    for end in (1..v.len()).rev() {
+        v.swap(0, end);
+        heapify_down::<T, C, D>(&mut v[..end], 0, order);
+    }
+}
+
+/// Sorts v smallest first in place, O(N log N) with no allocation. Not stable
+pub fn heapsort<T: Ord>(v: &mut [T]) {
+    sort_heap::<T, Max, 2>(v, &Max);
+}
+
+/// Sorts v in place so compare says each item is not Greater than the next. Not stable
+pub fn heapsort_by<T, F: Fn(&T, &T) -> Ordering>(v: &mut [T], compare: F) {
+    // a heap that pops the largest first leaves the largest at the back
+    sort_heap::<T, _, 2>(v, &|a: &T, b: &T| compare(b, a));
+}
+
+/// The front of a PriorityQueue, mutably, from peek_mut
+pub struct PeekMut<'a, T, C: Compare<T> = Min, const D: usize = 2> {
+    pq: &'a mut PriorityQueue<T, C, D>,
+}
+
+impl<T, C: Compare<T>, const D: usize> PeekMut<'_, T, C, D> {
+    /// takes the item out of the queue instead of putting it back
+    pub fn pop(this: Self) -> T {
+        // pop leaves a valid heap, the sift on drop then finds nothing to do
+        this.pq.pop().unwrap()
+    }
+}
+
+impl<T, C: Compare<T>, const D: usize> Deref for PeekMut<'_, T, C, D> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        &self.pq.queue[0]
+    }
+}
+
+impl<T, C: Compare<T>, const D: usize> DerefMut for PeekMut<'_, T, C, D> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.pq.queue[0]
+    }
+}
+
+impl<T, C: Compare<T>, const D: usize> Drop for PeekMut<'_, T, C, D> {
+    /// the front may have changed priority, sink it back into place
+    fn drop(&mut self) {
+        heapify_down::<T, C, D>(&mut self.pq.queue, 0, &self.pq.order);
+    }
+}
+
+/// Pops a PriorityQueue in order, from drain_sorted
+pub struct DrainSorted<'a, T, C: Compare<T> = Min, const D: usize = 2> {
+    pq: &'a mut PriorityQueue<T, C, D>,
+}
+
+impl<T, C: Compare<T>, const D: usize> Iterator for DrainSorted<'_, T, C, D> {
+    type Item = T;
+    fn next(&mut self) -> Option<T> {
+        self.pq.pop()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.pq.len(), Some(self.pq.len()))
+    }
+}
+
+impl<T, C: Compare<T>, const D: usize> ExactSizeIterator for DrainSorted<'_, T, C, D> {}
+
+impl<T, C: Compare<T>, const D: usize> FusedIterator for DrainSorted<'_, T, C, D> {}
+
+impl<T, C: Compare<T>, const D: usize> Drop for DrainSorted<'_, T, C, D> {
+    fn drop(&mut self) {
+        self.pq.clear();
+    }
+}
+
+impl<T: Ord, C, const D: usize> PriorityQueue<T, C, D> {
+    /// every value smallest first, whatever order the queue pops them in
+    fn sorted(&self) -> Vec<&T> {
+        let mut values: Vec<&T> = self.queue.iter().collect();
+        values.sort();
+        values
+    }
+}
+
+impl<T: fmt::Debug, C, const D: usize> fmt::Debug for PriorityQueue<T, C, D> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.queue.iter()).finish()
+    }
+}
+
+impl<T: Clone, C: Clone, const D: usize> Clone for PriorityQueue<T, C, D> {
+    fn clone(&self) -> Self {
+        PriorityQueue {
+            queue: self.queue.clone(),
+            order: self.order.clone(),
         }
     }
 }
 
+impl<T, C: Compare<T>, const D: usize> Collection for PriorityQueue<T, C, D> {
+    fn len(&self) -> usize {
+        PriorityQueue::len(self)
+    }
+
+    fn clear(&mut self) {
+        PriorityQueue::clear(self)
+    }
+}
+
+impl<T, C: Compare<T>, const D: usize> Heap<T> for PriorityQueue<T, C, D> {
+    fn push(&mut self, item: T) {
+        PriorityQueue::push(self, item)
+    }
+
+    fn pop(&mut self) -> Option<T> {
+        PriorityQueue::pop(self)
+    }
+
+    fn peek(&self) -> Option<&T> {
+        PriorityQueue::peek(self)
+    }
+}
+
+/// O(N + M) at worst, the array has to be re-heaped
+impl<T, C: Compare<T>, const D: usize> MergeableHeap<T> for PriorityQueue<T, C, D> {
+    fn meld(&mut self, other: &mut Self) {
+        PriorityQueue::append(self, other)
+    }
+}
+
+impl<T, C: Compare<T> + Default, const D: usize> Default for PriorityQueue<T, C, D> {
+    fn default() -> Self {
+        Self::d_ary(C::default())
+    }
+}
+
+impl<T, C: Compare<T> + Default, const D: usize> From<Vec<T>> for PriorityQueue<T, C, D> {
+    fn from(vec: Vec<T>) -> Self {
+        Self::from_vec_d_ary(vec, C::default())
+    }
+}
+
+impl<T, C: Compare<T> + Default, const D: usize> FromIterator<T> for PriorityQueue<T, C, D> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        Self::from(iter.into_iter().collect::<Vec<T>>())
+    }
+}
+
+impl<T, C: Compare<T>, const D: usize> Extend<T> for PriorityQueue<T, C, D> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for value in iter {
+            self.push(value);
+        }
+    }
+}
+
+// two queues are equal if they hold the same values, however the heaps are laid out,
+// and compare smallest value first whichever end each one pops from
+impl<T: Ord, C, const D: usize> PartialEq for PriorityQueue<T, C, D> {
+    fn eq(&self, other: &Self) -> bool {
+        self.queue.len() == other.queue.len() && self.sorted() == other.sorted()
+    }
+}
+
+impl<T: Ord, C, const D: usize> Eq for PriorityQueue<T, C, D> {}
+
+impl<T: Ord + Hash, C, const D: usize> Hash for PriorityQueue<T, C, D> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.sorted().hash(state);
+    }
+}
+
+impl<T: Ord, C, const D: usize> PartialOrd for PriorityQueue<T, C, D> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T: Ord, C, const D: usize> Ord for PriorityQueue<T, C, D> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.sorted().cmp(&other.sorted())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::cmp::Reverse;
 
     #[test]
     fn pq_works() {
         let mut pq = PriorityQueue::new();
-        assert_eq!(None, pq.peak());
+        assert_eq!(None, pq.peek());
         assert_eq!(None, pq.pop());
         pq.push(50);
-        assert_eq!(Some(&50), pq.peak());
+        assert_eq!(Some(&50), pq.peek());
         pq.push(75);
         pq.push(100);
         dbg!(&pq);
-        assert_eq!(Some(&50), pq.peak());
+        assert_eq!(Some(&50), pq.peek());
         pq.push(30);
         dbg!(&pq);
-        assert_eq!(Some(&30), pq.peak());
+        assert_eq!(Some(&30), pq.peek());
         assert_eq!(Some(30), pq.pop());
         dbg!(&pq);
         assert_eq!(Some(50), pq.pop());
@@ -127,4 +492,270 @@ mod tests {
         assert_eq!(None, pq.pop());
     }
 
+    #[test]
+    fn is_a_collection() {
+        let mut pq: PriorityQueue<i32> = [3, 1, 2].into_iter().collect();
+        assert_eq!(3, Collection::len(&pq));
+        Collection::clear(&mut pq);
+        assert!(Collection::is_empty(&pq));
+        assert_eq!(None, pq.peek());
+        pq.push(4);
+        assert_eq!(Some(4), pq.pop());
+    }
+
+    #[test]
+    fn std_traits_match_binary_heap() {
+        use std::collections::hash_map::DefaultHasher;
+        use std::collections::BinaryHeap;
+
+        fn hash<T: Hash>(t: &T) -> u64 {
+            let mut h = DefaultHasher::new();
+            t.hash(&mut h);
+            h.finish()
+        }
+
+        let samples = [
+            vec![],
+            vec![3],
+            vec![3, 1, 2],
+            vec![2, 3, 1],
+            vec![1, 2, 2],
+            vec![5, 0],
+        ];
+        for xs in &samples {
+            let mut pq: PriorityQueue<i32> = xs.iter().copied().collect();
+            let mut heap: BinaryHeap<Reverse<i32>> = xs.iter().copied().map(Reverse).collect();
+            pq.extend([4, -1]);
+            heap.extend([Reverse(4), Reverse(-1)]);
+            while let Some(Reverse(v)) = heap.pop() {
+                assert_eq!(Some(v), pq.pop());
+            }
+            assert_eq!(None, pq.pop());
+
+            for ys in &samples {
+                let (a, b): (PriorityQueue<i32>, PriorityQueue<i32>) =
+                    (xs.iter().copied().collect(), ys.iter().copied().collect());
+                let (mut sorted_xs, mut sorted_ys) = (xs.clone(), ys.clone());
+                sorted_xs.sort();
+                sorted_ys.sort();
+                assert_eq!(sorted_xs == sorted_ys, a == b);
+                assert_eq!(sorted_xs.cmp(&sorted_ys), a.cmp(&b));
+                if a == b {
+                    assert_eq!(hash(&a), hash(&b));
+                }
+            }
+        }
+        assert_eq!(PriorityQueue::<i32>::default(), PriorityQueue::new());
+    }
+
+    fn pop_all<T, C: Compare<T>, const D: usize>(mut pq: PriorityQueue<T, C, D>) -> Vec<T> {
+        std::iter::from_fn(|| pq.pop()).collect()
+    }
+
+    #[test]
+    fn max_heap() {
+        let mut pq = PriorityQueue::new_max();
+        pq.extend([3, 9, 1, 9, 4]);
+        assert_eq!(Some(&9), pq.peek());
+        assert_eq!(vec![9, 9, 4, 3, 1], pop_all(pq));
+
+        // max and min queues of the same values are equal
+        let max: PriorityQueue<i32, Max> = [1, 2].into_iter().collect();
+        let min: PriorityQueue<i32> = [2, 1].into_iter().collect();
+        assert_eq!(max.sorted(), min.sorted());
+    }
+
+    #[test]
+    fn by_key() {
+        let mut pq = PriorityQueue::by_key(|s: &&str| s.len());
+        pq.extend(["three", "a", "to", "four"]);
+        assert_eq!(Some(&"a"), pq.peek());
+        assert_eq!(vec!["a", "to", "four", "three"], pop_all(pq));
+
+        let mut longest = PriorityQueue::by_key(|s: &&str| Reverse(s.len()));
+        longest.extend(["three", "a", "to"]);
+        assert_eq!(Some("three"), longest.pop());
+    }
+
+    #[test]
+    fn floats_with_a_comparator() {
+        // NaN sorts after every other value under total_cmp, so it can't scramble the heap
+        let mut pq = PriorityQueue::with_comparator(|a: &f64, b: &f64| a.total_cmp(b));
+        pq.extend([2.5, f64::NAN, -1.0, 0.0, f64::INFINITY, -0.0]);
+        let out = pop_all(pq);
+        assert_eq!(vec![-1.0, -0.0, 0.0, 2.5, f64::INFINITY], out[..5]);
+        assert!(out[5].is_nan());
+    }
+
+    #[test]
+    fn no_default_needed() {
+        #[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+        struct Job(u8);
+
+        let mut pq = PriorityQueue::new();
+        pq.push(Job(2));
+        pq.push(Job(1));
+        assert_eq!(Some(Job(1)), pq.pop());
+        assert_eq!(1, pq.len());
+        assert_eq!("[Job(2)]", format!("{:?}", pq));
+    }
+
+    #[test]
+    fn matches_sorting_with_any_comparator() {
+        let values: Vec<i64> = (0..200).map(|i| (i * 7919) % 101 - 50).collect();
+        let by_abs = PriorityQueue::by_key(|v: &i64| v.abs());
+        let mut pq = by_abs;
+        pq.extend(values.iter().copied());
+        let popped = pop_all(pq);
+        let mut expected = values.clone();
+        expected.sort_by_key(|v| v.abs());
+        assert_eq!(
+            expected.iter().map(|v| v.abs()).collect::<Vec<_>>(),
+            popped.iter().map(|v| v.abs()).collect::<Vec<_>>()
+        );
+
+        let mut pq = PriorityQueue::with_comparator(|a: &i64, b: &i64| b.cmp(a));
+        pq.extend(values.iter().copied());
+        expected.sort_by(|a, b| b.cmp(a));
+        assert_eq!(expected, pop_all(pq));
+    }
+
+    fn assert_heap<T, C: Compare<T>, const D: usize>(pq: &PriorityQueue<T, C, D>) {
+        for i in 1..pq.queue.len() {
+            assert!(!before(&pq.queue[i], &pq.queue[(i - 1) / D], &pq.order));
+        }
+    }
+
+    #[test]
+    fn floyd_heapify_builds_valid_heaps() {
+        for n in 0..40 {
+            let values: Vec<i32> = (0..n).map(|i| (i * 37) % 11).collect();
+            let pq = PriorityQueue::from_vec(values.clone());
+            assert_heap(&pq);
+            let max = PriorityQueue::from_vec_with_comparator(values.clone(), Max);
+            assert_heap(&max);
+            let collected: PriorityQueue<i32> = values.iter().copied().collect();
+            assert_heap(&collected);
+
+            let mut sorted = values.clone();
+            sorted.sort();
+            assert_eq!(sorted, pq.into_sorted_vec());
+            sorted.reverse();
+            assert_eq!(sorted, max.into_sorted_vec());
+            assert_eq!(n as usize, collected.into_vec().len());
+        }
+    }
+
+    #[test]
+    fn append() {
+        for (a, b) in [(0, 0), (1, 30), (30, 1), (20, 20), (100, 3)] {
+            let mut x: PriorityQueue<i32> = (0..a).map(|i| (i * 7) % 13).collect();
+            let mut y: PriorityQueue<i32> = (0..b).map(|i| (i * 5) % 17).collect();
+            let mut expected: Vec<i32> = x.iter().chain(y.iter()).copied().collect();
+            expected.sort();
+            x.append(&mut y);
+            assert!(y.is_empty());
+            assert_eq!(expected, pop_all(x));
+        }
+    }
+
+    #[test]
+    fn drain_sorted() {
+        let mut pq = PriorityQueue::new_max();
+        pq.extend([4, 8, 1, 5]);
+        let mut d = pq.drain_sorted();
+        assert_eq!(4, d.len());
+        assert_eq!(Some(8), d.next());
+        assert_eq!(Some(5), d.next());
+        drop(d);
+        // dropping the drain empties the queue
+        assert!(pq.is_empty());
+        pq.push(3);
+        assert_eq!(vec![3], pq.drain_sorted().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn peek_mut_sifts_on_drop() {
+        let mut pq: PriorityQueue<i32> = [5, 1, 8, 3].into_iter().collect();
+        assert_eq!(None, PriorityQueue::<i32>::new().peek_mut().map(|p| *p));
+        {
+            let mut top = pq.peek_mut().unwrap();
+            assert_eq!(1, *top);
+            *top = 10;
+        }
+        assert_eq!(Some(&3), pq.peek());
+        // reading through it leaves the order alone
+        assert_eq!(3, *pq.peek_mut().unwrap());
+        assert_eq!(3, PeekMut::pop(pq.peek_mut().unwrap()));
+        assert_eq!(vec![5, 8, 10], pop_all(pq));
+    }
+
+    #[test]
+    fn heapsort_slices() {
+        let mut empty: [i32; 0] = [];
+        heapsort(&mut empty);
+        for n in 0..50 {
+            let mut v: Vec<i64> = (0..n).map(|i| (i * 7919) % 23 - 11).collect();
+            let mut expected = v.clone();
+            expected.sort();
+            heapsort(&mut v);
+            assert_eq!(expected, v);
+
+            heapsort_by(&mut v, |a, b| b.abs().cmp(&a.abs()));
+            expected.sort_by_key(|x| std::cmp::Reverse(x.abs()));
+            let abs = |v: &[i64]| v.iter().map(|x| x.abs()).collect::<Vec<_>>();
+            assert_eq!(abs(&expected), abs(&v));
+        }
+        let mut words = ["pear", "fig", "apple"];
+        heapsort(&mut words);
+        assert_eq!(["apple", "fig", "pear"], words);
+    }
+
+    fn d_ary<const D: usize>() {
+        let mut seed = 3u32;
+        let mut next = move || {
+            seed = seed.wrapping_mul(1664525).wrapping_add(1013904223);
+            seed >> 16
+        };
+        let values: Vec<u32> = (0..500).map(|_| next() % 200).collect();
+        let mut sorted = values.clone();
+        sorted.sort();
+
+        let mut pq = PriorityQueue::<u32, Min, D>::default();
+        for &v in &values {
+            pq.push(v);
+        }
+        assert_heap(&pq);
+        for &v in &sorted[..100] {
+            assert_eq!(Some(v), pq.pop());
+        }
+        assert_heap(&pq);
+        assert_eq!(sorted[100..], pop_all(pq));
+
+        for n in [0, 1, 2, D, D + 1, D * D + 2, 100] {
+            let built = PriorityQueue::<u32, Min, D>::from(values[..n].to_vec());
+            assert_heap(&built);
+            let mut expected = values[..n].to_vec();
+            expected.sort();
+            assert_eq!(expected, built.into_sorted_vec());
+        }
+
+        let mut max = PriorityQueue::<u32, Max, D>::from_vec_d_ary(values.clone(), Max);
+        let mut more: PriorityQueue<u32, Max, D> = (0..300).collect();
+        max.append(&mut more);
+        assert_heap(&max);
+        *max.peek_mut().unwrap() = 0;
+        assert_heap(&max);
+        assert_eq!(Some(&298), max.peek());
+        assert_eq!(800, max.drain_sorted().count());
+    }
+
+    #[test]
+    fn d_ary_heaps() {
+        d_ary::<2>();
+        d_ary::<3>();
+        d_ary::<4>();
+        d_ary::<8>();
+        d_ary::<16>();
+    }
 }
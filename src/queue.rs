@@ -5,16 +5,24 @@
  * deque - removes from head
  * peek - looks at next value in queue without consuming
  */
-use std::fmt::Debug;
+use std::cmp::Ordering;
+use std::hash::{Hash, Hasher};
 
 use crate::doubly_linked_list::DubLinkedList;
+use crate::traits::{Collection, Fifo};
 
 pub struct Queue<T> {
     pub size: usize,
     list: DubLinkedList<T>,
 }
 
-impl<T: Debug> Queue<T> {
+impl<T> Default for Queue<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Queue<T> {
     pub fn new() -> Queue<T> {
         Queue {
             size: 0,
@@ -34,6 +42,18 @@ impl<T: Debug> Queue<T> {
         self.list.pop_front()
     }
 
+    pub fn len(&self) -> usize {
+        self.size
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.size == 0
+    }
+
+    pub fn clear(&mut self) {
+        while self.deque().is_some() {}
+    }
+
     pub fn peek(&self) -> Option<T>
     where
         T: Clone,
@@ -42,6 +62,69 @@ impl<T: Debug> Queue<T> {
     }
 }
 
+impl<T> Collection for Queue<T> {
+    fn len(&self) -> usize {
+        self.size
+    }
+
+    fn clear(&mut self) {
+        Queue::clear(self)
+    }
+}
+
+impl<T> Fifo<T> for Queue<T> {
+    fn enqueue(&mut self, item: T) {
+        self.queue(item)
+    }
+
+    fn dequeue(&mut self) -> Option<T> {
+        self.deque()
+    }
+}
+
+impl<T> FromIterator<T> for Queue<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut q = Queue::new();
+        q.extend(iter);
+        q
+    }
+}
+
+impl<T> Extend<T> for Queue<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for item in iter {
+            self.queue(item);
+        }
+    }
+}
+
+// comparisons go from the head of the queue to the tail
+impl<T: PartialEq> PartialEq for Queue<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.list == other.list
+    }
+}
+
+impl<T: Eq> Eq for Queue<T> {}
+
+impl<T: Hash> Hash for Queue<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.list.hash(state);
+    }
+}
+
+impl<T: PartialOrd> PartialOrd for Queue<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        self.list.partial_cmp(&other.list)
+    }
+}
+
+impl<T: Ord> Ord for Queue<T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.list.cmp(&other.list)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
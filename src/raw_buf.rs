@@ -0,0 +1,245 @@
+/*
+RawBuf:
+ * the allocation behind MyVec, Stack and RingBuffer, like RawVec in the Rustonomicon:
+   https://doc.rust-lang.org/nomicon/vec/vec-raw.html
+ * owns a pointer and a capacity, nothing about which slots hold live items
+ * grows by realloc, so the first min(old, new) slots survive a resize
+ * zero sized types never allocate, their capacity is usize::MAX
+ * zero capacity never allocates either, the pointer is dangling
+ * dropping frees the memory but never drops items, that's the owning collection's job
+ * the memory comes from an Allocator, Global unless the collection was made with a `*_in` constructor
+*/
+use std::alloc::{self, Layout};
+use std::fmt;
+use std::mem;
+use std::ptr::NonNull;
+
+use crate::allocator::{Allocator, Global};
+
+/// Why a reserve couldn't make room
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TryReserveError {
+    /// the new capacity is more than usize (or isize::MAX bytes) can hold
+    CapacityOverflow,
+    /// the allocator returned null
+    AllocError { layout: Layout },
+}
+
+impl fmt::Display for TryReserveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TryReserveError::CapacityOverflow => write!(f, "capacity overflow"),
+            TryReserveError::AllocError { layout } => {
+                write!(f, "memory allocation of {} bytes failed", layout.size())
+            }
+        }
+    }
+}
+
+impl std::error::Error for TryReserveError {}
+
+/// the infallible reserves panic or abort the same way Vec does
+fn handle_reserve(r: Result<(), TryReserveError>) {
+    match r {
+        Ok(()) => {}
+        Err(TryReserveError::CapacityOverflow) => panic!("capacity overflow"),
+        Err(TryReserveError::AllocError { layout }) => alloc::handle_alloc_error(layout),
+    }
+}
+
+pub(crate) struct RawBuf<T, A: Allocator = Global> {
+    ptr: NonNull<T>,
+    cap: usize,
+    alloc: A,
+}
+
+unsafe impl<T: Send, A: Allocator + Send> Send for RawBuf<T, A> {}
+unsafe impl<T: Sync, A: Allocator + Sync> Sync for RawBuf<T, A> {}
+
+impl<T, A: Allocator> RawBuf<T, A> {
+    const IS_ZST: bool = mem::size_of::<T>() == 0;
+
+    pub(crate) fn new_in(alloc: A) -> Self {
+        // zero sized types never need memory, so there's room for as many as len can count
+        let cap = if Self::IS_ZST { usize::MAX } else { 0 };
+        RawBuf {
+            ptr: NonNull::dangling(),
+            cap,
+            alloc,
+        }
+    }
+
+    pub(crate) fn with_capacity_in(cap: usize, alloc: A) -> Self {
+        let mut buf = Self::new_in(alloc);
+        handle_reserve(buf.try_set_capacity(cap));
+        buf
+    }
+
+    pub(crate) fn allocator(&self) -> &A {
+        &self.alloc
+    }
+
+    pub(crate) fn ptr(&self) -> *mut T {
+        self.ptr.as_ptr()
+    }
+
+    pub(crate) fn capacity(&self) -> usize {
+        self.cap
+    }
+
+    /// Resizes to exactly new_cap slots, keeping the first min(old, new) of them.
+    /// Anything live past new_cap is the caller's to move or drop first
+    pub(crate) fn try_set_capacity(&mut self, new_cap: usize) -> Result<(), TryReserveError> {
+        if Self::IS_ZST || new_cap == self.cap {
+            return Ok(());
+        }
+        if new_cap == 0 {
+            self.deallocate();
+            self.ptr = NonNull::dangling();
+            self.cap = 0;
+            return Ok(());
+        }
+
+        // Layout::array already refuses anything over isize::MAX bytes
+        let new_layout =
+            Layout::array::<T>(new_cap).map_err(|_| TryReserveError::CapacityOverflow)?;
+        let new_ptr = if self.cap == 0 {
+            self.alloc.allocate(new_layout)
+        } else {
+            let old_layout = Layout::array::<T>(self.cap).unwrap();
+            let old_ptr = self.ptr.cast::<u8>();
+            if new_cap > self.cap {
+                unsafe { self.alloc.grow(old_ptr, old_layout, new_layout) }
+            } else {
+                unsafe { self.alloc.shrink(old_ptr, old_layout, new_layout) }
+            }
+        };
+
+        let new_ptr = new_ptr.map_err(|_| TryReserveError::AllocError { layout: new_layout })?;
+        self.ptr = new_ptr.cast::<T>();
+        self.cap = new_cap;
+        Ok(())
+    }
+
+    /// the capacity needed for additional more than len items
+    fn needed(len: usize, additional: usize) -> Result<usize, TryReserveError> {
+        len.checked_add(additional)
+            .ok_or(TryReserveError::CapacityOverflow)
+    }
+
+    /// Room for at least additional more than len items, doubling so repeated pushes stay amortized O(1)
+    pub(crate) fn try_reserve(
+        &mut self,
+        len: usize,
+        additional: usize,
+    ) -> Result<(), TryReserveError> {
+        let needed = Self::needed(len, additional)?;
+        if needed <= self.cap {
+            return Ok(());
+        }
+        self.try_set_capacity(needed.max(self.cap.saturating_mul(2)))
+    }
+
+    /// Room for exactly additional more than len items, no more
+    pub(crate) fn try_reserve_exact(
+        &mut self,
+        len: usize,
+        additional: usize,
+    ) -> Result<(), TryReserveError> {
+        let needed = Self::needed(len, additional)?;
+        if needed <= self.cap {
+            return Ok(());
+        }
+        self.try_set_capacity(needed)
+    }
+
+    /// Panics on capacity overflow and aborts if the allocator fails, like Vec::reserve
+    pub(crate) fn reserve(&mut self, len: usize, additional: usize) {
+        handle_reserve(self.try_reserve(len, additional));
+    }
+
+    pub(crate) fn reserve_exact(&mut self, len: usize, additional: usize) {
+        handle_reserve(self.try_reserve_exact(len, additional));
+    }
+
+    /// Gives back any capacity past len
+    pub(crate) fn shrink_to(&mut self, len: usize) {
+        if self.cap > len {
+            handle_reserve(self.try_set_capacity(len));
+        }
+    }
+
+    fn deallocate(&mut self) {
+        if self.cap != 0 && !Self::IS_ZST {
+            let layout = Layout::array::<T>(self.cap).unwrap();
+            unsafe { self.alloc.deallocate(self.ptr.cast::<u8>(), layout) };
+        }
+    }
+}
+
+impl<T, A: Allocator> Drop for RawBuf<T, A> {
+    fn drop(&mut self) {
+        self.deallocate();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn grows_and_shrinks() {
+        let mut b = RawBuf::<u64>::new_in(Global);
+        assert_eq!(0, b.capacity());
+        b.reserve(0, 1);
+        assert_eq!(1, b.capacity());
+        b.reserve(1, 1);
+        assert_eq!(2, b.capacity());
+        b.reserve(2, 5);
+        assert_eq!(7, b.capacity());
+        b.reserve_exact(7, 1);
+        assert_eq!(8, b.capacity());
+
+        // realloc keeps what was written
+        for i in 0..8 {
+            unsafe { b.ptr().add(i).write(i as u64) };
+        }
+        b.reserve(8, 1);
+        assert_eq!(16, b.capacity());
+        b.shrink_to(3);
+        assert_eq!(3, b.capacity());
+        let kept: Vec<u64> = (0..3).map(|i| unsafe { b.ptr().add(i).read() }).collect();
+        assert_eq!(vec![0, 1, 2], kept);
+
+        b.shrink_to(0);
+        assert_eq!(0, b.capacity());
+        assert_eq!(NonNull::dangling(), b.ptr);
+    }
+
+    #[test]
+    fn overflow_is_reported() {
+        let mut b = RawBuf::<u32>::with_capacity_in(2, Global);
+        assert_eq!(
+            Err(TryReserveError::CapacityOverflow),
+            b.try_reserve(2, usize::MAX)
+        );
+        assert_eq!(
+            Err(TryReserveError::CapacityOverflow),
+            b.try_reserve_exact(0, usize::MAX / 2)
+        );
+        assert_eq!(2, b.capacity());
+    }
+
+    #[test]
+    fn zero_sized_types_never_allocate() {
+        let mut b = RawBuf::<()>::with_capacity_in(5, Global);
+        assert_eq!(usize::MAX, b.capacity());
+        b.reserve(100, 100);
+        b.shrink_to(0);
+        assert_eq!(usize::MAX, b.capacity());
+        assert_eq!(
+            Err(TryReserveError::CapacityOverflow),
+            b.try_reserve(1, usize::MAX)
+        );
+    }
+}
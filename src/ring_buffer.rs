@@ -7,52 +7,129 @@
  * If you exceed the size of array on either side, you can wrap around to the other, that is it's possible for actual index of head > tail
  * e.g. head may be at 4 and tail may be at 1 in a list of 10 elements
  * RingBuffer may describe this as head = 4, tail = 11 and to get actual index of tail you'd do `tail % len` which in the case would be 1
- * push_front
- * pop_back
+ * push_front/push_back
+ * pop_front/pop_back
+ * front/back
+ * What a push does when full is up to the `Overflow` policy:
+ * Overwrite (`new`) - overwrites the item at the other end, i.e. push_front drops the back
+ * Reject - drops the item being pushed, the buffer is left as is
+ * Grow (`new_growable`) - reallocates at double the capacity and unwraps the ring, like VecDeque
+ * push_*_evicting hands back whichever item didn't fit, try_push_* never evicts anything
+ * BlockingRingBuffer is the block/notify mode: shared between threads, pushes wait for room
+ * Bulk access: as_slices gives the (up to) two contiguous runs, front first; make_contiguous unwraps in place
+ * write_from/read_into copy Copy items in at the back and out at the front with at most two memcpys each
+ * RingBuffer<u8> is io::Write (at the back), io::Read and io::BufRead (from the front), like VecDeque<u8>
+ * new_in/with_overflow_in take the memory from any crate::allocator::Allocator instead of the global one
 */
-use std::alloc::Layout;
+use std::cmp::Ordering;
 use std::fmt::Debug;
-use std::mem;
-use std::ops::{Index, IndexMut};
-use std::ptr::{self, NonNull};
+use std::hash::{Hash, Hasher};
+use std::io::{self, BufRead, Read, Write};
+use std::iter::FusedIterator;
+use std::mem::MaybeUninit;
+use std::ops::{Bound, Index, IndexMut, RangeBounds};
+use std::ptr;
+use std::slice;
+use std::sync::{Condvar, Mutex};
 
-pub struct RingBuffer<T> {
-    ptr: NonNull<T>,
+use crate::allocator::{Allocator, Global};
+use crate::raw_buf::RawBuf;
+use crate::traits::{Collection, Deque, Fifo, RandomAccess};
+
+/// capacity of `RingBuffer::default()`
+pub const DEFAULT_CAPACITY: usize = 16;
+
+/// what a push does when the buffer is full
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Overflow {
+    /// evict the item at the other end (the oldest, when pushing to one end)
+    Overwrite,
+    /// drop the item being pushed
+    Reject,
+    /// double the capacity
+    Grow,
+}
+
+pub struct RingBuffer<T, A: Allocator = Global> {
+    /// at least cap slots, usize::MAX of them for zero sized types
+    buf: RawBuf<T, A>,
     head: Option<usize>,
     tail: Option<usize>,
     cap: usize,
     length: usize,
+    overflow: Overflow,
 }
 
-impl<T: Debug> Debug for RingBuffer<T> {
+impl<T: Debug, A: Allocator> Debug for RingBuffer<T, A> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_list().entries(self.iter()).finish()
     }
 }
 
 impl<T> RingBuffer<T> {
-    /// if you allocate more memory than you have, this will panic
-    fn allocate(cap: usize) -> NonNull<T> {
-        let layout = Layout::array::<T>(cap).unwrap();
+    /// bounded, overwrites once full
+    pub fn new(cap: usize) -> Self {
+        Self::with_overflow(cap, Overflow::Overwrite)
+    }
 
-        assert!(cap <= isize::MAX as usize, "Allocation too large!!!!!");
+    /// grows once full, never overwrites
+    pub fn new_growable(cap: usize) -> Self {
+        Self::with_overflow(cap, Overflow::Grow)
+    }
 
-        let raw_ptr = unsafe { std::alloc::alloc(layout) };
+    pub fn with_overflow(cap: usize, overflow: Overflow) -> Self {
+        Self::with_overflow_in(cap, overflow, Global)
+    }
+}
 
-        match NonNull::new(raw_ptr as *mut T) {
-            Some(ptr) => ptr,
-            None => std::alloc::handle_alloc_error(layout),
-        }
+impl<T, A: Allocator> RingBuffer<T, A> {
+    /// bounded, overwrites once full, its memory comes from alloc
+    pub fn new_in(cap: usize, alloc: A) -> Self {
+        Self::with_overflow_in(cap, Overflow::Overwrite, alloc)
     }
 
-    pub fn new(cap: usize) -> Self {
-        let ptr = Self::allocate(cap);
+    pub fn with_overflow_in(cap: usize, overflow: Overflow, alloc: A) -> Self {
         RingBuffer {
-            ptr,
+            buf: RawBuf::with_capacity_in(cap, alloc),
             head: None,
             tail: None,
             cap,
             length: 0,
+            overflow,
+        }
+    }
+
+    pub fn overflow(&self) -> Overflow {
+        self.overflow
+    }
+
+    pub fn set_overflow(&mut self, overflow: Overflow) {
+        self.overflow = overflow;
+    }
+
+    pub fn is_growable(&self) -> bool {
+        self.overflow == Overflow::Grow
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.length == self.cap
+    }
+
+    pub fn len(&self) -> usize {
+        self.length
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.length == 0
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.cap
+    }
+
+    pub fn clear(&mut self) {
+        while let Some(item) = self.pop_back() {
+            drop(item);
         }
     }
 
@@ -69,73 +146,521 @@ impl<T> RingBuffer<T> {
         }
     }
 
-    pub fn push_front(&mut self, item: T) {
-        // add el to head - 1
-        let new_head = if let Some(old_i) = self.head.take() {
-            self.before(old_i)
+    fn after(&self, i: usize) -> usize {
+        if i == self.cap - 1 {
+            0
         } else {
-            &self.cap / 4
-        };
+            i + 1
+        }
+    }
 
-        let is_overwrite = self.length == self.cap;
+    fn grow(&mut self) {
+        self.reallocate((self.cap * 2).max(1));
+    }
 
-        unsafe {
-            let p = self.ptr.as_ptr().add(new_head);
-            if mem::needs_drop::<T>() && is_overwrite {
-                let v = ptr::read(p);
-                drop(v);
+    /// Makes room for at least `additional` more items, growing (at least doubling) whatever the policy
+    pub fn reserve(&mut self, additional: usize) {
+        let needed = self
+            .length
+            .checked_add(additional)
+            .expect("capacity overflow");
+        if needed > self.cap {
+            self.reallocate(needed.max(self.cap * 2));
+        }
+    }
+
+    /// Grows the array to new_cap (at least double cap) in place and unwraps the ring:
+    /// head stays put and whatever wrapped around to 0.. moves to just past the old end
+    fn reallocate(&mut self, new_cap: usize) {
+        let old_cap = self.cap;
+        debug_assert!(new_cap >= old_cap * 2);
+        // realloc keeps the first old_cap slots where they were
+        self.buf.reserve_exact(old_cap, new_cap - old_cap);
+        if let Some(head) = self.head {
+            let wrapped = (head + self.length).saturating_sub(old_cap);
+            unsafe {
+                ptr::copy_nonoverlapping(self.buf.ptr(), self.buf.ptr().add(old_cap), wrapped);
+            }
+            self.tail = Some(head + self.length - 1);
+        }
+        self.cap = new_cap;
+    }
+
+    /// Deals with a full buffer before a push: None if the pushed item is rejected,
+    /// otherwise there's room now and Some holds the item evicted from the `evict` end, if any
+    fn make_room(&mut self, evict: fn(&mut Self) -> Option<T>) -> Option<Option<T>> {
+        if self.length < self.cap {
+            return Some(None);
+        }
+        match self.overflow {
+            Overflow::Grow => {
+                self.grow();
+                Some(None)
+            }
+            // nothing to overwrite if there's no room at all
+            Overflow::Overwrite if self.cap > 0 => Some(evict(self)),
+            Overflow::Overwrite | Overflow::Reject => None,
+        }
+    }
+
+    /// Pushes item to the front and hands back whichever item didn't fit, if any:
+    /// the back when overwriting or item itself when rejecting
+    pub fn push_front_evicting(&mut self, item: T) -> Option<T> {
+        match self.make_room(Self::pop_back) {
+            Some(evicted) => {
+                self.write_front(item);
+                evicted
+            }
+            None => Some(item),
+        }
+    }
+
+    pub fn push_back_evicting(&mut self, item: T) -> Option<T> {
+        match self.make_room(Self::pop_front) {
+            Some(evicted) => {
+                self.write_back(item);
+                evicted
             }
-            ptr::write(p, item);
+            None => Some(item),
+        }
+    }
+
+    /// the overflow policy decides what gets dropped when full
+    pub fn push_front(&mut self, item: T) {
+        drop(self.push_front_evicting(item));
+    }
+
+    pub fn push_back(&mut self, item: T) {
+        drop(self.push_back_evicting(item));
+    }
+
+    /// Hands item back instead of evicting anything when full, whatever the policy;
+    /// only a growable buffer makes room
+    pub fn try_push_front(&mut self, item: T) -> Result<(), T> {
+        if self.is_full() && !self.is_growable() {
+            return Err(item);
+        }
+        self.push_front(item);
+        Ok(())
+    }
+
+    pub fn try_push_back(&mut self, item: T) -> Result<(), T> {
+        if self.is_full() && !self.is_growable() {
+            return Err(item);
         }
+        self.push_back(item);
+        Ok(())
+    }
+
+    /// there has to be room
+    fn write_front(&mut self, item: T) {
+        // add el to head - 1, or a quarter of the way in if empty to leave room on both sides
+        let new_head = match self.head {
+            Some(i) => self.before(i),
+            None => self.cap / 4,
+        };
+        unsafe { ptr::write(self.buf.ptr().add(new_head), item) };
 
-        // update head
         self.head = Some(new_head);
-        // update tail if length == cap
         if self.length == 0 {
             self.tail = Some(new_head);
-        } else if is_overwrite {
-            self.tail = self.tail.map(|i| self.before(i));
         }
-        // update length if length < cap
-        if self.length < self.cap {
-            self.length += 1;
+        self.length += 1;
+    }
+
+    fn write_back(&mut self, item: T) {
+        let new_tail = match self.tail {
+            Some(i) => self.after(i),
+            None => self.cap / 4,
+        };
+        unsafe { ptr::write(self.buf.ptr().add(new_tail), item) };
+
+        self.tail = Some(new_tail);
+        if self.length == 0 {
+            self.head = Some(new_tail);
         }
+        self.length += 1;
+    }
+
+    pub fn pop_front(&mut self) -> Option<T> {
+        let old_head = self.head.take()?;
+        self.length -= 1;
+        if self.length > 0 {
+            self.head = Some(self.after(old_head));
+        } else {
+            self.tail = None;
+        }
+        unsafe { Some(ptr::read(self.buf.ptr().add(old_head))) }
     }
 
     pub fn pop_back(&mut self) -> Option<T> {
         // get el at tail
-        let old_tail = if let Some(old_i) = self.tail.take() {
-            old_i
-        } else {
-            return None;
-        };
+        let old_tail = self.tail.take()?;
         // decrement length
         self.length -= 1;
         // update tail to tail - 1
         if self.length > 0 {
             self.tail = Some(self.before(old_tail));
+        } else {
+            self.head = None;
         }
         // return el
-        unsafe { Some(ptr::read(self.ptr.as_ptr().add(old_tail))) }
+        unsafe { Some(ptr::read(self.buf.ptr().add(old_tail))) }
+    }
+
+    pub fn front(&self) -> Option<&T> {
+        self.head.map(|i| unsafe { &*self.buf.ptr().add(i) })
+    }
+
+    pub fn back(&self) -> Option<&T> {
+        self.tail.map(|i| unsafe { &*self.buf.ptr().add(i) })
     }
 
-    pub fn iter(&self) -> RingBufferIterator<T> {
+    pub fn front_mut(&mut self) -> Option<&mut T> {
+        self.head.map(|i| unsafe { &mut *self.buf.ptr().add(i) })
+    }
+
+    pub fn back_mut(&mut self) -> Option<&mut T> {
+        self.tail.map(|i| unsafe { &mut *self.buf.ptr().add(i) })
+    }
+
+    pub fn iter(&self) -> RingBufferIterator<'_, T, A> {
         RingBufferIterator {
             current_front: 0,
             current_back: self.length,
             buf: self,
         }
     }
+
+    pub fn iter_mut(&mut self) -> IterMut<'_, T> {
+        let (first, second) = self.as_mut_slices();
+        IterMut {
+            first: first.iter_mut(),
+            second: second.iter_mut(),
+        }
+    }
+
+    /// panics if either index is out of bounds
+    pub fn swap(&mut self, i: usize, j: usize) {
+        assert!(i < self.length && j < self.length, "index out of bounds");
+        unsafe {
+            ptr::swap(
+                self.buf.ptr().add(self.offset(i)),
+                self.buf.ptr().add(self.offset(j)),
+            )
+        };
+    }
+
+    /// drops items off the back until there are at most len
+    pub fn truncate(&mut self, len: usize) {
+        while self.length > len {
+            drop(self.pop_back());
+        }
+    }
+
+    /// Keeps only the items f says yes to, in order. Each is visited once, front to back.
+    pub fn retain<F: FnMut(&T) -> bool>(&mut self, mut f: F) {
+        // keepers get swapped up behind the ones already kept, so a panic in f only reorders things
+        let mut kept = 0;
+        for i in 0..self.length {
+            if f(&self[i]) {
+                if kept != i {
+                    self.swap(kept, i);
+                }
+                kept += 1;
+            }
+        }
+        self.truncate(kept);
+    }
+
+    /// (array index of the front, how many items run from there to the end of the array, how many wrapped to 0)
+    fn runs(&self) -> (usize, usize, usize) {
+        match self.head {
+            Some(head) => {
+                let first = self.length.min(self.cap - head);
+                (head, first, self.length - first)
+            }
+            None => (0, 0, 0),
+        }
+    }
+
+    /// The items front to back, in the two pieces they are stored as; the second is empty unless the ring wraps
+    pub fn as_slices(&self) -> (&[T], &[T]) {
+        let (head, first, second) = self.runs();
+        unsafe {
+            (
+                slice::from_raw_parts(self.buf.ptr().add(head), first),
+                slice::from_raw_parts(self.buf.ptr(), second),
+            )
+        }
+    }
+
+    pub fn as_mut_slices(&mut self) -> (&mut [T], &mut [T]) {
+        let (head, first, second) = self.runs();
+        unsafe {
+            (
+                slice::from_raw_parts_mut(self.buf.ptr().add(head), first),
+                slice::from_raw_parts_mut(self.buf.ptr(), second),
+            )
+        }
+    }
+
+    /// Rotates the array in place so the items start at index 0, then they're all in one slice
+    pub fn make_contiguous(&mut self) -> &mut [T] {
+        if let Some(head) = self.head {
+            if head + self.length > self.cap {
+                // the gap is uninitialised, MaybeUninit lets us shuffle it along without reading it
+                let all = unsafe {
+                    slice::from_raw_parts_mut(self.buf.ptr() as *mut MaybeUninit<T>, self.cap)
+                };
+                all.rotate_left(head);
+                self.head = Some(0);
+                self.tail = Some(self.length - 1);
+            }
+        }
+        self.as_mut_slices().0
+    }
+
+    /// Clones every item onto the back, one push at a time, so the overflow policy applies to each
+    pub fn extend_from_slice(&mut self, items: &[T])
+    where
+        T: Clone,
+    {
+        if self.is_growable() {
+            self.reserve(items.len());
+        }
+        for item in items {
+            self.push_back(item.clone());
+        }
+    }
+
+    /// Copies as many items as there is room for onto the back, returns how many.
+    /// Never evicts anything; a growable buffer grows to take all of them.
+    pub fn write_from(&mut self, items: &[T]) -> usize
+    where
+        T: Copy,
+    {
+        if self.is_growable() {
+            self.reserve(items.len());
+        }
+        let n = items.len().min(self.cap - self.length);
+        if n == 0 {
+            return 0;
+        }
+        let start = match self.tail {
+            Some(tail) => self.after(tail),
+            None => self.cap / 4,
+        };
+        let first = n.min(self.cap - start);
+        unsafe {
+            ptr::copy_nonoverlapping(items.as_ptr(), self.buf.ptr().add(start), first);
+            ptr::copy_nonoverlapping(items.as_ptr().add(first), self.buf.ptr(), n - first);
+        }
+        if self.length == 0 {
+            self.head = Some(start);
+        }
+        self.length += n;
+        self.tail = Some((start + n - 1) % self.cap);
+        n
+    }
+
+    /// Moves as many items as fit from the front into `out`, returns how many
+    pub fn read_into(&mut self, out: &mut [T]) -> usize
+    where
+        T: Copy,
+    {
+        let n = out.len().min(self.length);
+        let (head, first, _) = self.runs();
+        let first = first.min(n);
+        unsafe {
+            ptr::copy_nonoverlapping(self.buf.ptr().add(head), out.as_mut_ptr(), first);
+            ptr::copy_nonoverlapping(self.buf.ptr(), out.as_mut_ptr().add(first), n - first);
+        }
+        self.forget_front(n);
+        n
+    }
+
+    /// Moves the front up past n (<= length) items without dropping them
+    fn forget_front(&mut self, n: usize) {
+        self.length -= n;
+        if self.length == 0 {
+            self.head = None;
+            self.tail = None;
+        } else if let Some(head) = self.head {
+            self.head = Some((head + n) % self.cap);
+        }
+    }
+
+    /// Removes the items in range (indexes from the front) and iterates over them.
+    /// Whatever isn't iterated is dropped with the Drain, then the items after the range move up.
+    /// panics if the range is out of bounds
+    pub fn drain<R: RangeBounds<usize>>(&mut self, range: R) -> Drain<'_, T, A> {
+        let start = match range.start_bound() {
+            Bound::Included(&i) => i,
+            Bound::Excluded(&i) => i + 1,
+            Bound::Unbounded => 0,
+        };
+        let end = match range.end_bound() {
+            Bound::Included(&i) => i + 1,
+            Bound::Excluded(&i) => i,
+            Bound::Unbounded => self.length,
+        };
+        assert!(
+            start <= end && end <= self.length,
+            "drain range out of bounds"
+        );
+
+        let head = self.head.unwrap_or(0);
+        let len = self.length;
+        // until the Drain is dropped the buffer only owns the items before the range,
+        // so forgetting the Drain leaks the rest rather than dropping anything twice
+        self.length = start;
+        if start == 0 {
+            self.head = None;
+            self.tail = None;
+        } else {
+            self.tail = Some((head + start - 1) % self.cap);
+        }
+        Drain {
+            head,
+            front: start,
+            back: end,
+            start,
+            end,
+            len,
+            buf: self,
+        }
+    }
+}
+
+impl<A: Allocator> Write for RingBuffer<u8, A> {
+    /// Ok(0) once a bounded buffer is full, so write_all fails with WriteZero rather than overwriting
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        Ok(self.write_from(buf))
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl<A: Allocator> Read for RingBuffer<u8, A> {
+    /// Ok(0) when empty, which readers take as end of stream
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        Ok(self.read_into(buf))
+    }
+}
+
+impl<A: Allocator> BufRead for RingBuffer<u8, A> {
+    /// the bytes up to where the ring wraps, the rest come after consuming these
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        Ok(self.as_slices().0)
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.forget_front(amt.min(self.length));
+    }
+}
+
+pub struct Drain<'a, T, A: Allocator = Global> {
+    buf: &'a mut RingBuffer<T, A>,
+    /// array index of the front when the drain started
+    head: usize,
+    /// next items to hand out from either end, as indexes from the old front
+    front: usize,
+    back: usize,
+    /// the range being drained and the old length
+    start: usize,
+    end: usize,
+    len: usize,
+}
+
+impl<T, A: Allocator> Drain<'_, T, A> {
+    fn slot(&self, i: usize) -> *mut T {
+        unsafe { self.buf.buf.ptr().add((self.head + i) % self.buf.cap) }
+    }
+
+    /// Moves the n items from src (indexes from the old front) down to dst < src.
+    /// One ptr::copy per run where neither end wraps, and each end wraps at most once,
+    /// so that's at most three copies. Going front to back never overwrites an item not yet moved
+    unsafe fn move_down(&mut self, src: usize, dst: usize, n: usize) {
+        let cap = self.buf.cap;
+        let mut done = 0;
+        while done < n {
+            let (from, to) = (
+                (self.head + src + done) % cap,
+                (self.head + dst + done) % cap,
+            );
+            let run = (n - done).min(cap - from).min(cap - to);
+            let base = self.buf.buf.ptr();
+            ptr::copy(base.add(from), base.add(to), run);
+            done += run;
+        }
+    }
+}
+
+impl<T, A: Allocator> Iterator for Drain<'_, T, A> {
+    type Item = T;
+    fn next(&mut self) -> Option<T> {
+        if self.front == self.back {
+            return None;
+        }
+        let item = unsafe { ptr::read(self.slot(self.front)) };
+        self.front += 1;
+        Some(item)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let n = self.back - self.front;
+        (n, Some(n))
+    }
 }
 
-impl<T> Index<usize> for RingBuffer<T> {
+impl<T, A: Allocator> DoubleEndedIterator for Drain<'_, T, A> {
+    fn next_back(&mut self) -> Option<T> {
+        if self.front == self.back {
+            return None;
+        }
+        self.back -= 1;
+        Some(unsafe { ptr::read(self.slot(self.back)) })
+    }
+}
+
+impl<T, A: Allocator> ExactSizeIterator for Drain<'_, T, A> {}
+
+impl<T, A: Allocator> FusedIterator for Drain<'_, T, A> {}
+
+impl<T, A: Allocator> Drop for Drain<'_, T, A> {
+    fn drop(&mut self) {
+        for i in self.front..self.back {
+            unsafe { ptr::drop_in_place(self.slot(i)) };
+        }
+        // close the gap by moving the items after the range up to its start
+        let moved = self.len - self.end;
+        if self.start != self.end {
+            unsafe { self.move_down(self.end, self.start, moved) };
+        }
+        let new_len = self.start + moved;
+        let buf = &mut *self.buf;
+        buf.length = new_len;
+        if new_len == 0 {
+            buf.head = None;
+            buf.tail = None;
+        } else {
+            buf.head = Some(self.head);
+            buf.tail = Some((self.head + new_len - 1) % buf.cap);
+        }
+    }
+}
+
+impl<T, A: Allocator> Index<usize> for RingBuffer<T, A> {
     type Output = T;
     /// Panics if index is out of bounds
     fn index(&self, index: usize) -> &Self::Output {
         if index < self.length {
             unsafe {
-                self.ptr
-                    .as_ptr()
+                self.buf
+                    .ptr()
                     .add(self.offset(index))
                     .as_ref()
                     .expect("NPE how?")
@@ -146,12 +671,12 @@ impl<T> Index<usize> for RingBuffer<T> {
     }
 }
 
-impl<T> IndexMut<usize> for RingBuffer<T> {
+impl<T, A: Allocator> IndexMut<usize> for RingBuffer<T, A> {
     fn index_mut(&mut self, index: usize) -> &mut Self::Output {
         if index < self.length {
             unsafe {
-                self.ptr
-                    .as_ptr()
+                self.buf
+                    .ptr()
                     .add(self.offset(index))
                     .as_mut()
                     .expect("NPE how?")
@@ -162,14 +687,68 @@ impl<T> IndexMut<usize> for RingBuffer<T> {
     }
 }
 
-pub struct RingBufferIterator<'a, T> {
+impl<T, A: Allocator> Collection for RingBuffer<T, A> {
+    fn len(&self) -> usize {
+        self.length
+    }
+
+    fn clear(&mut self) {
+        RingBuffer::clear(self)
+    }
+}
+
+// in at the front, out at the back; enqueueing into a full buffer overwrites the oldest item
+impl<T, A: Allocator> Fifo<T> for RingBuffer<T, A> {
+    fn enqueue(&mut self, item: T) {
+        self.push_front(item)
+    }
+
+    fn dequeue(&mut self) -> Option<T> {
+        self.pop_back()
+    }
+}
+
+impl<T, A: Allocator> Deque<T> for RingBuffer<T, A> {
+    fn push_front(&mut self, item: T) {
+        RingBuffer::push_front(self, item)
+    }
+
+    fn push_back(&mut self, item: T) {
+        RingBuffer::push_back(self, item)
+    }
+
+    fn pop_front(&mut self) -> Option<T> {
+        RingBuffer::pop_front(self)
+    }
+
+    fn pop_back(&mut self) -> Option<T> {
+        RingBuffer::pop_back(self)
+    }
+}
+
+// index 0 is the front, i.e. the newest item when filled with push_front
+impl<T, A: Allocator> RandomAccess<T> for RingBuffer<T, A> {
+    fn get(&self, index: usize) -> Option<&T> {
+        (index < self.length).then(|| &self[index])
+    }
+
+    fn get_mut(&mut self, index: usize) -> Option<&mut T> {
+        if index < self.length {
+            Some(&mut self[index])
+        } else {
+            None
+        }
+    }
+}
+
+pub struct RingBufferIterator<'a, T, A: Allocator = Global> {
     current_front: usize,
     current_back: usize,
-    buf: &'a RingBuffer<T>,
+    buf: &'a RingBuffer<T, A>,
 }
 
 // this fn has been graciously donated by twitch.tv/laundmo
-impl<'a, T: Debug> Debug for RingBufferIterator<'a, T> {
+impl<'a, T: Debug, A: Allocator> Debug for RingBufferIterator<'a, T, A> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let mut listb = f.debug_list();
         for i in self.current_front..self.current_back {
@@ -179,7 +758,7 @@ impl<'a, T: Debug> Debug for RingBufferIterator<'a, T> {
     }
 }
 
-impl<'a, T> Iterator for RingBufferIterator<'a, T> {
+impl<'a, T, A: Allocator> Iterator for RingBufferIterator<'a, T, A> {
     type Item = &'a T;
     fn next(&mut self) -> Option<Self::Item> {
         if self.current_front != self.current_back {
@@ -190,9 +769,14 @@ impl<'a, T> Iterator for RingBufferIterator<'a, T> {
             None
         }
     }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let n = self.current_back - self.current_front;
+        (n, Some(n))
+    }
 }
 
-impl<'a, T> DoubleEndedIterator for RingBufferIterator<'a, T> {
+impl<'a, T, A: Allocator> DoubleEndedIterator for RingBufferIterator<'a, T, A> {
     fn next_back(&mut self) -> Option<Self::Item> {
         if self.current_front != self.current_back {
             self.current_back -= 1;
@@ -203,23 +787,227 @@ impl<'a, T> DoubleEndedIterator for RingBufferIterator<'a, T> {
     }
 }
 
-impl<T> Drop for RingBuffer<T> {
+impl<'a, T, A: Allocator> ExactSizeIterator for RingBufferIterator<'a, T, A> {}
+
+impl<'a, T, A: Allocator> FusedIterator for RingBufferIterator<'a, T, A> {}
+
+/// front to back over the two runs from as_mut_slices
+pub struct IterMut<'a, T> {
+    first: slice::IterMut<'a, T>,
+    second: slice::IterMut<'a, T>,
+}
+
+impl<'a, T> Iterator for IterMut<'a, T> {
+    type Item = &'a mut T;
+    fn next(&mut self) -> Option<Self::Item> {
+        self.first.next().or_else(|| self.second.next())
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let n = self.first.len() + self.second.len();
+        (n, Some(n))
+    }
+}
+
+impl<'a, T> DoubleEndedIterator for IterMut<'a, T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.second.next_back().or_else(|| self.first.next_back())
+    }
+}
+
+impl<'a, T> ExactSizeIterator for IterMut<'a, T> {}
+
+impl<'a, T> FusedIterator for IterMut<'a, T> {}
+
+/// Pops from whichever end is asked for; dropping it drops what's left and frees the array with the buffer
+pub struct IntoIter<T, A: Allocator = Global>(RingBuffer<T, A>);
+
+impl<T, A: Allocator> Iterator for IntoIter<T, A> {
+    type Item = T;
+    fn next(&mut self) -> Option<T> {
+        self.0.pop_front()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.0.length, Some(self.0.length))
+    }
+}
+
+impl<T, A: Allocator> DoubleEndedIterator for IntoIter<T, A> {
+    fn next_back(&mut self) -> Option<T> {
+        self.0.pop_back()
+    }
+}
+
+impl<T, A: Allocator> ExactSizeIterator for IntoIter<T, A> {}
+
+impl<T, A: Allocator> FusedIterator for IntoIter<T, A> {}
+
+impl<T, A: Allocator> IntoIterator for RingBuffer<T, A> {
+    type Item = T;
+    type IntoIter = IntoIter<T, A>;
+    fn into_iter(self) -> IntoIter<T, A> {
+        IntoIter(self)
+    }
+}
+
+impl<'a, T, A: Allocator> IntoIterator for &'a RingBuffer<T, A> {
+    type Item = &'a T;
+    type IntoIter = RingBufferIterator<'a, T, A>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, T, A: Allocator> IntoIterator for &'a mut RingBuffer<T, A> {
+    type Item = &'a mut T;
+    type IntoIter = IterMut<'a, T>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
+impl<T> Default for RingBuffer<T> {
+    fn default() -> Self {
+        Self::new(DEFAULT_CAPACITY)
+    }
+}
+
+impl<T> FromIterator<T> for RingBuffer<T> {
+    /// A buffer just big enough for every item, pushed to the front one by one like `extend`,
+    /// so the last item ends up at index 0
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let items: Vec<T> = iter.into_iter().collect();
+        let mut r = RingBuffer::new(items.len().max(1));
+        r.extend(items);
+        r
+    }
+}
+
+impl<T, A: Allocator> Extend<T> for RingBuffer<T, A> {
+    /// push_front every item, overwriting the oldest items once full
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for item in iter {
+            self.push_front(item);
+        }
+    }
+}
+
+// comparisons go from index 0 (the newest item) up, capacity is ignored
+impl<T: PartialEq, A: Allocator> PartialEq for RingBuffer<T, A> {
+    fn eq(&self, other: &Self) -> bool {
+        self.length == other.length && self.iter().eq(other.iter())
+    }
+}
+
+impl<T: Eq, A: Allocator> Eq for RingBuffer<T, A> {}
+
+impl<T: Hash, A: Allocator> Hash for RingBuffer<T, A> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.length.hash(state);
+        for item in self.iter() {
+            item.hash(state);
+        }
+    }
+}
+
+impl<T: PartialOrd, A: Allocator> PartialOrd for RingBuffer<T, A> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        self.iter().partial_cmp(other.iter())
+    }
+}
+
+impl<T: Ord, A: Allocator> Ord for RingBuffer<T, A> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.iter().cmp(other.iter())
+    }
+}
+
+impl<T, A: Allocator> Drop for RingBuffer<T, A> {
     fn drop(&mut self) {
         while let Some(i) = self.pop_back() {
             drop(i);
         }
-        unsafe {
-            std::alloc::dealloc(
-                self.ptr.as_ptr() as *mut u8,
-                Layout::array::<T>(self.cap).unwrap(),
-            );
+    }
+}
+
+/// Block/notify mode: a bounded buffer shared between threads by reference (e.g. in an Arc).
+/// push_front waits while full and pop_back waits while empty, each waking the other side.
+pub struct BlockingRingBuffer<T> {
+    buf: Mutex<RingBuffer<T>>,
+    not_full: Condvar,
+    not_empty: Condvar,
+}
+
+impl<T> BlockingRingBuffer<T> {
+    /// panics if cap is 0, a push would wait forever
+    pub fn new(cap: usize) -> Self {
+        assert!(
+            cap > 0,
+            "a blocking buffer needs room for at least one item"
+        );
+        BlockingRingBuffer {
+            buf: Mutex::new(RingBuffer::with_overflow(cap, Overflow::Reject)),
+            not_full: Condvar::new(),
+            not_empty: Condvar::new(),
         }
     }
+
+    pub fn len(&self) -> usize {
+        self.buf.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.buf.lock().unwrap().capacity()
+    }
+
+    /// blocks until there's room
+    pub fn push_front(&self, item: T) {
+        let mut buf = self.buf.lock().unwrap();
+        while buf.is_full() {
+            buf = self.not_full.wait(buf).unwrap();
+        }
+        buf.push_front(item);
+        self.not_empty.notify_one();
+    }
+
+    pub fn try_push_front(&self, item: T) -> Result<(), T> {
+        let res = self.buf.lock().unwrap().try_push_front(item);
+        if res.is_ok() {
+            self.not_empty.notify_one();
+        }
+        res
+    }
+
+    /// blocks until there's an item
+    pub fn pop_back(&self) -> T {
+        let mut buf = self.buf.lock().unwrap();
+        loop {
+            if let Some(item) = buf.pop_back() {
+                self.not_full.notify_one();
+                return item;
+            }
+            buf = self.not_empty.wait(buf).unwrap();
+        }
+    }
+
+    pub fn try_pop_back(&self) -> Option<T> {
+        let item = self.buf.lock().unwrap().pop_back();
+        if item.is_some() {
+            self.not_full.notify_one();
+        }
+        item
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_util::{drops, Rng, Zst};
 
     #[test]
     fn ring_buffer_works() {
@@ -298,7 +1086,7 @@ mod tests {
 
         let mut i2 = r.iter();
         assert_eq!(Some(&5.5), i2.next_back());
-        assert_eq!(Some(&8.2), i2.rev().nth(0));
+        assert_eq!(Some(&8.2), i2.next_back());
 
         let mut i3 = r.iter();
         dbg!(&i3);
@@ -311,4 +1099,661 @@ mod tests {
         assert_eq!(None, i3.next());
         dbg!(&i3);
     }
+
+    #[test]
+    fn double_ended() {
+        let mut r = RingBuffer::new(4);
+        assert_eq!(None, r.front());
+        assert_eq!(None, r.pop_front());
+        r.push_back(2);
+        r.push_back(3);
+        r.push_front(1);
+        assert_eq!(Some(&1), r.front());
+        assert_eq!(Some(&3), r.back());
+        *r.back_mut().unwrap() = 30;
+        *r.front_mut().unwrap() = 10;
+        assert_eq!(vec![&10, &2, &30], r.iter().collect::<Vec<_>>());
+        assert_eq!(Some(10), r.pop_front());
+        assert_eq!(Some(30), r.pop_back());
+        assert_eq!(Some(2), r.pop_front());
+        assert_eq!(None, r.pop_back());
+        assert_eq!(None, r.back());
+        assert!(r.is_empty());
+
+        // wrap around both ways from the starting quarter
+        for i in 0..4 {
+            r.push_back(i);
+        }
+        assert!(r.is_full());
+        assert_eq!(Some(0), r.pop_front());
+        r.push_back(4);
+        assert_eq!(vec![&1, &2, &3, &4], r.iter().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn bounded_overwrites_the_other_end() {
+        let mut r = RingBuffer::new(3);
+        assert!(!r.is_growable());
+        r.extend([1, 2, 3]);
+        // push_front drops the back and push_back drops the front
+        r.push_front(4);
+        assert_eq!(vec![&4, &3, &2], r.iter().collect::<Vec<_>>());
+        r.push_back(5);
+        assert_eq!(vec![&3, &2, &5], r.iter().collect::<Vec<_>>());
+        assert_eq!(3, r.capacity());
+    }
+
+    #[test]
+    fn growable_never_overwrites() {
+        let mut r = RingBuffer::new_growable(2);
+        assert!(r.is_growable());
+        r.push_front(1);
+        r.push_back(2);
+        // full and wrapped, the front sits at the end of the array
+        r.push_front(0);
+        r.push_back(3);
+        r.push_back(4);
+        assert_eq!(8, r.capacity());
+        assert_eq!(vec![&0, &1, &2, &3, &4], r.iter().collect::<Vec<_>>());
+        assert_eq!(Some(0), r.pop_front());
+        assert_eq!(Some(4), r.pop_back());
+    }
+
+    #[test]
+    fn growable_matches_vec_deque() {
+        use std::collections::VecDeque;
+        let mut rng = Rng(0x2545_f491_4f6c_dd1d);
+        let mut r = RingBuffer::new_growable(1);
+        let mut d = VecDeque::new();
+        for i in 0..if cfg!(miri) { 300 } else { 5_000 } {
+            match rng.next() % 5 {
+                0 => {
+                    r.push_front(i);
+                    d.push_front(i);
+                }
+                1 | 2 => {
+                    r.push_back(i);
+                    d.push_back(i);
+                }
+                3 => assert_eq!(d.pop_front(), r.pop_front()),
+                _ => assert_eq!(d.pop_back(), r.pop_back()),
+            }
+            assert_eq!(d.len(), r.len());
+            assert_eq!(d.front(), r.front());
+            assert_eq!(d.back(), r.back());
+        }
+        assert!(d.iter().eq(r.iter()));
+    }
+
+    #[test]
+    fn overwrite_policy_evicts_oldest() {
+        let mut r = RingBuffer::new(2);
+        assert_eq!(Overflow::Overwrite, r.overflow());
+        assert_eq!(None, r.push_front_evicting(1));
+        assert_eq!(None, r.push_front_evicting(2));
+        assert_eq!(Some(1), r.push_front_evicting(3));
+        assert_eq!(Some(3), r.push_back_evicting(4));
+        assert_eq!(vec![&2, &4], r.iter().collect::<Vec<_>>());
+        // try_ never evicts
+        assert_eq!(Err(5), r.try_push_front(5));
+        assert_eq!(Err(5), r.try_push_back(5));
+        assert_eq!(Some(4), r.pop_back());
+        assert_eq!(Ok(()), r.try_push_front(5));
+        assert_eq!(vec![&5, &2], r.iter().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn reject_policy_keeps_what_is_there() {
+        let mut r = RingBuffer::with_overflow(2, Overflow::Reject);
+        r.push_front(1);
+        r.push_front(2);
+        // push_front quietly drops the new item, the evicting version hands it back
+        r.push_front(3);
+        assert_eq!(Some(4), r.push_front_evicting(4));
+        assert_eq!(Some(5), r.push_back_evicting(5));
+        assert_eq!(Err(6), r.try_push_front(6));
+        assert_eq!(vec![&2, &1], r.iter().collect::<Vec<_>>());
+        assert_eq!(Some(1), r.pop_back());
+        assert_eq!(None, r.push_back_evicting(7));
+        assert_eq!(vec![&2, &7], r.iter().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn grow_policy_never_evicts() {
+        let mut r = RingBuffer::with_overflow(1, Overflow::Grow);
+        assert!(r.is_growable());
+        assert_eq!(None, r.push_front_evicting(1));
+        assert_eq!(None, r.push_back_evicting(2));
+        assert_eq!(Ok(()), r.try_push_front(0));
+        assert_eq!(vec![&0, &1, &2], r.iter().collect::<Vec<_>>());
+
+        // switching policy later on
+        r.set_overflow(Overflow::Reject);
+        while !r.is_full() {
+            r.push_back(9);
+        }
+        assert_eq!(Err(3), r.try_push_back(3));
+    }
+
+    #[test]
+    fn blocking_policy_waits_for_the_consumer() {
+        use std::sync::Arc;
+        use std::thread;
+
+        let buf = Arc::new(BlockingRingBuffer::new(4));
+        let producer = {
+            let buf = Arc::clone(&buf);
+            thread::spawn(move || {
+                for i in 0..1_000 {
+                    buf.push_front(i);
+                }
+            })
+        };
+        // never more than cap in flight, and nothing lost or reordered
+        for i in 0..1_000 {
+            assert!(buf.len() <= 4);
+            assert_eq!(i, buf.pop_back());
+        }
+        producer.join().unwrap();
+        assert!(buf.is_empty());
+        assert_eq!(None, buf.try_pop_back());
+
+        for i in 0..4 {
+            assert_eq!(Ok(()), buf.try_push_front(i));
+        }
+        assert_eq!(Err(4), buf.try_push_front(4));
+        assert_eq!(4, buf.capacity());
+    }
+
+    /// a full ring of 0..cap whose front sits `shift` slots in, so it wraps unless shift is 0
+    fn wrapped(cap: usize, shift: usize) -> RingBuffer<i32> {
+        let mut r = RingBuffer::new(cap);
+        // the first push into an empty ring lands at cap / 4, walk a sentinel from there to just before shift
+        r.push_back(-1);
+        for _ in 0..(shift + 2 * cap - 1 - cap / 4) % cap {
+            r.push_back(-1);
+            r.pop_front();
+        }
+        for i in 0..cap as i32 - 1 {
+            r.push_back(i);
+        }
+        r.pop_front();
+        r.push_back(cap as i32 - 1);
+        r
+    }
+
+    #[test]
+    fn slices_and_make_contiguous() {
+        let r = RingBuffer::<i32>::new(6);
+        assert_eq!((&[][..], &[][..]), r.as_slices());
+
+        let mut r = wrapped(6, 4);
+        assert_eq!((&[0, 1][..], &[2, 3, 4, 5][..]), r.as_slices());
+        {
+            let (a, b) = r.as_mut_slices();
+            a[0] = 10;
+            b[3] = 50;
+        }
+        assert_eq!(&mut [10, 1, 2, 3, 4, 50], r.make_contiguous());
+        assert_eq!((&[10, 1, 2, 3, 4, 50][..], &[][..]), r.as_slices());
+        // and it's still a working ring
+        r.push_front(-1);
+        assert_eq!(vec![&-1, &10, &1, &2, &3, &4], r.iter().collect::<Vec<_>>());
+
+        for shift in 0..6 {
+            let mut r = wrapped(6, shift);
+            r.pop_back();
+            assert_eq!(&[0, 1, 2, 3, 4], r.make_contiguous());
+        }
+    }
+
+    #[test]
+    fn bulk_copies_across_the_wrap() {
+        let mut r = wrapped(8, 6);
+        let mut out = [0; 5];
+        assert_eq!(5, r.read_into(&mut out));
+        assert_eq!([0, 1, 2, 3, 4], out);
+        // 3 left, the back is at index 5, so this wraps around
+        assert_eq!(5, r.write_from(&[10, 11, 12, 13, 14, 15, 16]));
+        assert!(r.is_full());
+        assert_eq!(0, r.write_from(&[1]));
+        let mut out = [0; 10];
+        assert_eq!(8, r.read_into(&mut out));
+        assert_eq!([5, 6, 7, 10, 11, 12, 13, 14], out[..8]);
+        assert!(r.is_empty());
+        assert_eq!(0, r.read_into(&mut out));
+
+        // write_from and the pushes see the same ring
+        assert_eq!(2, r.write_from(&[1, 2]));
+        r.push_front(0);
+        r.push_back(3);
+        assert_eq!(vec![&0, &1, &2, &3], r.iter().collect::<Vec<_>>());
+
+        let mut g = RingBuffer::new_growable(2);
+        g.push_back(0);
+        assert_eq!(100, g.write_from(&(1..=100).collect::<Vec<_>>()));
+        assert!(g.capacity() >= 101);
+        assert!((0..=100).eq(g.iter().copied()));
+    }
+
+    #[test]
+    fn extend_from_slice_follows_the_policy() {
+        let mut r = RingBuffer::new(3);
+        r.extend_from_slice(&["a".to_string(), "b".to_string()]);
+        r.extend_from_slice(&["c".to_string(), "d".to_string()]);
+        assert_eq!(vec!["b", "c", "d"], r.iter().collect::<Vec<_>>());
+
+        let mut g = RingBuffer::new_growable(1);
+        g.extend_from_slice(&[1, 2, 3, 4]);
+        assert_eq!(vec![&1, &2, &3, &4], g.iter().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn drain_ranges() {
+        let mut r = wrapped(8, 5);
+        assert_eq!(vec![2, 3, 4], r.drain(2..5).collect::<Vec<_>>());
+        assert_eq!(vec![&0, &1, &5, &6, &7], r.iter().collect::<Vec<_>>());
+        assert_eq!(vec![7, 6], r.drain(3..).rev().collect::<Vec<_>>());
+        assert_eq!(vec![0], r.drain(..=0).collect::<Vec<_>>());
+        assert_eq!(vec![&1, &5], r.iter().collect::<Vec<_>>());
+        r.push_front(0);
+        r.push_back(6);
+        assert_eq!(vec![&0, &1, &5, &6], r.iter().collect::<Vec<_>>());
+
+        // partially consumed, the rest still goes
+        let mut d = r.drain(1..3);
+        assert_eq!(2, d.len());
+        assert_eq!(Some(1), d.next());
+        drop(d);
+        assert_eq!(vec![&0, &6], r.iter().collect::<Vec<_>>());
+
+        assert_eq!(vec![0, 6], r.drain(..).collect::<Vec<_>>());
+        assert!(r.is_empty());
+        assert_eq!(0, r.drain(..).count());
+        r.push_back(1);
+        assert_eq!(Some(&1), r.front());
+    }
+
+    #[test]
+    fn drain_closes_the_gap_across_the_wrap() {
+        use std::collections::VecDeque;
+        for cap in 1..=if cfg!(miri) { 5 } else { 9 } {
+            for shift in 0..cap {
+                for popped in 0..cap {
+                    let len = cap - popped;
+                    for start in 0..=len {
+                        for end in start..=len {
+                            let mut r = wrapped(cap, shift);
+                            let mut model: VecDeque<i32> = r.iter().copied().collect();
+                            for _ in 0..popped {
+                                r.pop_back();
+                                model.pop_back();
+                            }
+                            assert!(r.drain(start..end).eq(model.drain(start..end)));
+                            assert!(r.iter().eq(model.iter()));
+                            // head and tail still line up, also through a reallocate
+                            r.set_overflow(Overflow::Grow);
+                            r.push_back(100);
+                            r.push_front(-100);
+                            assert_eq!(Some(&100), r.back());
+                            assert_eq!(Some(&-100), r.front());
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn drain_out_of_bounds() {
+        let mut r = wrapped(4, 0);
+        r.drain(2..5);
+    }
+
+    #[test]
+    fn drain_drops_and_leaks_safely() {
+        use std::rc::Rc;
+        let counter = Rc::new(());
+        let mut r = RingBuffer::new(6);
+        for _ in 0..6 {
+            r.push_back(Rc::clone(&counter));
+        }
+        drop(r.drain(1..4));
+        assert_eq!(4, Rc::strong_count(&counter));
+        assert_eq!(3, r.len());
+        // forgetting the Drain leaks what's from the range on, but never frees anything twice
+        std::mem::forget(r.drain(1..2));
+        assert_eq!(1, r.len());
+        drop(r);
+        assert_eq!(3, Rc::strong_count(&counter));
+        // hand back the two leaked counts so the counter is freed, Miri checks for leaks
+        for _ in 0..2 {
+            unsafe { Rc::decrement_strong_count(Rc::as_ptr(&counter)) };
+        }
+        assert_eq!(1, Rc::strong_count(&counter));
+    }
+
+    /// hands out what it was given in the chunk sizes it was given, like a socket would
+    struct MockSocket {
+        chunks: Vec<Vec<u8>>,
+    }
+
+    impl Read for MockSocket {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            if self.chunks.is_empty() {
+                return Ok(0);
+            }
+            let chunk = &mut self.chunks[0];
+            let n = chunk.len().min(buf.len());
+            buf[..n].copy_from_slice(&chunk[..n]);
+            chunk.drain(..n);
+            if chunk.is_empty() {
+                self.chunks.remove(0);
+            }
+            Ok(n)
+        }
+    }
+
+    /// frames are a u16 big endian length then that many bytes
+    fn frame(payload: &[u8]) -> Vec<u8> {
+        let mut f = (payload.len() as u16).to_be_bytes().to_vec();
+        f.extend_from_slice(payload);
+        f
+    }
+
+    /// pulls a whole frame off the front if there is one. The header is peeked by index,
+    /// fill_buf stops at the wrap and could show only one of its two bytes
+    fn parse_frame(r: &mut RingBuffer<u8>) -> Option<Vec<u8>> {
+        if r.len() < 2 {
+            return None;
+        }
+        let len = u16::from_be_bytes([r[0], r[1]]) as usize;
+        if r.len() < 2 + len {
+            return None;
+        }
+        r.consume(2);
+        let mut payload = vec![0; len];
+        r.read_exact(&mut payload).unwrap();
+        Some(payload)
+    }
+
+    #[test]
+    fn framed_parser_over_fragmented_socket() {
+        let payloads: Vec<Vec<u8>> = (0..40u8)
+            .map(|i| (0..i % 11).map(|b| b ^ i).collect())
+            .collect();
+        let stream: Vec<u8> = payloads.iter().flat_map(|p| frame(p)).collect();
+        // awkward chunk sizes so frames get split everywhere, including over the wrap
+        let mut chunks = vec![];
+        let mut rest = &stream[..];
+        let mut size = 1;
+        while !rest.is_empty() {
+            let n = size.min(rest.len());
+            chunks.push(rest[..n].to_vec());
+            rest = &rest[n..];
+            size = size % 7 + 1;
+        }
+        let mut socket = MockSocket { chunks };
+
+        // small enough that it wraps many times, big enough for the largest frame
+        let mut ring = RingBuffer::new(16);
+        let mut received = vec![];
+        let mut tmp = [0u8; 5];
+        loop {
+            let room = tmp.len().min(ring.capacity() - ring.len());
+            let n = socket.read(&mut tmp[..room]).unwrap();
+            ring.write_all(&tmp[..n]).unwrap();
+            while let Some(payload) = parse_frame(&mut ring) {
+                received.push(payload);
+            }
+            if n == 0 && room > 0 {
+                break;
+            }
+        }
+        assert_eq!(payloads, received);
+        assert!(ring.is_empty());
+    }
+
+    #[test]
+    fn buf_read_across_the_wrap() {
+        let mut r = RingBuffer::new(8);
+        // the first write lands at index 2, leave its last byte at index 7 so lines straddle the end
+        r.write_all(b"xxxxxx").unwrap();
+        r.consume(5);
+        r.write_all(b"ab\ncd\ne").unwrap();
+        assert_eq!(b"x", r.fill_buf().unwrap());
+        let mut line = String::new();
+        r.read_line(&mut line).unwrap();
+        assert_eq!("xab\n", line);
+        let mut until = vec![];
+        r.read_until(b'\n', &mut until).unwrap();
+        assert_eq!(b"cd\n", &until[..]);
+
+        // full: writes stop short instead of overwriting
+        assert_eq!(7, r.write(b"fghijklmn").unwrap());
+        assert_eq!(0, r.write(b"o").unwrap());
+        assert_eq!(
+            io::ErrorKind::WriteZero,
+            r.write_all(b"o").unwrap_err().kind()
+        );
+        let mut all = String::new();
+        r.read_to_string(&mut all).unwrap();
+        assert_eq!("efghijkl", all);
+        assert_eq!(0, r.read(&mut [0; 4]).unwrap());
+        assert!(r.fill_buf().unwrap().is_empty());
+    }
+
+    #[test]
+    fn growable_writer_takes_everything() {
+        let mut r = RingBuffer::new_growable(4);
+        write!(r, "hello-{}", 42).unwrap();
+        r.write_all(&[b'!'; 100]).unwrap();
+        let mut out = vec![];
+        io::copy(&mut r, &mut out).unwrap();
+        assert_eq!(108, out.len());
+        assert!(out.starts_with(b"hello-42!"));
+    }
+
+    #[test]
+    fn iterators_know_their_length() {
+        let r = wrapped(6, 4);
+        let mut it = r.iter();
+        assert_eq!(6, it.len());
+        it.next();
+        it.next_back();
+        assert_eq!(4, it.len());
+        assert_eq!(vec![&4, &3, &2, &1], it.rev().collect::<Vec<_>>());
+        let mut it = r.iter();
+        it.by_ref().for_each(drop);
+        assert_eq!(None, it.next());
+        assert_eq!(None, it.next());
+        let mut total = 0;
+        for v in &r {
+            total += v;
+        }
+        assert_eq!(15, total);
+    }
+
+    #[test]
+    fn iter_mut_across_the_wrap() {
+        let mut r = wrapped(6, 4);
+        for v in r.iter_mut() {
+            *v *= 10;
+        }
+        assert_eq!(
+            vec![&0, &10, &20, &30, &40, &50],
+            r.iter().collect::<Vec<_>>()
+        );
+        let mut it = r.iter_mut();
+        assert_eq!(6, it.len());
+        *it.next_back().unwrap() = -1;
+        *it.next().unwrap() = -2;
+        assert_eq!(4, it.len());
+        for v in &mut r {
+            *v += 1;
+        }
+        assert_eq!(
+            vec![&-1, &11, &21, &31, &41, &0],
+            r.iter().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn into_iter_hands_back_ownership() {
+        let r = wrapped(6, 4);
+        let mut it = r.into_iter();
+        assert_eq!(6, it.len());
+        assert_eq!(Some(0), it.next());
+        assert_eq!(Some(5), it.next_back());
+        assert_eq!(vec![1, 2, 3, 4], it.collect::<Vec<_>>());
+
+        let mut r = RingBuffer::new(4);
+        r.extend_from_slice(&["a".to_string(), "b".to_string(), "c".to_string()]);
+        let v: Vec<String> = r.into_iter().rev().collect();
+        assert_eq!(vec!["c", "b", "a"], v);
+    }
+
+    #[test]
+    fn into_iter_dropped_halfway() {
+        use std::rc::Rc;
+        let counter = Rc::new(());
+        let mut r = RingBuffer::new_growable(2);
+        for _ in 0..5 {
+            r.push_back(Rc::clone(&counter));
+        }
+        let mut it = r.into_iter();
+        let first = it.next().unwrap();
+        drop(it);
+        assert_eq!(2, Rc::strong_count(&counter));
+        drop(first);
+        assert_eq!(1, Rc::strong_count(&counter));
+    }
+
+    #[test]
+    fn retain_keeps_order() {
+        let mut r = wrapped(8, 6);
+        r.retain(|v| v % 3 != 0);
+        assert_eq!(vec![&1, &2, &4, &5, &7], r.iter().collect::<Vec<_>>());
+        r.retain(|_| true);
+        assert_eq!(5, r.len());
+        r.retain(|_| false);
+        assert!(r.is_empty());
+        r.push_back(1);
+        assert_eq!(Some(&1), r.front());
+
+        // each item is seen exactly once, in order
+        let mut r = wrapped(8, 3);
+        let mut seen = vec![];
+        r.retain(|&v| {
+            seen.push(v);
+            v > 4
+        });
+        assert_eq!((0..8).collect::<Vec<_>>(), seen);
+        assert_eq!(vec![5, 6, 7], r.drain(..).collect::<Vec<_>>());
+
+        let mut r = wrapped(4, 0);
+        r.swap(0, 3);
+        r.truncate(2);
+        assert_eq!(vec![&3, &1], r.iter().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn zero_capacity() {
+        for overflow in [Overflow::Overwrite, Overflow::Reject] {
+            let mut r = RingBuffer::with_overflow(0, overflow);
+            assert!(r.is_full());
+            // nothing to overwrite, so every push is turned away
+            assert_eq!(Some(1), r.push_front_evicting(1));
+            assert_eq!(Some(2), r.push_back_evicting(2));
+            r.push_front(3);
+            r.push_back(4);
+            assert_eq!(Err(5), r.try_push_back(5));
+            assert!(r.is_empty());
+            assert_eq!(None, r.pop_front());
+            assert_eq!(None, r.pop_back());
+            assert_eq!(None, r.front());
+            assert_eq!(None, r.iter().next());
+            assert_eq!(None, r.iter_mut().next());
+            assert_eq!((&[][..], &[][..]), r.as_slices());
+            assert!(r.make_contiguous().is_empty());
+            assert_eq!(0, r.write_from(&[1, 2]));
+            assert_eq!(0, r.read_into(&mut [0; 2]));
+            assert_eq!(0, r.drain(..).count());
+            r.retain(|_| true);
+            r.clear();
+            assert_eq!(None, RandomAccess::get(&r, 0));
+            assert_eq!(0, r.into_iter().count());
+        }
+
+        let mut g = RingBuffer::new_growable(0);
+        g.push_back(1);
+        g.push_front(0);
+        assert_eq!(vec![&0, &1], g.iter().collect::<Vec<_>>());
+        let mut g = RingBuffer::new_growable(0);
+        assert_eq!(3, g.write_from(&[1, 2, 3]));
+        let mut bytes: RingBuffer<u8> = RingBuffer::new(0);
+        assert_eq!(0, bytes.write(b"abc").unwrap());
+        assert!(bytes.fill_buf().unwrap().is_empty());
+        assert_eq!(1, RingBuffer::<u8>::from_iter(None).capacity());
+    }
+
+    #[test]
+    fn zero_sized_types() {
+        let before = drops();
+        let mut r = RingBuffer::new(3);
+        for _ in 0..5 {
+            r.push_front(Zst);
+        }
+        // still bounded by capacity, the two oldest were overwritten
+        assert_eq!(3, r.len());
+        assert_eq!(before + 2, drops());
+        assert!(r.push_back_evicting(Zst).is_some());
+        assert_eq!(before + 3, drops());
+        assert_eq!(3, r.as_slices().0.len() + r.as_slices().1.len());
+        assert_eq!(2, r.drain(1..).count());
+        r.retain(|_| false);
+        assert_eq!(before + 6, drops());
+
+        let mut g = RingBuffer::new_growable(1);
+        for _ in 0..100 {
+            g.push_back(Zst);
+            g.push_front(Zst);
+        }
+        assert_eq!(200, g.len());
+        assert_eq!(200, g.iter_mut().count());
+        g.make_contiguous();
+        let mut it = g.into_iter();
+        it.next();
+        drop(it);
+        assert_eq!(before + 206, drops());
+
+        let mut units = RingBuffer::new(2);
+        assert_eq!(2, units.write_from(&[(), (), ()]));
+        assert_eq!(2, units.read_into(&mut [(); 4]));
+    }
+
+    #[test]
+    fn drops_every_item_once() {
+        use std::rc::Rc;
+        let counter = Rc::new(());
+        let mut r = RingBuffer::new_growable(2);
+        for _ in 0..10 {
+            r.push_back(Rc::clone(&counter));
+            r.push_front(Rc::clone(&counter));
+        }
+        drop(r.pop_front());
+        assert_eq!(20, Rc::strong_count(&counter));
+        drop(r);
+        assert_eq!(1, Rc::strong_count(&counter));
+
+        let mut bounded = RingBuffer::new(3);
+        for _ in 0..10 {
+            bounded.push_front(Rc::clone(&counter));
+            bounded.push_back(Rc::clone(&counter));
+        }
+        assert_eq!(4, Rc::strong_count(&counter));
+        bounded.clear();
+        assert_eq!(1, Rc::strong_count(&counter));
+    }
 }
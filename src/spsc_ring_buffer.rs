@@ -0,0 +1,475 @@
+/*
+ * SPSC (single producer, single consumer) RingBuffer
+ * Lock-free, for streaming items from one thread to another
+ * Same raw fixed-size array as RingBuffer, shared by an Arc between two halves:
+ * Producer - push/push_slice at the tail
+ * Consumer - pop/pop_slice/peek at the head
+ * head and tail are atomic counters that go up and wrap back to 0 at 2 * cap, the slot is `counter % cap`
+ * (wrapping at usize::MAX instead would jump slots whenever cap doesn't divide 2^64)
+ * Counting to 2 * cap rather than cap keeps full (tail - head == cap) apart from empty (tail == head)
+ * head is only written by the consumer and tail only by the producer, so each side owns its own index
+ * and just needs to see the other's: tail - head is the length, never more than cap
+ * A Release store of an index publishes the slots before it, the other side's Acquire load sees them
+ * Full when tail - head == cap: push hands the item back instead of overwriting or blocking
+ * Either half can be sent to another thread but not shared: two threads using one half
+ *  at once would break the one-owner-per-index rule, and peek hands out &T across them
+*/
+use std::alloc::{self, Layout};
+use std::cell::Cell;
+use std::marker::PhantomData;
+use std::ptr::{self, NonNull};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+/// keeps head and tail on separate cache lines so the two threads don't keep stealing it from each other
+#[repr(align(64))]
+struct CachePadded(AtomicUsize);
+
+struct Shared<T> {
+    ptr: NonNull<T>,
+    cap: usize,
+    head: CachePadded,
+    tail: CachePadded,
+}
+
+// the halves make sure only one thread touches a given slot at a time
+unsafe impl<T: Send> Send for Shared<T> {}
+unsafe impl<T: Send> Sync for Shared<T> {}
+
+/// the halves are !Sync, so each one is only ever used from one thread at a time
+type NotSync = PhantomData<Cell<()>>;
+
+pub struct Producer<T> {
+    shared: Arc<Shared<T>>,
+    marker: NotSync,
+}
+
+/// The reading half. Send but not Sync, peek's &T must never reach two threads:
+/// ```compile_fail
+/// fn shared_between_threads<S: Sync>() {}
+/// shared_between_threads::<rust_dsa::spsc_ring_buffer::Consumer<std::cell::Cell<u8>>>();
+/// ```
+pub struct Consumer<T> {
+    shared: Arc<Shared<T>>,
+    marker: NotSync,
+}
+
+/// panics if cap is 0
+pub fn spsc_ring_buffer<T>(cap: usize) -> (Producer<T>, Consumer<T>) {
+    starting_at(cap, 0)
+}
+
+/// both counters start at `start`, so tests can begin right before they wrap
+fn starting_at<T>(cap: usize, start: usize) -> (Producer<T>, Consumer<T>) {
+    assert!(cap > 0, "an SPSC ring needs room for at least one item");
+    assert!(cap <= usize::MAX / 2, "Capacity too large!!!!!");
+    let layout = Layout::array::<T>(cap).expect("Allocation too large!!!!!");
+    // nothing to allocate for zero sized types
+    let ptr = if layout.size() == 0 {
+        NonNull::dangling()
+    } else {
+        match NonNull::new(unsafe { alloc::alloc(layout) } as *mut T) {
+            Some(ptr) => ptr,
+            None => alloc::handle_alloc_error(layout),
+        }
+    };
+    let shared = Arc::new(Shared {
+        ptr,
+        cap,
+        head: CachePadded(AtomicUsize::new(start)),
+        tail: CachePadded(AtomicUsize::new(start)),
+    });
+    (
+        Producer {
+            shared: Arc::clone(&shared),
+            marker: PhantomData,
+        },
+        Consumer {
+            shared,
+            marker: PhantomData,
+        },
+    )
+}
+
+impl<T> Shared<T> {
+    fn index(&self, counter: usize) -> usize {
+        if counter >= self.cap {
+            counter - self.cap
+        } else {
+            counter
+        }
+    }
+
+    fn slot(&self, counter: usize) -> *mut T {
+        unsafe { self.ptr.as_ptr().add(self.index(counter)) }
+    }
+
+    /// `counter + n` wrapped at 2 * cap, n is at most cap
+    fn advance(&self, counter: usize, n: usize) -> usize {
+        let left = 2 * self.cap - counter;
+        if n >= left {
+            n - left
+        } else {
+            counter + n
+        }
+    }
+
+    /// how many items are between the two counters
+    fn distance(&self, head: usize, tail: usize) -> usize {
+        if tail >= head {
+            tail - head
+        } else {
+            2 * self.cap - head + tail
+        }
+    }
+
+    fn len(&self) -> usize {
+        let tail = self.tail.0.load(Ordering::Acquire);
+        let head = self.head.0.load(Ordering::Acquire);
+        self.distance(head, tail)
+    }
+
+    /// Slots from counter `start` up to `n` of them, split where the array wraps around:
+    /// (index of the first run, its length, length of the run starting at 0)
+    fn runs(&self, start: usize, n: usize) -> (usize, usize, usize) {
+        let i = self.index(start);
+        let first = n.min(self.cap - i);
+        (i, first, n - first)
+    }
+}
+
+impl<T> Drop for Shared<T> {
+    /// both halves are gone, whatever is between head and tail was never popped
+    fn drop(&mut self) {
+        let head = *self.head.0.get_mut();
+        let tail = *self.tail.0.get_mut();
+        let mut i = head;
+        while i != tail {
+            unsafe { ptr::drop_in_place(self.slot(i)) };
+            i = self.advance(i, 1);
+        }
+        let layout = Layout::array::<T>(self.cap).unwrap();
+        if layout.size() != 0 {
+            unsafe { alloc::dealloc(self.ptr.as_ptr() as *mut u8, layout) };
+        }
+    }
+}
+
+impl<T> Producer<T> {
+    pub fn capacity(&self) -> usize {
+        self.shared.cap
+    }
+
+    /// may be out of date as soon as it returns, the consumer keeps popping
+    pub fn len(&self) -> usize {
+        self.shared.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.len() == self.shared.cap
+    }
+
+    /// how many items can be pushed right now, could only grow until the next push
+    pub fn free(&self) -> usize {
+        self.shared.cap - self.len()
+    }
+
+    /// Err(item) if full
+    pub fn push(&mut self, item: T) -> Result<(), T> {
+        let s = &*self.shared;
+        let tail = s.tail.0.load(Ordering::Relaxed);
+        // Acquire so the consumer is done reading the slot we're about to reuse
+        let head = s.head.0.load(Ordering::Acquire);
+        if s.distance(head, tail) == s.cap {
+            return Err(item);
+        }
+        unsafe { ptr::write(s.slot(tail), item) };
+        s.tail.0.store(s.advance(tail, 1), Ordering::Release);
+        Ok(())
+    }
+
+    /// Copies as many items from the front of `items` as fit, returns how many did
+    pub fn push_slice(&mut self, items: &[T]) -> usize
+    where
+        T: Copy,
+    {
+        let s = &*self.shared;
+        let tail = s.tail.0.load(Ordering::Relaxed);
+        let head = s.head.0.load(Ordering::Acquire);
+        let n = items.len().min(s.cap - s.distance(head, tail));
+        let (i, first, second) = s.runs(tail, n);
+        unsafe {
+            ptr::copy_nonoverlapping(items.as_ptr(), s.ptr.as_ptr().add(i), first);
+            ptr::copy_nonoverlapping(items.as_ptr().add(first), s.ptr.as_ptr(), second);
+        }
+        s.tail.0.store(s.advance(tail, n), Ordering::Release);
+        n
+    }
+
+    /// whether the consumer has been dropped, nothing pushed from now on will be read
+    pub fn is_abandoned(&self) -> bool {
+        Arc::strong_count(&self.shared) == 1
+    }
+}
+
+impl<T> Consumer<T> {
+    pub fn capacity(&self) -> usize {
+        self.shared.cap
+    }
+
+    /// may be out of date as soon as it returns, the producer keeps pushing
+    pub fn len(&self) -> usize {
+        self.shared.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn pop(&mut self) -> Option<T> {
+        let s = &*self.shared;
+        let head = s.head.0.load(Ordering::Relaxed);
+        // Acquire so the producer's write to the slot is visible
+        let tail = s.tail.0.load(Ordering::Acquire);
+        if head == tail {
+            return None;
+        }
+        let item = unsafe { ptr::read(s.slot(head)) };
+        s.head.0.store(s.advance(head, 1), Ordering::Release);
+        Some(item)
+    }
+
+    /// the next item pop would return
+    pub fn peek(&self) -> Option<&T> {
+        let s = &*self.shared;
+        let head = s.head.0.load(Ordering::Relaxed);
+        let tail = s.tail.0.load(Ordering::Acquire);
+        // the producer can't touch the slot until we pop it, which needs &mut self
+        (head != tail).then(|| unsafe { &*s.slot(head) })
+    }
+
+    /// Copies as many items as are ready into the front of `out`, returns how many
+    pub fn pop_slice(&mut self, out: &mut [T]) -> usize
+    where
+        T: Copy,
+    {
+        let s = &*self.shared;
+        let head = s.head.0.load(Ordering::Relaxed);
+        let tail = s.tail.0.load(Ordering::Acquire);
+        let n = out.len().min(s.distance(head, tail));
+        let (i, first, second) = s.runs(head, n);
+        unsafe {
+            ptr::copy_nonoverlapping(s.ptr.as_ptr().add(i), out.as_mut_ptr(), first);
+            ptr::copy_nonoverlapping(s.ptr.as_ptr(), out.as_mut_ptr().add(first), second);
+        }
+        s.head.0.store(s.advance(head, n), Ordering::Release);
+        n
+    }
+
+    /// whether the producer has been dropped, once empty nothing more will arrive
+    pub fn is_abandoned(&self) -> bool {
+        Arc::strong_count(&self.shared) == 1
+    }
+}
+
+impl<T> Iterator for Consumer<T> {
+    type Item = T;
+    /// None when nothing is ready yet, which isn't necessarily the end
+    fn next(&mut self) -> Option<T> {
+        self.pop()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::rc::Rc;
+    use std::thread;
+
+    // both halves have to be able to move to another thread
+    const _: fn() = || {
+        fn send<S: Send>() {}
+        send::<Producer<Box<u8>>>();
+        send::<Consumer<Box<u8>>>();
+        send::<Consumer<Cell<u8>>>();
+    };
+
+    #[test]
+    fn push_pop_wraps_around() {
+        let (mut p, mut c) = spsc_ring_buffer(3);
+        assert_eq!(None, c.pop());
+        assert_eq!(None, c.peek());
+        for round in 0..10 {
+            assert_eq!(Ok(()), p.push(round * 3));
+            assert_eq!(Ok(()), p.push(round * 3 + 1));
+            assert_eq!(Ok(()), p.push(round * 3 + 2));
+            assert!(p.is_full());
+            assert_eq!(Err(-1), p.push(-1));
+            assert_eq!(Some(&(round * 3)), c.peek());
+            assert_eq!(Some(round * 3), c.pop());
+            assert_eq!(1, p.free());
+            assert_eq!(
+                vec![round * 3 + 1, round * 3 + 2],
+                c.by_ref().collect::<Vec<_>>()
+            );
+            assert!(c.is_empty());
+        }
+    }
+
+    #[test]
+    fn slices_split_at_the_wrap() {
+        let (mut p, mut c) = spsc_ring_buffer(5);
+        assert_eq!(3, p.push_slice(&[1, 2, 3]));
+        let mut out = [0; 2];
+        assert_eq!(2, c.pop_slice(&mut out));
+        assert_eq!([1, 2], out);
+        // 3 sits at index 2, so 4, 5, 6 wrap around to 0
+        assert_eq!(4, p.push_slice(&[4, 5, 6, 7, 8, 9]));
+        assert!(p.is_full());
+        assert_eq!(0, p.push_slice(&[10]));
+        let mut out = [0; 8];
+        assert_eq!(5, c.pop_slice(&mut out));
+        assert_eq!([3, 4, 5, 6, 7], out[..5]);
+        assert_eq!(0, c.pop_slice(&mut out));
+    }
+
+    #[test]
+    fn leftovers_are_dropped_with_the_last_half() {
+        let counter = Rc::new(());
+        let (mut p, mut c) = spsc_ring_buffer(4);
+        for _ in 0..6 {
+            let _ = p.push(Rc::clone(&counter));
+            drop(c.pop());
+        }
+        for _ in 0..3 {
+            p.push(Rc::clone(&counter)).unwrap();
+        }
+        assert_eq!(4, Rc::strong_count(&counter));
+        drop(c);
+        assert!(p.is_abandoned());
+        assert_eq!(4, Rc::strong_count(&counter));
+        drop(p);
+        assert_eq!(1, Rc::strong_count(&counter));
+    }
+
+    #[test]
+    fn counters_wrap_back_to_the_same_slots() {
+        // 2 * 3 - 2: one more push each round and the counters go back to 0
+        let (mut p, mut c) = starting_at(3, 4);
+        for round in 0..10 {
+            assert_eq!(Ok(()), p.push(round));
+            assert_eq!(Ok(()), p.push(round + 100));
+            assert_eq!(2, p.len());
+            assert_eq!(Some(round), c.pop());
+            assert_eq!(Some(round + 100), c.pop());
+            assert!(c.is_empty());
+        }
+        let (mut p, mut c) = starting_at(5, 8);
+        assert_eq!(5, p.push_slice(&[1, 2, 3, 4, 5, 6]));
+        assert!(p.is_full());
+        assert_eq!(Err(7), p.push(7));
+        let mut out = [0; 6];
+        assert_eq!(5, c.pop_slice(&mut out));
+        assert_eq!([1, 2, 3, 4, 5], out[..5]);
+        assert!(c.is_empty());
+    }
+
+    #[test]
+    fn counters_near_the_top_of_usize() {
+        // the biggest ring allowed counts all the way up to usize::MAX - 1
+        let cap = usize::MAX / 2;
+        let (mut p, mut c) = starting_at::<()>(cap, 2 * cap - 2);
+        assert_eq!(5, p.push_slice(&[(); 5]));
+        assert_eq!(5, c.len());
+        assert_eq!(Some(()), c.pop());
+        let mut out = [(); 3];
+        assert_eq!(3, c.pop_slice(&mut out));
+        assert_eq!(1, c.len());
+        assert_eq!(cap - 1, p.free());
+    }
+
+    #[test]
+    fn zero_sized_items() {
+        let (mut p, mut c) = spsc_ring_buffer(2);
+        assert_eq!(Ok(()), p.push(()));
+        assert_eq!(Ok(()), p.push(()));
+        assert_eq!(Err(()), p.push(()));
+        assert_eq!(Some(()), c.pop());
+        assert_eq!(1, p.push_slice(&[(), (), ()]));
+        assert_eq!(2, c.len());
+        let mut out = [(); 3];
+        assert_eq!(2, c.pop_slice(&mut out));
+        assert!(c.is_empty());
+    }
+
+    #[test]
+    #[should_panic]
+    fn zero_capacity_panics() {
+        spsc_ring_buffer::<u8>(0);
+    }
+
+    // producer and consumer racing on a small ring: every item comes out once and in order
+
+    const STRESS: u64 = if cfg!(miri) { 500 } else { 50_000 };
+
+    #[test]
+    fn stress_single_items() {
+        let (mut p, mut c) = spsc_ring_buffer(7);
+        let producer = thread::spawn(move || {
+            for i in 0..STRESS {
+                let mut item = Box::new(i);
+                while let Err(back) = p.push(item) {
+                    item = back;
+                    thread::yield_now();
+                }
+            }
+        });
+        let mut expected = 0;
+        while expected < STRESS {
+            match c.pop() {
+                Some(item) => {
+                    assert_eq!(expected, *item);
+                    expected += 1;
+                }
+                None => thread::yield_now(),
+            }
+        }
+        producer.join().unwrap();
+        assert_eq!(None, c.pop());
+        assert!(c.is_abandoned());
+    }
+
+    #[test]
+    fn stress_slices() {
+        let (mut p, mut c) = spsc_ring_buffer(64);
+        let producer = thread::spawn(move || {
+            let items: Vec<u64> = (0..STRESS).collect();
+            let mut sent = 0;
+            // uneven batch sizes so the copies keep landing on the wrap point differently
+            let mut batch = 1;
+            while sent < items.len() {
+                let end = (sent + batch).min(items.len());
+                sent += p.push_slice(&items[sent..end]);
+                batch = batch % 37 + 1;
+            }
+        });
+        let mut out = [0u64; 23];
+        let mut expected = 0;
+        while expected < STRESS {
+            let n = c.pop_slice(&mut out);
+            for &v in &out[..n] {
+                assert_eq!(expected, v);
+                expected += 1;
+            }
+            if n == 0 {
+                thread::yield_now();
+            }
+        }
+        producer.join().unwrap();
+        assert!(c.is_empty());
+    }
+}
@@ -3,53 +3,58 @@
 Stack:
  * LiFo queue
  * pop/push of O(1)
+ * new_in takes the memory from any crate::allocator::Allocator instead of the global one
  */
-use std::alloc::{self, Layout};
-use std::mem;
-use std::ptr::{self, NonNull};
+use std::cmp::Ordering;
+use std::hash::{Hash, Hasher};
+use std::ptr;
 
-pub struct Stack<T> {
-    ptr: NonNull<T>,
-    cap: usize,
+use crate::allocator::{Allocator, Global};
+use crate::raw_buf::RawBuf;
+use crate::traits::{Collection, Lifo};
+
+pub struct Stack<T, A: Allocator = Global> {
+    buf: RawBuf<T, A>,
     len: usize,
 }
 
-unsafe impl<T: Send> Send for Stack<T> {}
-unsafe impl<T: Sync> Sync for Stack<T> {}
+impl<T> Default for Stack<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
 impl<T> Stack<T> {
     pub fn new() -> Stack<T> {
+        Self::new_in(Global)
+    }
+}
+
+impl<T, A: Allocator> Stack<T, A> {
+    /// empty, allocating from alloc once something is pushed
+    pub fn new_in(alloc: A) -> Stack<T, A> {
         Stack {
-            ptr: NonNull::dangling(),
-            cap: 0,
+            buf: RawBuf::new_in(alloc),
             len: 0,
         }
     }
 
     fn grow(&mut self) {
-        let (new_cap, layout) = if self.cap == 0 {
-            (1, Layout::array::<T>(1).unwrap())
-        } else {
-            let new_cap = self.cap * 2;
+        self.buf.reserve(self.len, 1);
+    }
 
-            assert!(new_cap <= isize::MAX as usize, "Allocation too large!!!!!");
+    pub fn len(&self) -> usize {
+        self.len
+    }
 
-            (new_cap, Layout::array::<T>(self.cap).unwrap())
-        };
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
 
-        let ptr = if self.cap == 0 {
-            unsafe { alloc::alloc(layout) }
-        } else {
-            let new_size = new_cap * mem::size_of::<T>();
-            unsafe { alloc::realloc(self.ptr.as_ptr() as *mut u8, layout, new_size) }
-        };
-
-        self.cap = new_cap;
-        match NonNull::new(ptr as *mut T) {
-            Some(ptr) => {
-                self.ptr = ptr;
-            }
-            None => alloc::handle_alloc_error(layout),
+    /// keeps the allocation
+    pub fn clear(&mut self) {
+        while let Some(item) = self.pop() {
+            drop(item);
         }
     }
 
@@ -58,40 +63,105 @@ impl<T> Stack<T> {
             None
         } else {
             self.len -= 1;
-            unsafe { Some(ptr::read(self.ptr.as_ptr().add(self.len))) }
+            unsafe { Some(ptr::read(self.buf.ptr().add(self.len))) }
         }
     }
 
+    /// bottom to top
+    fn as_slice(&self) -> &[T] {
+        unsafe { std::slice::from_raw_parts(self.buf.ptr(), self.len) }
+    }
+
     pub fn push(&mut self, item: T) {
         // item = 1, len = 5, cap = 8
-        if self.cap == self.len {
+        if self.buf.capacity() == self.len {
             self.grow();
         }
 
         unsafe {
-            ptr::write(self.ptr.as_ptr().add(self.len), item);
+            ptr::write(self.buf.ptr().add(self.len), item);
         }
         self.len += 1;
     }
 }
 
-impl<T> Drop for Stack<T> {
+impl<T, A: Allocator> Collection for Stack<T, A> {
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn clear(&mut self) {
+        Stack::clear(self)
+    }
+}
+
+impl<T, A: Allocator> Lifo<T> for Stack<T, A> {
+    fn push(&mut self, item: T) {
+        Stack::push(self, item)
+    }
+
+    fn pop(&mut self) -> Option<T> {
+        Stack::pop(self)
+    }
+}
+
+impl<T> FromIterator<T> for Stack<T> {
+    /// the last item ends up on top
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut s = Stack::new();
+        s.extend(iter);
+        s
+    }
+}
+
+impl<T, A: Allocator> Extend<T> for Stack<T, A> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for item in iter {
+            self.push(item);
+        }
+    }
+}
+
+// comparisons go from the bottom of the stack to the top, like a Vec
+impl<T: PartialEq, A: Allocator> PartialEq for Stack<T, A> {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_slice() == other.as_slice()
+    }
+}
+
+impl<T: Eq, A: Allocator> Eq for Stack<T, A> {}
+
+impl<T: Hash, A: Allocator> Hash for Stack<T, A> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.as_slice().hash(state);
+    }
+}
+
+impl<T: PartialOrd, A: Allocator> PartialOrd for Stack<T, A> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        self.as_slice().partial_cmp(other.as_slice())
+    }
+}
+
+impl<T: Ord, A: Allocator> Ord for Stack<T, A> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.as_slice().cmp(other.as_slice())
+    }
+}
+
+impl<T, A: Allocator> Drop for Stack<T, A> {
     fn drop(&mut self) {
         while let Some(item) = self.pop() {
             drop(item);
         }
-        unsafe {
-            alloc::dealloc(
-                self.ptr.as_ptr() as *mut u8,
-                Layout::array::<T>(self.cap).unwrap(),
-            );
-        }
+        // RawBuf frees the memory
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_util::{drops, Zst};
 
     #[test]
     fn stack_works() {
@@ -102,7 +172,7 @@ mod tests {
         assert_eq!(1, s.len);
         assert_eq!(Some(5), s.pop());
         s.push(4);
-        assert_eq!(1, s.cap);
+        assert_eq!(1, s.buf.capacity());
         s.push(10);
         assert_eq!(2, s.len);
         for n in 1..=1000 {
@@ -112,4 +182,45 @@ mod tests {
             s.pop();
         }
     }
+
+    #[test]
+    fn growing_keeps_items() {
+        let mut s: Stack<String> = (0..100).map(|i| i.to_string()).collect();
+        assert_eq!(128, s.buf.capacity());
+        for i in (0..100).rev() {
+            assert_eq!(Some(i.to_string()), s.pop());
+        }
+        assert_eq!(None, s.pop());
+    }
+
+    #[test]
+    fn empty_stack_drops() {
+        let s = Stack::<String>::new();
+        drop(s);
+    }
+
+    #[test]
+    fn zero_sized_types() {
+        let mut s = Stack::new();
+        assert_eq!(usize::MAX, s.buf.capacity());
+        for _ in 0..1000 {
+            s.push(Zst);
+        }
+        assert_eq!(1000, s.len());
+        assert!(s.pop().is_some());
+        assert_eq!(1, drops());
+        assert!(s == (0..999).map(|_| Zst).collect());
+        assert_eq!(1000, drops());
+        s.clear();
+        assert_eq!(1999, drops());
+        assert!(s.pop().is_none());
+        s.push(Zst);
+        drop(s);
+        assert_eq!(2000, drops());
+
+        let mut units = Stack::new();
+        units.push(());
+        assert_eq!(Some(()), units.pop());
+        assert_eq!(None, units.pop());
+    }
 }
@@ -0,0 +1,99 @@
+/*
+ * Fixtures shared by the unit tests
+ * Zst - a zero sized item that counts its drops, for checking that collections
+ *  which never allocate for it still drop every one exactly once
+ * The count is thread local so tests running in parallel don't see each other's drops
+ * Rng - xorshift, good enough to shuffle test input without pulling in a crate
+ * HandleHeap - the heaps that hand out Handles, all checked against the same model
+*/
+use std::cell::Cell;
+
+use crate::heap_handle::Handle;
+
+thread_local! {
+    static DROPS: Cell<usize> = const { Cell::new(0) };
+}
+
+/// zero sized, but counts its drops
+#[derive(Clone, PartialEq)]
+pub struct Zst;
+
+impl Drop for Zst {
+    fn drop(&mut self) {
+        DROPS.with(|d| d.set(d.get() + 1));
+    }
+}
+
+/// how many Zst have been dropped on this thread so far
+pub fn drops() -> usize {
+    DROPS.with(|d| d.get())
+}
+
+/// xorshift, the seed must not be 0
+pub struct Rng(pub u64);
+
+impl Rng {
+    pub fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+}
+
+/// A min heap of i64 with handles, as PairingHeap and BinomialHeap are
+pub trait HandleHeap: Default {
+    fn push_with_handle(&mut self, value: i64) -> Handle<i64>;
+    fn pop(&mut self) -> Option<i64>;
+    fn len(&self) -> usize;
+    fn contains(&self, handle: &Handle<i64>) -> bool;
+    fn get(&self, handle: &Handle<i64>) -> Option<&i64>;
+    fn decrease_key(&mut self, handle: &Handle<i64>, new: i64) -> bool;
+    fn remove(&mut self, handle: &Handle<i64>) -> Option<i64>;
+    /// panics if the heap's structure is broken
+    fn assert_valid(&self);
+}
+
+/// Random pushes, pops, decrease_keys and removes, checked step by step against a plain list
+pub fn matches_a_sorted_model<H: HandleHeap>(seed: u64) {
+    let mut rng = Rng(seed);
+    let mut next = move || (rng.next() >> 33) as i64;
+    let mut heap = H::default();
+    let mut model: Vec<(i64, Handle<i64>)> = Vec::new();
+    for step in 0..if cfg!(miri) { 300 } else { 3000 } {
+        match next() % 6 {
+            0 | 1 => {
+                let v = next() % 1000;
+                model.push((v, heap.push_with_handle(v)));
+            }
+            2 => {
+                let min = model.iter().map(|(v, _)| *v).min();
+                assert_eq!(min, heap.pop());
+                // ties can leave in either order, forget whichever handle went stale
+                model.retain(|(_, h)| heap.contains(h));
+            }
+            3 if !model.is_empty() => {
+                let i = next() as usize % model.len();
+                let new = model[i].0 - next() % 50;
+                let changed = heap.decrease_key(&model[i].1, new);
+                assert_eq!(new < model[i].0, changed);
+                if changed {
+                    model[i].0 = new;
+                }
+            }
+            4 if !model.is_empty() => {
+                let i = next() as usize % model.len();
+                let (v, h) = model.swap_remove(i);
+                assert_eq!(Some(v), heap.remove(&h));
+            }
+            _ => {}
+        }
+        assert_eq!(model.len(), heap.len());
+        for (v, h) in &model {
+            assert_eq!(Some(v), heap.get(h));
+        }
+        if step % 100 == 0 {
+            heap.assert_valid();
+        }
+    }
+}
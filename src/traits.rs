@@ -0,0 +1,279 @@
+/*
+ * Traits shared by the collections, so an algorithm or benchmark can be written once
+ * and run against any structure that fits it
+ * Collection - len, is_empty, clear; every structure implements it
+ * Lifo - push/pop at the same end (Stack, LinkedList, ArrayList, ...)
+ * Fifo - enqueue at one end, dequeue from the other (Queue, RingBuffer, ...)
+ * Deque - push/pop at both ends
+ * RandomAccess - get by index, O(1) for the array backed structures, O(N) for the lists
+ * OrderedSet - values kept in sorted order
+ * Heap - push anything, pop/peek whichever comes first (PriorityQueue, PairingHeap, BinomialHeap)
+ * MergeableHeap - a Heap that can swallow another one, O(1)/O(log N) for the pointer based heaps
+ *
+ * The inherent methods keep their names (queue/deque, push_front/pop_back, ...),
+ * the traits just give them a common vocabulary
+ */
+
+pub trait Collection {
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// removes (and drops) every item
+    fn clear(&mut self);
+}
+
+pub trait Lifo<T>: Collection {
+    fn push(&mut self, item: T);
+
+    /// the most recently pushed item
+    fn pop(&mut self) -> Option<T>;
+}
+
+pub trait Fifo<T>: Collection {
+    fn enqueue(&mut self, item: T);
+
+    /// the least recently enqueued item
+    fn dequeue(&mut self) -> Option<T>;
+}
+
+pub trait Deque<T>: Collection {
+    fn push_front(&mut self, item: T);
+    fn push_back(&mut self, item: T);
+    fn pop_front(&mut self) -> Option<T>;
+    fn pop_back(&mut self) -> Option<T>;
+}
+
+pub trait RandomAccess<T>: Collection {
+    /// None if index is out of bounds
+    fn get(&self, index: usize) -> Option<&T>;
+
+    fn get_mut(&mut self, index: usize) -> Option<&mut T>;
+
+    fn first(&self) -> Option<&T> {
+        self.get(0)
+    }
+
+    fn last(&self) -> Option<&T> {
+        self.len().checked_sub(1).and_then(|i| self.get(i))
+    }
+}
+
+pub trait OrderedSet<T>: Collection {
+    fn insert(&mut self, value: T);
+    fn contains(&self, value: &T) -> bool;
+
+    /// removes one copy of value, if there is one
+    fn remove(&mut self, value: &T) -> Option<T>;
+
+    fn min(&self) -> Option<&T>;
+    fn max(&self) -> Option<&T>;
+    fn pop_min(&mut self) -> Option<T>;
+    fn pop_max(&mut self) -> Option<T>;
+}
+
+pub trait Heap<T>: Collection {
+    fn push(&mut self, item: T);
+
+    /// the item that comes first in the heap's order
+    fn pop(&mut self) -> Option<T>;
+
+    fn peek(&self) -> Option<&T>;
+}
+
+pub trait MergeableHeap<T>: Heap<T> {
+    /// moves every item of other into self, leaving other empty
+    fn meld(&mut self, other: &mut Self);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::array_list::ArrayList;
+    use crate::binary_search_tree::BinarySearchTree;
+    use crate::binomial_heap::BinomialHeap;
+    use crate::doubly_linked_list::DubLinkedList;
+    use crate::linked_list::LinkedList;
+    use crate::pairing_heap::PairingHeap;
+    use crate::priority_queue::PriorityQueue;
+    use crate::queue::Queue;
+    use crate::ring_buffer::RingBuffer;
+    use crate::stack::Stack;
+    use crate::tree_map::TreeMap;
+    use crate::vec::MyVec;
+
+    // the kind of generic code the traits are for
+
+    fn lifo_order<S: Lifo<i32> + Default>() {
+        let mut s = S::default();
+        assert!(s.is_empty());
+        for i in 0..10 {
+            s.push(i);
+        }
+        assert_eq!(10, s.len());
+        assert_eq!(Some(9), s.pop());
+        assert_eq!(Some(8), s.pop());
+        s.clear();
+        assert!(s.is_empty());
+        assert_eq!(None, s.pop());
+        s.push(1);
+        assert_eq!(Some(1), s.pop());
+    }
+
+    fn fifo_order<Q: Fifo<i32>>(mut q: Q) {
+        assert!(q.is_empty());
+        for i in 0..10 {
+            q.enqueue(i);
+        }
+        assert_eq!(10, q.len());
+        assert_eq!(Some(0), q.dequeue());
+        assert_eq!(Some(1), q.dequeue());
+        q.clear();
+        assert!(q.is_empty());
+        assert_eq!(None, q.dequeue());
+        q.enqueue(1);
+        assert_eq!(Some(1), q.dequeue());
+    }
+
+    fn random_access<R: RandomAccess<i32> + Lifo<i32> + Default>() {
+        let mut r = R::default();
+        assert_eq!(None, r.first());
+        assert_eq!(None, r.last());
+        for i in 0..10 {
+            r.push(i);
+        }
+        let third = *r.get(3).unwrap();
+        *r.get_mut(3).unwrap() += 100;
+        assert_eq!(Some(&(third + 100)), r.get(3));
+        assert_eq!(None, r.get(10));
+        assert_eq!(None, r.get_mut(10));
+        // every item is reachable, in whichever order the structure keeps them
+        let mut seen: Vec<i32> = (0..r.len()).map(|i| *r.get(i).unwrap()).collect();
+        seen.sort();
+        let mut expected: Vec<i32> = (0..10)
+            .map(|i| if i == third { i + 100 } else { i })
+            .collect();
+        expected.sort();
+        assert_eq!(expected, seen);
+        assert_eq!(r.get(0), r.first());
+        assert_eq!(r.get(9), r.last());
+    }
+
+    #[test]
+    fn lifo_structures() {
+        lifo_order::<Stack<i32>>();
+        lifo_order::<LinkedList<i32>>();
+        lifo_order::<DubLinkedList<i32>>();
+        lifo_order::<ArrayList<i32>>();
+        lifo_order::<MyVec<i32>>();
+    }
+
+    #[test]
+    fn fifo_structures() {
+        fifo_order(Queue::new());
+        fifo_order(DubLinkedList::new());
+        fifo_order(RingBuffer::new(16));
+    }
+
+    #[test]
+    fn random_access_structures() {
+        random_access::<LinkedList<i32>>();
+        random_access::<ArrayList<i32>>();
+        random_access::<MyVec<i32>>();
+
+        let mut r: RingBuffer<i32> = (0..5).collect();
+        assert_eq!(Some(&4), r.first());
+        assert_eq!(Some(&0), r.last());
+        *RandomAccess::get_mut(&mut r, 1).unwrap() = -1;
+        assert_eq!(-1, r[1]);
+        assert_eq!(None, RandomAccess::get(&r, 5));
+    }
+
+    #[test]
+    fn deque_structures() {
+        let mut d = DubLinkedList::new();
+        Deque::push_back(&mut d, 1);
+        Deque::push_front(&mut d, 0);
+        Deque::push_back(&mut d, 2);
+        assert_eq!(3, Collection::len(&d));
+        assert_eq!(Some(0), Deque::pop_front(&mut d));
+        assert_eq!(Some(2), Deque::pop_back(&mut d));
+    }
+
+    fn ordered<S: OrderedSet<i32> + Default>() {
+        let mut s = S::default();
+        for v in [5, 1, 9, 3, 7, 3] {
+            s.insert(v);
+        }
+        assert_eq!(6, s.len());
+        assert_eq!(Some(&1), s.min());
+        assert_eq!(Some(&9), s.max());
+        assert!(s.contains(&7));
+        assert_eq!(Some(3), s.remove(&3));
+        assert!(s.contains(&3));
+        assert_eq!(None, s.remove(&4));
+        assert_eq!(Some(1), s.pop_min());
+        assert_eq!(Some(9), s.pop_max());
+        s.clear();
+        assert!(s.is_empty());
+        assert_eq!(None, s.min());
+    }
+
+    #[test]
+    fn ordered_sets() {
+        ordered::<BinarySearchTree<i32>>();
+    }
+
+    fn heap_order<H: MergeableHeap<i32> + Default>() {
+        let mut h = H::default();
+        assert_eq!(None, h.peek());
+        for v in [5, 1, 9, 3, 7, 3] {
+            h.push(v);
+        }
+        assert_eq!(Some(&1), h.peek());
+        let mut other = H::default();
+        for v in [4, 0, 8] {
+            other.push(v);
+        }
+        h.meld(&mut other);
+        assert!(other.is_empty());
+        assert_eq!(9, h.len());
+        let popped: Vec<i32> = std::iter::from_fn(|| h.pop()).collect();
+        assert_eq!(vec![0, 1, 3, 3, 4, 5, 7, 8, 9], popped);
+        assert!(h.is_empty());
+    }
+
+    #[test]
+    fn heaps() {
+        heap_order::<PriorityQueue<i32>>();
+        heap_order::<PairingHeap<i32>>();
+        heap_order::<BinomialHeap<i32>>();
+    }
+
+    #[test]
+    fn every_structure_is_a_collection() {
+        fn filled<C: Collection>(mut c: C, len: usize) {
+            assert_eq!(len, c.len());
+            assert!(!c.is_empty());
+            c.clear();
+            assert_eq!(0, c.len());
+            assert!(c.is_empty());
+        }
+        filled((0..3).collect::<Stack<i32>>(), 3);
+        filled((0..3).collect::<Queue<i32>>(), 3);
+        filled((0..3).collect::<LinkedList<i32>>(), 3);
+        filled((0..3).collect::<DubLinkedList<i32>>(), 3);
+        filled((0..3).collect::<RingBuffer<i32>>(), 3);
+        filled((0..3).collect::<ArrayList<i32>>(), 3);
+        filled((0..3).collect::<BinarySearchTree<i32>>(), 3);
+        filled((0..3).collect::<PriorityQueue<i32>>(), 3);
+        filled((0..3).collect::<PairingHeap<i32>>(), 3);
+        filled((0..3).collect::<BinomialHeap<i32>>(), 3);
+        filled((0..3).map(|i| (i, i)).collect::<TreeMap<i32, i32>>(), 3);
+        let mut v = MyVec::new();
+        v.push(1);
+        filled(v, 1);
+    }
+}
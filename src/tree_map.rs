@@ -0,0 +1,1135 @@
+/*
+ * TreeMap
+ * sorted map of K -> V, keys are unique
+ * Node.left.key < Node.key < Node.right.key
+ * Left leaning red-black tree, every Node is either red or black:
+ *  - red Nodes are always left children and never have a red child
+ *  - every path from the root to a leaf passes through the same number of black Nodes
+ * so the longest path is at most twice the shortest, height <= 2 log N
+ * insert, get & remove: O(log N)
+ * iteration is in key order, from either end
+ */
+use std::borrow::Borrow;
+use std::cmp::Ordering;
+use std::collections::VecDeque;
+use std::fmt::Debug;
+use std::hash::{Hash, Hasher};
+use std::iter::FusedIterator;
+use std::mem;
+use std::ptr::NonNull;
+
+use crate::traits::Collection;
+
+/// Clones node for node, the tree is balanced so the recursion stays shallow
+#[derive(Clone)]
+pub struct TreeMap<K, V> {
+    root: Link<K, V>,
+    len: usize,
+}
+
+type Link<K, V> = Option<Box<Node<K, V>>>;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Color {
+    Red,
+    Black,
+}
+
+/// Which child to step to, a path to a Node is a list of these
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Side {
+    Left,
+    Right,
+}
+
+impl Color {
+    fn flip(self) -> Self {
+        match self {
+            Color::Red => Color::Black,
+            Color::Black => Color::Red,
+        }
+    }
+}
+
+#[derive(Clone)]
+struct Node<K, V> {
+    key: K,
+    value: V,
+    color: Color,
+    left: Link<K, V>,
+    right: Link<K, V>,
+}
+
+impl<K: Debug, V: Debug> Debug for TreeMap<K, V> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+fn is_red<K, V>(link: &Link<K, V>) -> bool {
+    link.as_ref().is_some_and(|n| n.color == Color::Red)
+}
+
+impl<K, V> Node<K, V> {
+    fn new(key: K, value: V) -> Self {
+        Node {
+            key,
+            value,
+            color: Color::Red,
+            left: None,
+            right: None,
+        }
+    }
+
+    /// Assumes that self.right.is_some()
+    fn rotate_left(mut self: Box<Self>) -> Box<Self> {
+        let mut r = self.right.take().unwrap();
+        self.right = r.left.take();
+        r.color = self.color;
+        self.color = Color::Red;
+        r.left = Some(self);
+        r
+    }
+
+    /// Assumes that self.left.is_some()
+    fn rotate_right(mut self: Box<Self>) -> Box<Self> {
+        let mut l = self.left.take().unwrap();
+        self.left = l.right.take();
+        l.color = self.color;
+        self.color = Color::Red;
+        l.right = Some(self);
+        l
+    }
+
+    /// Assumes that both children are some
+    fn flip_colors(&mut self) {
+        self.color = self.color.flip();
+        for child in [&mut self.left, &mut self.right] {
+            let c = child.as_mut().unwrap();
+            c.color = c.color.flip();
+        }
+    }
+
+    /// Restores the left leaning red-black invariants on the way back up, returns the new root of self
+    fn balance(self: Box<Self>) -> Box<Self> {
+        self.balance_tracking(None)
+    }
+
+    /// balance, keeping path (see Node::insert) pointing at the same Node through the rotations
+    fn balance_tracking(mut self: Box<Self>, mut path: Option<&mut Vec<Side>>) -> Box<Self> {
+        if is_red(&self.right) && !is_red(&self.left) {
+            self = self.rotate_left();
+            if let Some(path) = path.as_deref_mut() {
+                rotated(path, Side::Left);
+            }
+        }
+        if is_red(&self.left) && is_red(&self.left.as_ref().unwrap().left) {
+            self = self.rotate_right();
+            if let Some(path) = path {
+                rotated(path, Side::Right);
+            }
+        }
+        if is_red(&self.left) && is_red(&self.right) {
+            self.flip_colors();
+        }
+        self
+    }
+
+    /// Makes self.left or one of its children red, so we can delete from the left
+    fn move_red_left(mut self: Box<Self>) -> Box<Self> {
+        self.flip_colors();
+        if is_red(&self.right.as_ref().unwrap().left) {
+            self.right = self.right.take().map(Node::rotate_right);
+            self = self.rotate_left();
+            self.flip_colors();
+        }
+        self
+    }
+
+    /// Makes self.right or one of its children red, so we can delete from the right
+    fn move_red_right(mut self: Box<Self>) -> Box<Self> {
+        self.flip_colors();
+        if is_red(&self.left.as_ref().unwrap().left) {
+            self = self.rotate_right();
+            self.flip_colors();
+        }
+        self
+    }
+
+    /// Removes the leftmost node of self, returns (new root of self, leftmost node)
+    fn take_min(mut self: Box<Self>) -> (Link<K, V>, Box<Self>) {
+        if self.left.is_none() {
+            return (self.right.take(), self);
+        }
+        if !is_red(&self.left) && !is_red(&self.left.as_ref().unwrap().left) {
+            self = self.move_red_left();
+        }
+        let (left, min) = self.left.take().unwrap().take_min();
+        self.left = left;
+        (Some(self.balance()), min)
+    }
+
+    fn leftmost<'a>(&'a self, stack: &mut Vec<&'a Node<K, V>>) -> &'a Self {
+        if let Some(n) = &self.left {
+            stack.push(self);
+            n.leftmost(stack)
+        } else {
+            self
+        }
+    }
+
+    fn rightmost<'a>(&'a self, stack: &mut Vec<&'a Node<K, V>>) -> &'a Self {
+        if let Some(n) = &self.right {
+            stack.push(self);
+            n.rightmost(stack)
+        } else {
+            self
+        }
+    }
+}
+
+/// Fixes up a path after the Node it starts from was rotated to `side`:
+/// the old root went down to that side and its child on the other side came up.
+/// The path is stored last step first, so the steps nearest the root are at the end
+fn rotated(path: &mut Vec<Side>, side: Side) {
+    let up = match side {
+        Side::Left => Side::Right,
+        Side::Right => Side::Left,
+    };
+    match path.last() {
+        // the old root, or somewhere under the child that stays with it
+        None => path.push(side),
+        Some(&s) if s == side => path.push(side),
+        // under the child that came up
+        Some(_) => {
+            path.pop();
+            match path.last() {
+                // that child's inner subtree moves across to the old root
+                Some(&s) if s == side => {
+                    *path.last_mut().unwrap() = up;
+                    path.push(side);
+                }
+                // the child itself, or its outer subtree which comes up with it
+                _ => {}
+            }
+        }
+    }
+}
+
+impl<K: Ord, V> Node<K, V> {
+    /// Inserts key & value below self, returns the new root of self.
+    /// `old` is set to the previous value if key was already present.
+    /// `path` (if wanted) ends up leading from the returned root to key's Node
+    fn insert(
+        link: Link<K, V>,
+        key: K,
+        value: V,
+        old: &mut Option<V>,
+        mut path: Option<&mut Vec<Side>>,
+    ) -> Box<Self> {
+        let mut n = match link {
+            None => return Box::new(Node::new(key, value)),
+            Some(n) => n,
+        };
+        match key.cmp(&n.key) {
+            Ordering::Less => {
+                let left = Node::insert(n.left.take(), key, value, old, path.as_deref_mut());
+                n.left = Some(left);
+                if let Some(path) = path.as_deref_mut() {
+                    path.push(Side::Left);
+                }
+            }
+            Ordering::Greater => {
+                let right = Node::insert(n.right.take(), key, value, old, path.as_deref_mut());
+                n.right = Some(right);
+                if let Some(path) = path.as_deref_mut() {
+                    path.push(Side::Right);
+                }
+            }
+            Ordering::Equal => *old = Some(mem::replace(&mut n.value, value)),
+        }
+        n.balance_tracking(path)
+    }
+
+    /// Removes key from below self, returns (new root of self, removed entry if key was there).
+    /// Restructures the way down either way, balance puts that right on the way back up
+    fn remove<Q>(mut self: Box<Self>, key: &Q) -> (Link<K, V>, Option<(K, V)>)
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let removed = if key < self.key.borrow() {
+            if self.left.is_none() {
+                // key isn't in the tree
+                None
+            } else {
+                if !is_red(&self.left) && !is_red(&self.left.as_ref().unwrap().left) {
+                    self = self.move_red_left();
+                }
+                let (left, r) = self.left.take().unwrap().remove(key);
+                self.left = left;
+                r
+            }
+        } else {
+            if is_red(&self.left) {
+                self = self.rotate_right();
+            }
+            if key == self.key.borrow() && self.right.is_none() {
+                return (None, Some((self.key, self.value)));
+            }
+            if self.right.is_none() {
+                // key isn't in the tree
+                None
+            } else {
+                if !is_red(&self.right) && !is_red(&self.right.as_ref().unwrap().left) {
+                    self = self.move_red_right();
+                }
+                if key == self.key.borrow() {
+                    // replace self with its in order successor
+                    let (right, min) = self.right.take().unwrap().take_min();
+                    self.right = right;
+                    let k = mem::replace(&mut self.key, min.key);
+                    let v = mem::replace(&mut self.value, min.value);
+                    Some((k, v))
+                } else {
+                    let (right, r) = self.right.take().unwrap().remove(key);
+                    self.right = right;
+                    r
+                }
+            }
+        };
+        (Some(self.balance()), removed)
+    }
+}
+
+impl<K, V> TreeMap<K, V> {
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// the tree is balanced, so dropping it recursively is fine
+    pub fn clear(&mut self) {
+        self.root = None;
+        self.len = 0;
+    }
+
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        let mut front = Vec::new();
+        let mut back = Vec::new();
+        if let Some(root) = self.root.as_ref() {
+            let first = root.leftmost(&mut front);
+            front.push(first);
+            let last = root.rightmost(&mut back);
+            back.push(last);
+        }
+        Iter {
+            front,
+            back,
+            len: self.len,
+        }
+    }
+
+    pub fn iter_mut(&mut self) -> IterMut<'_, K, V> {
+        IterMut {
+            pieces: self
+                .root
+                .as_deref_mut()
+                .map(Piece::Tree)
+                .into_iter()
+                .collect(),
+            len: self.len,
+        }
+    }
+
+    pub fn keys(&self) -> Keys<'_, K, V> {
+        Keys(self.iter())
+    }
+
+    pub fn values(&self) -> Values<'_, K, V> {
+        Values(self.iter())
+    }
+}
+
+impl<K: Ord, V> Default for TreeMap<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: Ord, V> TreeMap<K, V> {
+    pub fn new() -> Self {
+        TreeMap { root: None, len: 0 }
+    }
+
+    /// Returns the previous value if key was already present, the key itself isn't updated
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        self.insert_tracking(key, value, None)
+    }
+
+    /// insert, also filling path with the way from the root to key's Node
+    fn insert_tracking(&mut self, key: K, value: V, path: Option<&mut Vec<Side>>) -> Option<V> {
+        let mut old = None;
+        let mut root = Node::insert(self.root.take(), key, value, &mut old, path);
+        root.color = Color::Black;
+        self.root = Some(root);
+        if old.is_none() {
+            self.len += 1;
+        }
+        old
+    }
+
+    fn find<Q>(&self, key: &Q) -> Option<&Node<K, V>>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let mut link = &self.root;
+        while let Some(n) = link {
+            link = match key.cmp(n.key.borrow()) {
+                Ordering::Less => &n.left,
+                Ordering::Greater => &n.right,
+                Ordering::Equal => return Some(n),
+            };
+        }
+        None
+    }
+
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.find(key).map(|n| &n.value)
+    }
+
+    pub fn get_key_value<Q>(&self, key: &Q) -> Option<(&K, &V)>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.find(key).map(|n| (&n.key, &n.value))
+    }
+
+    pub fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let mut link = &mut self.root;
+        while let Some(n) = link {
+            link = match key.cmp(n.key.borrow()) {
+                Ordering::Less => &mut n.left,
+                Ordering::Greater => &mut n.right,
+                Ordering::Equal => return Some(&mut n.value),
+            };
+        }
+        None
+    }
+
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.find(key).is_some()
+    }
+
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.remove_entry(key).map(|(_, v)| v)
+    }
+
+    /// One search from the root, restructuring on the way down like any removal
+    pub fn remove_entry<Q>(&mut self, key: &Q) -> Option<(K, V)>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let mut root = self.root.take()?;
+        if !is_red(&root.left) && !is_red(&root.right) {
+            root.color = Color::Red;
+        }
+        let (root, removed) = root.remove(key);
+        self.root = root.map(|mut n| {
+            n.color = Color::Black;
+            n
+        });
+        if removed.is_some() {
+            self.len -= 1;
+        }
+        removed
+    }
+
+    /// One search: an occupied entry keeps hold of the Node it found
+    pub fn entry(&mut self, key: K) -> Entry<'_, K, V> {
+        let mut link = &mut self.root;
+        let mut found = None;
+        while let Some(n) = link {
+            link = match key.cmp(&n.key) {
+                Ordering::Less => &mut n.left,
+                Ordering::Greater => &mut n.right,
+                Ordering::Equal => {
+                    found = Some(NonNull::from(&mut **n));
+                    break;
+                }
+            };
+        }
+        match found {
+            Some(node) => Entry::Occupied(OccupiedEntry {
+                map: self,
+                key,
+                node,
+            }),
+            None => Entry::Vacant(VacantEntry { map: self, key }),
+        }
+    }
+}
+
+pub enum Entry<'a, K: Ord, V> {
+    Vacant(VacantEntry<'a, K, V>),
+    Occupied(OccupiedEntry<'a, K, V>),
+}
+
+pub struct VacantEntry<'a, K: Ord, V> {
+    map: &'a mut TreeMap<K, V>,
+    key: K,
+}
+
+pub struct OccupiedEntry<'a, K: Ord, V> {
+    map: &'a mut TreeMap<K, V>,
+    /// the key entry was called with, only needed again to remove
+    key: K,
+    /// The Node holding key. The map is borrowed for 'a and nothing moves its Boxes
+    /// until remove_entry, which gives the pointer up
+    node: NonNull<Node<K, V>>,
+}
+
+impl<'a, K: Ord, V> Entry<'a, K, V> {
+    pub fn key(&self) -> &K {
+        match self {
+            Entry::Vacant(e) => e.key(),
+            Entry::Occupied(e) => e.key(),
+        }
+    }
+
+    pub fn or_insert(self, default: V) -> &'a mut V {
+        self.or_insert_with(|| default)
+    }
+
+    pub fn or_insert_with<F: FnOnce() -> V>(self, default: F) -> &'a mut V {
+        match self {
+            Entry::Vacant(e) => e.insert(default()),
+            Entry::Occupied(e) => e.into_mut(),
+        }
+    }
+
+    pub fn or_default(self) -> &'a mut V
+    where
+        V: Default,
+    {
+        self.or_insert_with(V::default)
+    }
+
+    pub fn and_modify<F: FnOnce(&mut V)>(mut self, f: F) -> Self {
+        if let Entry::Occupied(e) = &mut self {
+            f(e.get_mut());
+        }
+        self
+    }
+}
+
+impl<'a, K: Ord, V> VacantEntry<'a, K, V> {
+    pub fn key(&self) -> &K {
+        &self.key
+    }
+
+    pub fn into_key(self) -> K {
+        self.key
+    }
+
+    /// Inserts, then follows the path insert left behind back to the new value
+    pub fn insert(self, value: V) -> &'a mut V {
+        let mut path = Vec::new();
+        self.map.insert_tracking(self.key, value, Some(&mut path));
+        let mut n = self.map.root.as_deref_mut().unwrap();
+        while let Some(side) = path.pop() {
+            let child = match side {
+                Side::Left => &mut n.left,
+                Side::Right => &mut n.right,
+            };
+            n = child.as_deref_mut().unwrap();
+        }
+        &mut n.value
+    }
+}
+
+impl<'a, K: Ord, V> OccupiedEntry<'a, K, V> {
+    /// the key stored in the map, not the one entry was called with
+    pub fn key(&self) -> &K {
+        unsafe { &self.node.as_ref().key }
+    }
+
+    pub fn get(&self) -> &V {
+        unsafe { &self.node.as_ref().value }
+    }
+
+    pub fn get_mut(&mut self) -> &mut V {
+        unsafe { &mut self.node.as_mut().value }
+    }
+
+    pub fn into_mut(mut self) -> &'a mut V {
+        unsafe { &mut self.node.as_mut().value }
+    }
+
+    pub fn insert(&mut self, value: V) -> V {
+        mem::replace(self.get_mut(), value)
+    }
+
+    pub fn remove(self) -> V {
+        self.remove_entry().1
+    }
+
+    pub fn remove_entry(self) -> (K, V) {
+        // the entry found its Node, so the key is there
+        self.map.remove_entry(&self.key).unwrap()
+    }
+}
+
+/// The front & back stacks each hold the next node to produce on their end at the top,
+/// `len` counts the entries left so we know when the two ends meet
+pub struct Iter<'a, K, V> {
+    front: Vec<&'a Node<K, V>>,
+    back: Vec<&'a Node<K, V>>,
+    len: usize,
+}
+
+impl<'a, K, V> Iterator for Iter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.len == 0 {
+            return None;
+        }
+        self.len -= 1;
+        let n = self.front.pop()?;
+        // check if right, then right.leftmost, else the next node is already on the stack
+        if let Some(r) = n.right.as_ref() {
+            let next = r.leftmost(&mut self.front);
+            self.front.push(next);
+        }
+        Some((&n.key, &n.value))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<K, V> DoubleEndedIterator for Iter<'_, K, V> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.len == 0 {
+            return None;
+        }
+        self.len -= 1;
+        let n = self.back.pop()?;
+        if let Some(l) = n.left.as_ref() {
+            let next = l.rightmost(&mut self.back);
+            self.back.push(next);
+        }
+        Some((&n.key, &n.value))
+    }
+}
+
+impl<K, V> ExactSizeIterator for Iter<'_, K, V> {}
+
+impl<K, V> FusedIterator for Iter<'_, K, V> {}
+
+pub struct Keys<'a, K, V>(Iter<'a, K, V>);
+
+impl<'a, K, V> Iterator for Keys<'a, K, V> {
+    type Item = &'a K;
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(|(k, _)| k)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+impl<K, V> DoubleEndedIterator for Keys<'_, K, V> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.0.next_back().map(|(k, _)| k)
+    }
+}
+
+impl<K, V> ExactSizeIterator for Keys<'_, K, V> {}
+
+impl<K, V> FusedIterator for Keys<'_, K, V> {}
+
+pub struct Values<'a, K, V>(Iter<'a, K, V>);
+
+impl<'a, K, V> Iterator for Values<'a, K, V> {
+    type Item = &'a V;
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(|(_, v)| v)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+impl<K, V> DoubleEndedIterator for Values<'_, K, V> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.0.next_back().map(|(_, v)| v)
+    }
+}
+
+impl<K, V> ExactSizeIterator for Values<'_, K, V> {}
+
+impl<K, V> FusedIterator for Values<'_, K, V> {}
+
+/// A run of entries IterMut still has to produce: one entry, or a whole subtree not yet split up
+enum Piece<'a, K, V> {
+    Entry(&'a K, &'a mut V),
+    Tree(&'a mut Node<K, V>),
+}
+
+/// The pieces are in key order. Each end splits subtrees into (left, entry, right) until it
+/// has an entry, so the two ends never hold the same node mutably
+pub struct IterMut<'a, K, V> {
+    pieces: VecDeque<Piece<'a, K, V>>,
+    len: usize,
+}
+
+impl<'a, K, V> Node<K, V> {
+    /// self's pieces, in key order
+    fn split_mut(&'a mut self) -> [Option<Piece<'a, K, V>>; 3] {
+        let Node {
+            key,
+            value,
+            left,
+            right,
+            ..
+        } = self;
+        [
+            left.as_deref_mut().map(Piece::Tree),
+            Some(Piece::Entry(key, value)),
+            right.as_deref_mut().map(Piece::Tree),
+        ]
+    }
+}
+
+impl<'a, K, V> Iterator for IterMut<'a, K, V> {
+    type Item = (&'a K, &'a mut V);
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match self.pieces.pop_front()? {
+                Piece::Entry(k, v) => {
+                    self.len -= 1;
+                    return Some((k, v));
+                }
+                Piece::Tree(n) => {
+                    for piece in n.split_mut().into_iter().rev().flatten() {
+                        self.pieces.push_front(piece);
+                    }
+                }
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<K, V> DoubleEndedIterator for IterMut<'_, K, V> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        loop {
+            match self.pieces.pop_back()? {
+                Piece::Entry(k, v) => {
+                    self.len -= 1;
+                    return Some((k, v));
+                }
+                Piece::Tree(n) => self.pieces.extend(n.split_mut().into_iter().flatten()),
+            }
+        }
+    }
+}
+
+impl<K, V> ExactSizeIterator for IterMut<'_, K, V> {}
+
+impl<K, V> FusedIterator for IterMut<'_, K, V> {}
+
+/// Every node on the stack still has to be produced, followed by its right subtree
+pub struct IntoIter<K, V> {
+    stack: Vec<Box<Node<K, V>>>,
+}
+
+impl<K, V> IntoIter<K, V> {
+    fn push_left(&mut self, mut link: Link<K, V>) {
+        while let Some(mut n) = link {
+            link = n.left.take();
+            self.stack.push(n);
+        }
+    }
+}
+
+impl<K, V> Iterator for IntoIter<K, V> {
+    type Item = (K, V);
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut n = self.stack.pop()?;
+        self.push_left(n.right.take());
+        Some((n.key, n.value))
+    }
+}
+
+impl<K, V> IntoIterator for TreeMap<K, V> {
+    type Item = (K, V);
+    type IntoIter = IntoIter<K, V>;
+    fn into_iter(mut self) -> Self::IntoIter {
+        let mut iter = IntoIter { stack: Vec::new() };
+        iter.push_left(self.root.take());
+        iter
+    }
+}
+
+impl<'a, K, V> IntoIterator for &'a TreeMap<K, V> {
+    type Item = (&'a K, &'a V);
+    type IntoIter = Iter<'a, K, V>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, K, V> IntoIterator for &'a mut TreeMap<K, V> {
+    type Item = (&'a K, &'a mut V);
+    type IntoIter = IterMut<'a, K, V>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
+impl<K, V> Collection for TreeMap<K, V> {
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn clear(&mut self) {
+        TreeMap::clear(self)
+    }
+}
+
+impl<K: Ord, V> FromIterator<(K, V)> for TreeMap<K, V> {
+    /// later values replace earlier ones with the same key
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        let mut m = TreeMap::new();
+        m.extend(iter);
+        m
+    }
+}
+
+impl<K: Ord, V> Extend<(K, V)> for TreeMap<K, V> {
+    fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, iter: I) {
+        for (k, v) in iter {
+            self.insert(k, v);
+        }
+    }
+}
+
+impl<K: PartialEq, V: PartialEq> PartialEq for TreeMap<K, V> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len == other.len && self.iter().eq(other.iter())
+    }
+}
+
+impl<K: Eq, V: Eq> Eq for TreeMap<K, V> {}
+
+impl<K: Hash, V: Hash> Hash for TreeMap<K, V> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.len.hash(state);
+        for entry in self.iter() {
+            entry.hash(state);
+        }
+    }
+}
+
+impl<K: PartialOrd, V: PartialOrd> PartialOrd for TreeMap<K, V> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        self.iter().partial_cmp(other.iter())
+    }
+}
+
+impl<K: Ord, V: Ord> Ord for TreeMap<K, V> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.iter().cmp(other.iter())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::Rng;
+    use std::collections::BTreeMap;
+
+    /// Asserts the left leaning red-black invariants, returns the black height of the node
+    fn check_rb<K: Ord, V>(link: &Link<K, V>) -> usize {
+        match link {
+            None => 1,
+            Some(n) => {
+                assert!(!is_red(&n.right), "red right child");
+                if n.color == Color::Red {
+                    assert!(!is_red(&n.left), "red node with red child");
+                }
+                if let Some(l) = &n.left {
+                    assert!(l.key < n.key);
+                }
+                if let Some(r) = &n.right {
+                    assert!(r.key > n.key);
+                }
+                let (l, r) = (check_rb(&n.left), check_rb(&n.right));
+                assert_eq!(l, r, "black heights differ");
+                l + (n.color == Color::Black) as usize
+            }
+        }
+    }
+
+    #[test]
+    fn tree_map_works() {
+        let mut m = TreeMap::new();
+        assert_eq!(None, m.insert("b", 2));
+        assert_eq!(None, m.insert("a", 1));
+        assert_eq!(None, m.insert("c", 3));
+        assert_eq!(Some(2), m.insert("b", 20));
+        assert_eq!(3, m.len());
+        assert_eq!(Some(&20), m.get("b"));
+        assert_eq!(None, m.get("d"));
+        *m.get_mut("a").unwrap() += 10;
+        assert_eq!(Some(&11), m.get("a"));
+        assert_eq!(Some(20), m.remove("b"));
+        assert_eq!(None, m.remove("b"));
+        assert_eq!(2, m.len());
+        let v: Vec<_> = m.iter().collect();
+        assert_eq!(vec![(&"a", &11), (&"c", &3)], v);
+        assert_eq!("{\"a\": 11, \"c\": 3}", format!("{m:?}"));
+    }
+
+    #[test]
+    fn borrowed_keys_work() {
+        let mut m = TreeMap::new();
+        m.insert("hello".to_string(), 1);
+        m.insert("hola".to_string(), 2);
+        assert_eq!(Some(&1), m.get("hello"));
+        assert!(m.contains_key("hola"));
+        assert_eq!(Some(("hola".to_string(), 2)), m.remove_entry("hola"));
+    }
+
+    #[test]
+    fn entry_works() {
+        let mut counts = TreeMap::new();
+        for w in "the cat and the hat and the bat".split(' ') {
+            *counts.entry(w).or_insert(0) += 1;
+        }
+        assert_eq!(Some(&3), counts.get("the"));
+        assert_eq!(Some(&2), counts.get("and"));
+        assert_eq!(Some(&1), counts.get("cat"));
+
+        counts.entry("cat").and_modify(|c| *c += 10).or_default();
+        counts.entry("dog").and_modify(|c| *c += 10).or_default();
+        assert_eq!(Some(&11), counts.get("cat"));
+        assert_eq!(Some(&0), counts.get("dog"));
+
+        match counts.entry("hat") {
+            Entry::Occupied(mut e) => {
+                assert_eq!(&"hat", e.key());
+                assert_eq!(1, e.insert(5));
+                assert_eq!(&5, e.get());
+                assert_eq!(5, e.remove());
+            }
+            Entry::Vacant(_) => panic!("hat should be present"),
+        }
+        match counts.entry("hat") {
+            Entry::Vacant(e) => *e.insert(7) *= 2,
+            Entry::Occupied(_) => panic!("hat should be removed"),
+        }
+        assert_eq!(Some(&14), counts.get("hat"));
+        check_rb(&counts.root);
+    }
+
+    #[test]
+    fn entries_point_at_the_right_node() {
+        // ordered by the first field only, so the stored key can be told apart
+        #[derive(Debug)]
+        struct Key(u32, &'static str);
+        impl PartialEq for Key {
+            fn eq(&self, other: &Self) -> bool {
+                self.0 == other.0
+            }
+        }
+        impl Eq for Key {}
+        impl PartialOrd for Key {
+            fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+                Some(self.cmp(other))
+            }
+        }
+        impl Ord for Key {
+            fn cmp(&self, other: &Self) -> Ordering {
+                self.0.cmp(&other.0)
+            }
+        }
+
+        let mut m = TreeMap::new();
+        m.insert(Key(1, "stored"), 0);
+        assert_eq!("stored", m.entry(Key(1, "asked")).key().1);
+        assert_eq!("asked", m.entry(Key(2, "asked")).key().1);
+
+        // every value handed back by a vacant insert, after whatever rotations it took
+        let mut rng = Rng(0x2545F4914F6CDD1D);
+        let mut expected = BTreeMap::from([(1, 0)]);
+        for _ in 0..300 {
+            let k = (rng.next() % 500) as u32;
+            *m.entry(Key(k, "")).or_insert(0) += k as u64 + 1;
+            *expected.entry(k).or_insert(0) += k as u64 + 1;
+        }
+        check_rb(&m.root);
+        assert!(m.iter().map(|(k, v)| (k.0, *v)).eq(expected.into_iter()));
+    }
+
+    #[test]
+    fn iterators_work() {
+        let mut m: TreeMap<u32, u32> = TreeMap::new();
+        for k in [5, 1, 9, 3, 7] {
+            m.insert(k, k * 10);
+        }
+        assert!(m.keys().copied().eq([1, 3, 5, 7, 9]));
+        assert!(m.values().copied().eq([10, 30, 50, 70, 90]));
+        for (k, v) in &mut m {
+            *v += k;
+        }
+        assert!(m.values().copied().eq([11, 33, 55, 77, 99]));
+        let v: Vec<_> = m.into_iter().collect();
+        assert_eq!(vec![(1, 11), (3, 33), (5, 55), (7, 77), (9, 99)], v);
+    }
+
+    #[test]
+    fn iterators_work_from_both_ends() {
+        let mut rng = Rng(0xD1B54A32D192ED03);
+        let mut m = TreeMap::new();
+        let mut expected = BTreeMap::new();
+        for _ in 0..200 {
+            let k = rng.next() % 300;
+            m.insert(k, k);
+            expected.insert(k, k);
+        }
+        assert_eq!(expected.len(), m.iter().len());
+        assert!(m.iter().rev().eq(expected.iter().rev()));
+        assert!(m.keys().rev().eq(expected.keys().rev()));
+        assert!(m.values().rev().eq(expected.values().rev()));
+
+        // the two ends meet in the middle without repeating or skipping anything
+        let (mut copy, mut expected_copy) = (m.clone(), expected.clone());
+        let (mut iter, mut model) = (m.iter(), expected.iter());
+        let (mut iter_mut, mut model_mut) = (copy.iter_mut(), expected_copy.iter_mut());
+        loop {
+            let back = rng.next().is_multiple_of(2);
+            let (got, want) = if back {
+                (iter.next_back(), model.next_back())
+            } else {
+                (iter.next(), model.next())
+            };
+            assert_eq!(want, got);
+            assert_eq!(model.len(), iter.len());
+            let (got, want) = if back {
+                (iter_mut.next_back(), model_mut.next_back())
+            } else {
+                (iter_mut.next(), model_mut.next())
+            };
+            assert_eq!(want, got);
+            assert_eq!(model_mut.len(), iter_mut.len());
+            if got.is_none() {
+                break;
+            }
+        }
+        assert_eq!(None, iter.next());
+        assert_eq!(None, iter_mut.next_back());
+
+        for (k, v) in m.iter_mut().rev().take(10) {
+            *v += k;
+        }
+        let doubled = expected.keys().rev().take(10).map(|k| (k, k * 2));
+        assert!(m.iter().rev().take(10).map(|(k, v)| (k, *v)).eq(doubled));
+    }
+
+    #[test]
+    fn clones_are_independent() {
+        let mut m: TreeMap<u32, String> = (0..50).map(|k| (k, k.to_string())).collect();
+        let c = m.clone();
+        check_rb(&c.root);
+        assert_eq!(m, c);
+        m.get_mut(&3).unwrap().push('!');
+        m.remove(&4);
+        assert_eq!(Some(&"3".to_string()), c.get(&3));
+        assert_eq!(50, c.len());
+        assert_ne!(m, c);
+    }
+
+    #[test]
+    fn matches_btreemap() {
+        let mut rng = Rng(0x9E3779B97F4A7C15);
+        let mut m = TreeMap::new();
+        let mut expected = BTreeMap::new();
+        for round in 0..if cfg!(miri) { 500 } else { 20_000usize } {
+            let k = rng.next() % 1000;
+            let v = rng.next();
+            if rng.next().is_multiple_of(3) {
+                assert_eq!(expected.remove(&k), m.remove(&k));
+            } else {
+                assert_eq!(expected.insert(k, v), m.insert(k, v));
+            }
+            assert_eq!(expected.get(&k), m.get(&k));
+            assert_eq!(expected.len(), m.len());
+            if round.is_multiple_of(500) {
+                check_rb(&m.root);
+                assert!(m.iter().eq(expected.iter()));
+            }
+        }
+        check_rb(&m.root);
+        assert!(m.iter().eq(expected.iter()));
+        assert!(m.into_iter().eq(expected));
+    }
+
+    #[test]
+    fn removing_absent_keys_leaves_a_valid_tree() {
+        let mut m: TreeMap<i32, ()> = (0..200).map(|k| (k * 2, ())).collect();
+        // every gap between keys, and past both ends
+        for k in (-1..=401).step_by(2) {
+            assert_eq!(None, m.remove_entry(&k));
+            check_rb(&m.root);
+        }
+        assert_eq!(200, m.len());
+        assert!(m.keys().copied().eq((0..200).map(|k| k * 2)));
+        assert_eq!(None, TreeMap::<i32, ()>::new().remove(&0));
+    }
+
+    #[test]
+    fn sorted_input_stays_balanced() {
+        let n = if cfg!(miri) { 1_000 } else { 100_000 };
+        let mut m = TreeMap::new();
+        for k in 0..n {
+            m.insert(k, ());
+        }
+        check_rb(&m.root);
+        for k in (0..n).step_by(3) {
+            assert_eq!(Some(()), m.remove(&k));
+        }
+        check_rb(&m.root);
+        assert!(m.keys().copied().eq((0..n).filter(|k| k % 3 != 0)));
+    }
+}
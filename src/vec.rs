@@ -1,72 +1,113 @@
-#![allow(dead_code, warnings)]
+#![allow(dead_code)]
 /*
 Reference implementation of MyVec taken from Rustonomicon:
 https://doc.rust-lang.org/nomicon/vec
-Purposefully left incomplete but will be used for a reference for unsafe rust actions when implementing the rest of our DSs
+Used as the reference for unsafe rust actions when implementing the rest of our DSs, and filled out
+into a usable Vec: with_capacity, reserve/reserve_exact/try_reserve, shrink_to_fit, truncate, clear,
+drain, split_off, retain, dedup, swap_remove, Clone, Extend/FromIterator
+Generic over where its memory comes from, `MyVec::new_in(&arena)` for anything but the global allocator
 */
 
-use std::alloc::{self, Layout};
+use std::fmt;
+use std::iter::FusedIterator;
+use std::marker::PhantomData;
 use std::mem::{self, ManuallyDrop};
+use std::ops::{Bound, RangeBounds};
 use std::ptr::{self, NonNull};
 
-pub struct MyVec<T> {
-    ptr: NonNull<T>,
-    cap: usize,
+use crate::allocator::{Allocator, Global};
+use crate::raw_buf::RawBuf;
+pub use crate::raw_buf::TryReserveError;
+
+pub struct MyVec<T, A: Allocator = Global> {
+    buf: RawBuf<T, A>,
     len: usize,
 }
 
-unsafe impl<T: Send> Send for MyVec<T> {}
-unsafe impl<T: Sync> Sync for MyVec<T> {}
-
 impl<T> MyVec<T> {
     pub fn new() -> Self {
-        assert!(mem::size_of::<T>() != 0, "We're not ready to handle ZSTs");
+        Self::new_in(Global)
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self::with_capacity_in(capacity, Global)
+    }
+}
+
+impl<T, A: Allocator> MyVec<T, A> {
+    /// empty, allocating from alloc once something is pushed
+    pub fn new_in(alloc: A) -> Self {
         MyVec {
-            ptr: NonNull::dangling(),
+            buf: RawBuf::new_in(alloc),
             len: 0,
-            cap: 0,
         }
     }
 
-    fn grow(&mut self) {
-        let (new_cap, new_layout) = if self.cap == 0 {
-            (1, Layout::array::<T>(1).unwrap())
-        } else {
-            let new_cap = 2 * self.cap;
-            let new_layout = Layout::array::<T>(new_cap).unwrap();
-            (new_cap, new_layout)
-        };
+    pub fn with_capacity_in(capacity: usize, alloc: A) -> Self {
+        MyVec {
+            buf: RawBuf::with_capacity_in(capacity, alloc),
+            len: 0,
+        }
+    }
 
-        assert!(
-            new_layout.size() <= isize::MAX as usize,
-            "Allocation too large"
-        );
+    pub fn allocator(&self) -> &A {
+        self.buf.allocator()
+    }
 
-        let new_ptr = if self.cap == 0 {
-            unsafe { alloc::alloc(new_layout) }
-        } else {
-            let old_layout = Layout::array::<T>(self.cap).unwrap();
-            let old_ptr = self.ptr.as_ptr() as *mut u8;
-            unsafe { alloc::realloc(old_ptr, old_layout, new_layout.size()) }
-        };
+    pub fn len(&self) -> usize {
+        self.len
+    }
 
-        self.ptr = match NonNull::new(new_ptr as *mut T) {
-            Some(p) => p,
-            None => alloc::handle_alloc_error(new_layout),
-        };
-        self.cap = new_cap;
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.buf.capacity()
+    }
+
+    /// Makes room for at least additional more items, doubling so repeated pushes stay amortized O(1)
+    pub fn try_reserve(&mut self, additional: usize) -> Result<(), TryReserveError> {
+        self.buf.try_reserve(self.len, additional)
+    }
+
+    /// Makes room for exactly additional more items, no more
+    pub fn try_reserve_exact(&mut self, additional: usize) -> Result<(), TryReserveError> {
+        self.buf.try_reserve_exact(self.len, additional)
+    }
+
+    /// Panics on capacity overflow and aborts if the allocator fails, like Vec::reserve
+    pub fn reserve(&mut self, additional: usize) {
+        self.buf.reserve(self.len, additional);
+    }
+
+    pub fn reserve_exact(&mut self, additional: usize) {
+        self.buf.reserve_exact(self.len, additional);
+    }
+
+    /// Gives back any capacity past len
+    pub fn shrink_to_fit(&mut self) {
+        self.buf.shrink_to(self.len);
+    }
+
+    fn grow(&mut self) {
+        self.buf.reserve(self.len, 1);
+    }
+
+    fn ptr(&self) -> *mut T {
+        self.buf.ptr()
     }
 
     pub fn push(&mut self, elem: T) {
-        if self.len == self.cap {
+        if self.len == self.buf.capacity() {
             self.grow();
         }
 
         unsafe {
-            ptr::write(self.ptr.as_ptr().add(self.len), elem);
+            ptr::write(self.ptr().add(self.len), elem);
         }
 
-        self.len = self.len + 1;
+        self.len += 1;
     }
 
     pub fn pop(&mut self) -> Option<T> {
@@ -74,7 +115,7 @@ impl<T> MyVec<T> {
             None
         } else {
             self.len -= 1;
-            unsafe { Some(ptr::read(self.ptr.as_ptr().add(self.len))) }
+            unsafe { Some(ptr::read(self.ptr().add(self.len))) }
         }
     }
 
@@ -82,18 +123,18 @@ impl<T> MyVec<T> {
         // Note: `<=` because it's valid to insert after everything
         // which would be equivalent to push.
         assert!(index <= self.len, "index out of bounds");
-        if self.cap == self.len {
+        if self.buf.capacity() == self.len {
             self.grow();
         }
 
         unsafe {
             // ptr::copy(src, dest, len): "copy from src to dest len elems"
             ptr::copy(
-                self.ptr.as_ptr().add(index),
-                self.ptr.as_ptr().add(index + 1),
+                self.ptr().add(index),
+                self.ptr().add(index + 1),
                 self.len - index,
             );
-            ptr::write(self.ptr.as_ptr().add(index), elem);
+            ptr::write(self.ptr().add(index), elem);
             self.len += 1;
         }
     }
@@ -103,138 +144,606 @@ impl<T> MyVec<T> {
         assert!(index < self.len, "index out of bounds");
         unsafe {
             self.len -= 1;
-            let result = ptr::read(self.ptr.as_ptr().add(index));
+            let result = ptr::read(self.ptr().add(index));
             ptr::copy(
-                self.ptr.as_ptr().add(index + 1),
-                self.ptr.as_ptr().add(index),
+                self.ptr().add(index + 1),
+                self.ptr().add(index),
                 self.len - index,
             );
             result
         }
     }
+
+    /// Removes index in O(1) by moving the last item into its place
+    pub fn swap_remove(&mut self, index: usize) -> T {
+        assert!(index < self.len, "index out of bounds");
+        unsafe {
+            self.len -= 1;
+            let result = ptr::read(self.ptr().add(index));
+            ptr::copy(self.ptr().add(self.len), self.ptr().add(index), 1);
+            result
+        }
+    }
+
+    /// Drops everything past len, keeps the allocation
+    pub fn truncate(&mut self, len: usize) {
+        if len >= self.len {
+            return;
+        }
+        let tail = ptr::slice_from_raw_parts_mut(unsafe { self.ptr().add(len) }, self.len - len);
+        // shorten first, a panicking drop then leaks the rest instead of dropping it twice
+        self.len = len;
+        unsafe { ptr::drop_in_place(tail) };
+    }
+
+    pub fn clear(&mut self) {
+        self.truncate(0);
+    }
+
+    /// Splits into [0, at) and [at, len), returning the second half in a new allocation
+    pub fn split_off(&mut self, at: usize) -> Self
+    where
+        A: Clone,
+    {
+        assert!(at <= self.len, "index out of bounds");
+        let mut other = Self::with_capacity_in(self.len - at, self.allocator().clone());
+        unsafe {
+            ptr::copy_nonoverlapping(self.ptr().add(at), other.ptr(), self.len - at);
+        }
+        other.len = self.len - at;
+        self.len = at;
+        other
+    }
+
+    /// Keeps only the items keep returns true for, in order.
+    /// If keep panics the vec keeps what it had kept so far plus everything not yet visited
+    pub fn retain<F: FnMut(&T) -> bool>(&mut self, mut keep: F) {
+        // [0, visited - deleted) are the kept items, [visited, len) are still to look at.
+        // Dropping it closes the gap between them, whether retain finishes or keep panics
+        struct Compact<'a, T, A: Allocator> {
+            vec: &'a mut MyVec<T, A>,
+            len: usize,
+            visited: usize,
+            deleted: usize,
+        }
+
+        impl<T, A: Allocator> Drop for Compact<'_, T, A> {
+            fn drop(&mut self) {
+                unsafe {
+                    if self.deleted > 0 {
+                        ptr::copy(
+                            self.vec.ptr().add(self.visited),
+                            self.vec.ptr().add(self.visited - self.deleted),
+                            self.len - self.visited,
+                        );
+                    }
+                    self.vec.len = self.len - self.deleted;
+                }
+            }
+        }
+
+        let len = self.len;
+        let mut compact = Compact {
+            vec: self,
+            len,
+            visited: 0,
+            deleted: 0,
+        };
+        while compact.visited < len {
+            unsafe {
+                let cur = compact.vec.ptr().add(compact.visited);
+                let kept = keep(&*cur);
+                // counted as visited first, so a panicking drop isn't dropped again
+                compact.visited += 1;
+                if !kept {
+                    compact.deleted += 1;
+                    ptr::drop_in_place(cur);
+                } else if compact.deleted > 0 {
+                    let to = compact.vec.ptr().add(compact.visited - 1 - compact.deleted);
+                    ptr::copy_nonoverlapping(cur, to, 1);
+                }
+            }
+        }
+    }
+
+    /// Removes consecutive items same_bucket(item, previous kept item) says are duplicates
+    pub fn dedup_by<F: FnMut(&mut T, &mut T) -> bool>(&mut self, mut same_bucket: F) {
+        let len = self.len;
+        if len <= 1 {
+            return;
+        }
+        self.len = 0;
+        // [0, write) are the kept items
+        let mut write = 1;
+        for read in 1..len {
+            unsafe {
+                let cur = self.ptr().add(read);
+                let prev = self.ptr().add(write - 1);
+                if same_bucket(&mut *cur, &mut *prev) {
+                    ptr::drop_in_place(cur);
+                } else {
+                    if read != write {
+                        ptr::copy_nonoverlapping(cur, self.ptr().add(write), 1);
+                    }
+                    write += 1;
+                }
+            }
+        }
+        self.len = write;
+    }
+
+    pub fn dedup(&mut self)
+    where
+        T: PartialEq,
+    {
+        self.dedup_by(|a, b| a == b)
+    }
+
+    /// Removes range from the vec, yielding the removed items.
+    /// Whatever the Drain doesn't get to is dropped with it, then the tail slides down
+    pub fn drain<R: RangeBounds<usize>>(&mut self, range: R) -> Drain<'_, T, A> {
+        let start = match range.start_bound() {
+            Bound::Included(&s) => s,
+            Bound::Excluded(&s) => s + 1,
+            Bound::Unbounded => 0,
+        };
+        let end = match range.end_bound() {
+            Bound::Included(&e) => e + 1,
+            Bound::Excluded(&e) => e,
+            Bound::Unbounded => self.len,
+        };
+        assert!(start <= end && end <= self.len, "drain range out of bounds");
+
+        let len = self.len;
+        // if the Drain is leaked the drained items and the tail leak with it
+        self.len = start;
+        let items = unsafe { std::slice::from_raw_parts(self.ptr().add(start), end - start) };
+        Drain {
+            items: items.iter(),
+            tail_start: end,
+            tail_len: len - end,
+            vec: NonNull::from(self),
+            marker: PhantomData,
+        }
+    }
+}
+
+pub struct Drain<'a, T, A: Allocator = Global> {
+    items: std::slice::Iter<'a, T>,
+    tail_start: usize,
+    tail_len: usize,
+    vec: NonNull<MyVec<T, A>>,
+    marker: PhantomData<&'a mut MyVec<T, A>>,
 }
 
-impl<T> Drop for MyVec<T> {
+impl<T, A: Allocator> Iterator for Drain<'_, T, A> {
+    type Item = T;
+    fn next(&mut self) -> Option<T> {
+        self.items.next().map(|item| unsafe { ptr::read(item) })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.items.size_hint()
+    }
+}
+
+impl<T, A: Allocator> DoubleEndedIterator for Drain<'_, T, A> {
+    fn next_back(&mut self) -> Option<T> {
+        self.items
+            .next_back()
+            .map(|item| unsafe { ptr::read(item) })
+    }
+}
+
+impl<T, A: Allocator> ExactSizeIterator for Drain<'_, T, A> {}
+
+impl<T, A: Allocator> FusedIterator for Drain<'_, T, A> {}
+
+impl<T, A: Allocator> Drop for Drain<'_, T, A> {
     fn drop(&mut self) {
-        if self.cap != 0 {
-            while let Some(_) = self.pop() {}
-            let layout = Layout::array::<T>(self.cap).unwrap();
-            unsafe {
-                alloc::dealloc(self.ptr.as_ptr() as *mut u8, layout);
+        for _ in &mut *self {}
+        unsafe {
+            let vec = self.vec.as_mut();
+            let start = vec.len;
+            if self.tail_len > 0 && self.tail_start != start {
+                ptr::copy(
+                    vec.ptr().add(self.tail_start),
+                    vec.ptr().add(start),
+                    self.tail_len,
+                );
             }
+            vec.len = start + self.tail_len;
+        }
+    }
+}
+
+impl<T: Clone, A: Allocator + Clone> Clone for MyVec<T, A> {
+    fn clone(&self) -> Self {
+        let mut v = Self::with_capacity_in(self.len, self.allocator().clone());
+        // pushing keeps len right if a clone panics
+        for item in self.iter() {
+            v.push(item.clone());
+        }
+        v
+    }
+}
+
+impl<T: fmt::Debug, A: Allocator> fmt::Debug for MyVec<T, A> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<T, A: Allocator> Extend<T> for MyVec<T, A> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        let iter = iter.into_iter();
+        self.reserve(iter.size_hint().0);
+        for item in iter {
+            self.push(item);
         }
     }
 }
 
+impl<T> FromIterator<T> for MyVec<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut v = Self::new();
+        v.extend(iter);
+        v
+    }
+}
+
+impl<T, A: Allocator> Drop for MyVec<T, A> {
+    fn drop(&mut self) {
+        self.clear();
+        // RawBuf frees the memory
+    }
+}
+
 use std::ops::Deref;
 
-impl<T> Deref for MyVec<T> {
+impl<T, A: Allocator> Deref for MyVec<T, A> {
     type Target = [T];
     fn deref(&self) -> &[T] {
-        unsafe { std::slice::from_raw_parts(self.ptr.as_ptr(), self.len) }
+        unsafe { std::slice::from_raw_parts(self.ptr(), self.len) }
     }
 }
 
 use std::ops::DerefMut;
 
-impl<T> DerefMut for MyVec<T> {
+impl<T, A: Allocator> DerefMut for MyVec<T, A> {
     fn deref_mut(&mut self) -> &mut [T] {
-        unsafe { std::slice::from_raw_parts_mut(self.ptr.as_ptr(), self.len) }
+        unsafe { std::slice::from_raw_parts_mut(self.ptr(), self.len) }
     }
 }
 
-pub struct IntoIter<T> {
-    buf: NonNull<T>,
-    cap: usize,
+pub struct IntoIter<T, A: Allocator = Global> {
+    _buf: RawBuf<T, A>, // we don't actually care about this. Just need it to live.
     start: *const T,
     end: *const T,
 }
 
-impl<T> IntoIterator for MyVec<T> {
+impl<T, A: Allocator> IntoIterator for MyVec<T, A> {
     type Item = T;
-    type IntoIter = IntoIter<T>;
-    fn into_iter(self) -> IntoIter<T> {
+    type IntoIter = IntoIter<T, A>;
+    fn into_iter(self) -> IntoIter<T, A> {
         let vec = ManuallyDrop::new(self);
 
-        let ptr = vec.ptr;
-        let cap = vec.cap;
+        // can't destructure MyVec since it's Drop
+        let buf = unsafe { ptr::read(&vec.buf) };
         let len = vec.len;
+        let ptr = buf.ptr();
 
-        unsafe {
-            IntoIter {
-                buf: ptr,
-                cap,
-                start: ptr.as_ptr(),
-                end: if cap == 0 {
-                    ptr.as_ptr()
-                } else {
-                    ptr.as_ptr().add(len)
-                },
-            }
+        IntoIter {
+            start: ptr,
+            // for zero sized types the pointers only count, a byte per item
+            end: if mem::size_of::<T>() == 0 {
+                ptr.wrapping_byte_add(len)
+            } else {
+                unsafe { ptr.add(len) }
+            },
+            _buf: buf,
         }
     }
 }
 
-impl<T> Iterator for IntoIter<T> {
+impl<T, A: Allocator> Iterator for IntoIter<T, A> {
     type Item = T;
     fn next(&mut self) -> Option<T> {
         if self.start == self.end {
             None
         } else {
             unsafe {
-                let result = ptr::read(self.start);
-                self.start = self.start.offset(1);
-                Some(result)
+                if mem::size_of::<T>() == 0 {
+                    self.start = self.start.wrapping_byte_add(1);
+                    Some(ptr::read(NonNull::dangling().as_ptr()))
+                } else {
+                    let result = ptr::read(self.start);
+                    self.start = self.start.offset(1);
+                    Some(result)
+                }
             }
         }
     }
 
     fn size_hint(&self) -> (usize, Option<usize>) {
-        let len = (self.end as usize - self.start as usize) / mem::size_of::<T>();
+        let elem_size = mem::size_of::<T>().max(1);
+        let len = (self.end as usize - self.start as usize) / elem_size;
         (len, Some(len))
     }
 }
 
-impl<T> DoubleEndedIterator for IntoIter<T> {
+impl<T, A: Allocator> DoubleEndedIterator for IntoIter<T, A> {
     fn next_back(&mut self) -> Option<T> {
         if self.start == self.end {
             None
         } else {
             unsafe {
-                self.end = self.end.offset(-1);
-                Some(ptr::read(self.end))
+                if mem::size_of::<T>() == 0 {
+                    self.end = self.end.wrapping_byte_sub(1);
+                    Some(ptr::read(NonNull::dangling().as_ptr()))
+                } else {
+                    self.end = self.end.offset(-1);
+                    Some(ptr::read(self.end))
+                }
             }
         }
     }
 }
 
-impl<T> Drop for IntoIter<T> {
+impl<T, A: Allocator> Drop for IntoIter<T, A> {
     fn drop(&mut self) {
-        if self.cap != 0 {
-            for _ in &mut *self {}
-            let layout = Layout::array::<T>(self.cap).unwrap();
-            unsafe {
-                alloc::dealloc(self.buf.as_ptr() as *mut u8, layout);
-            }
-        }
+        // only the items are ours to drop, RawBuf frees the memory
+        for _ in &mut *self {}
+    }
+}
+
+impl<T> Default for MyVec<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+use crate::traits::{Collection, Lifo, RandomAccess};
+
+impl<T, A: Allocator> Collection for MyVec<T, A> {
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn clear(&mut self) {
+        MyVec::clear(self)
+    }
+}
+
+impl<T, A: Allocator> Lifo<T> for MyVec<T, A> {
+    fn push(&mut self, item: T) {
+        MyVec::push(self, item)
+    }
+
+    fn pop(&mut self) -> Option<T> {
+        MyVec::pop(self)
+    }
+}
+
+impl<T, A: Allocator> RandomAccess<T> for MyVec<T, A> {
+    fn get(&self, index: usize) -> Option<&T> {
+        (**self).get(index)
+    }
+
+    fn get_mut(&mut self, index: usize) -> Option<&mut T> {
+        (**self).get_mut(index)
     }
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::test_util::{drops, Zst};
 
     #[test]
     fn vec_works() {
-        dbg!(std::mem::size_of::<MyVec<i8>>());
-        dbg!(std::mem::size_of::<MyVec<i64>>());
-        dbg!(std::mem::align_of::<MyVec<i8>>());
-        dbg!(std::mem::align_of::<MyVec<i64>>());
-        dbg!(std::mem::size_of::<MyVec<i128>>());
-        dbg!(Layout::array::<i8>(1).unwrap());
-        dbg!(Layout::array::<i8>(5).unwrap());
-        dbg!(Layout::array::<i64>(1).unwrap());
-        dbg!(Layout::array::<i64>(5).unwrap());
-        assert!(true);
+        // pointer, capacity and length whatever the item type, with room for Option's niche
+        let words = 3 * mem::size_of::<usize>();
+        assert_eq!(words, mem::size_of::<MyVec<i8>>());
+        assert_eq!(words, mem::size_of::<MyVec<i128>>());
+        assert_eq!(words, mem::size_of::<Option<MyVec<i64>>>());
+        assert_eq!(mem::align_of::<usize>(), mem::align_of::<MyVec<i8>>());
+    }
+
+    #[test]
+    fn zero_sized_types() {
+        let mut v = MyVec::new();
+        assert_eq!(usize::MAX, v.capacity());
+        for _ in 0..10 {
+            v.push(Zst);
+        }
+        v.insert(3, Zst);
+        v.insert(11, Zst);
+        assert_eq!(12, v.len());
+        drop(v.remove(0));
+        drop(v.pop());
+        assert_eq!(2, drops());
+        assert_eq!(10, v.iter().count());
+
+        let mut it = v.into_iter();
+        assert_eq!((10, Some(10)), it.size_hint());
+        drop(it.next());
+        drop(it.next_back());
+        assert_eq!((8, Some(8)), it.size_hint());
+        assert_eq!(4, drops());
+        // dropping the iterator halfway drops the rest
+        drop(it);
+        assert_eq!(12, drops());
+
+        let mut units = MyVec::new();
+        units.push(());
+        units.push(());
+        assert_eq!(2, units.into_iter().rev().count());
+        assert_eq!(0, MyVec::<()>::new().into_iter().count());
+    }
+
+    fn to_vec<T: Clone>(v: &MyVec<T>) -> Vec<T> {
+        v.iter().cloned().collect()
+    }
+
+    #[test]
+    fn capacity_management() {
+        let mut v = MyVec::<u32>::with_capacity(10);
+        assert_eq!(10, v.capacity());
+        v.extend(0..10);
+        assert_eq!(10, v.capacity());
+        v.push(10);
+        assert_eq!(20, v.capacity());
+        v.reserve(5);
+        assert_eq!(20, v.capacity());
+        v.reserve_exact(15);
+        assert_eq!(26, v.capacity());
+        v.truncate(4);
+        v.shrink_to_fit();
+        assert_eq!(4, v.capacity());
+        assert_eq!(vec![0, 1, 2, 3], to_vec(&v));
+        v.clear();
+        v.shrink_to_fit();
+        assert_eq!(0, v.capacity());
+        v.push(7);
+        assert_eq!(vec![7], to_vec(&v));
+    }
+
+    #[test]
+    fn try_reserve_reports_errors() {
+        let mut v = MyVec::<u64>::new();
+        assert_eq!(Ok(()), v.try_reserve(3));
+        assert!(v.capacity() >= 3);
+        v.push(1);
+        assert_eq!(
+            Err(TryReserveError::CapacityOverflow),
+            v.try_reserve(usize::MAX)
+        );
+        // fits in usize, but not in isize::MAX bytes
+        assert_eq!(
+            Err(TryReserveError::CapacityOverflow),
+            v.try_reserve_exact(usize::MAX / 4)
+        );
+        assert_eq!(vec![1], to_vec(&v));
+        assert_eq!(
+            "capacity overflow",
+            TryReserveError::CapacityOverflow.to_string()
+        );
+
+        let mut units = MyVec::<()>::new();
+        assert_eq!(Ok(()), units.try_reserve(usize::MAX));
+        units.push(());
+        assert_eq!(
+            Err(TryReserveError::CapacityOverflow),
+            units.try_reserve(usize::MAX)
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "capacity overflow")]
+    fn reserve_panics_on_overflow() {
+        let mut v = MyVec::<u8>::new();
+        v.push(1);
+        v.reserve(usize::MAX);
+    }
+
+    #[test]
+    fn removing_items() {
+        let mut v: MyVec<i32> = (0..10).collect();
+        assert_eq!(2, v.swap_remove(2));
+        assert_eq!(8, v.swap_remove(8));
+        assert_eq!(vec![0, 1, 9, 3, 4, 5, 6, 7], to_vec(&v));
+
+        let tail = v.split_off(5);
+        assert_eq!(vec![0, 1, 9, 3, 4], to_vec(&v));
+        assert_eq!(vec![5, 6, 7], to_vec(&tail));
+        assert!(v.split_off(5).is_empty());
+
+        v.retain(|x| x % 3 != 0);
+        assert_eq!(vec![1, 4], to_vec(&v));
+
+        let mut d: MyVec<i32> = [1, 1, 2, 3, 3, 3, 1, 4, 4].into_iter().collect();
+        d.dedup();
+        assert_eq!(vec![1, 2, 3, 1, 4], to_vec(&d));
+        let mut words: MyVec<&str> = ["a", "A", "b", "B", "b"].into_iter().collect();
+        words.dedup_by(|a, b| a.eq_ignore_ascii_case(b));
+        assert_eq!(vec!["a", "b"], to_vec(&words));
+    }
+
+    #[test]
+    fn drain_ranges() {
+        let mut v: MyVec<String> = (0..6).map(|i| i.to_string()).collect();
+        let drained: Vec<String> = v.drain(1..3).collect();
+        assert_eq!(vec!["1", "2"], drained);
+        assert_eq!(vec!["0", "3", "4", "5"], to_vec(&v));
+
+        // dropping a half used drain drops the rest and closes the gap
+        let mut d = v.drain(..=2);
+        assert_eq!(Some("4".to_string()), d.next_back());
+        assert_eq!(2, d.len());
+        drop(d);
+        assert_eq!(vec!["5"], to_vec(&v));
+
+        assert_eq!(0, v.drain(1..).count());
+        assert_eq!(1, v.drain(..).count());
+        assert!(v.is_empty());
+
+        // a leaked drain leaks the items, but leaves the vec usable
+        let mut v: MyVec<i32> = (0..4).collect();
+        mem::forget(v.drain(1..2));
+        assert_eq!(vec![0], to_vec(&v));
+        v.push(9);
+        assert_eq!(vec![0, 9], to_vec(&v));
+    }
+
+    #[test]
+    fn clone_and_debug() {
+        let v: MyVec<String> = ["a", "b"].iter().map(|s| s.to_string()).collect();
+        let c = v.clone();
+        assert_eq!(to_vec(&v), to_vec(&c));
+        assert_eq!(r#"["a", "b"]"#, format!("{:?}", c));
+    }
+
+    #[test]
+    fn retain_keeps_everything_it_has_not_dropped_when_keep_panics() {
+        let mut v: MyVec<String> = (0..8).map(|i| i.to_string()).collect();
+        let panicked = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            v.retain(|s| {
+                assert_ne!("5", s);
+                s != "1" && s != "2"
+            })
+        }));
+        assert!(panicked.is_err());
+        assert_eq!(["0", "3", "4", "5", "6", "7"], to_vec(&v).as_slice());
+    }
+
+    #[test]
+    fn every_item_dropped_once() {
+        let before = drops();
+        let mut v: MyVec<Zst> = (0..10).map(|_| Zst).collect();
+        v.truncate(8);
+        v.retain({
+            let mut i = 0;
+            move |_| {
+                i += 1;
+                i % 2 == 0
+            }
+        });
+        assert_eq!(4, v.len());
+        drop(v.drain(1..3));
+        v.dedup_by(|_, _| true);
+        assert_eq!(1, v.len());
+        drop(v.clone());
+        drop(v);
+        assert_eq!(11, drops() - before);
+    }
+
+    #[test]
+    fn empty_vecs_never_allocate() {
+        let v = MyVec::<String>::new();
+        assert_eq!(0, v.capacity());
+        assert_eq!(0, v.len());
+        assert_eq!(None, v.into_iter().next());
+        drop(MyVec::<u64>::new());
     }
 }
@@ -0,0 +1,235 @@
+use rust_dsa::array_list::ArrayList;
+use rust_dsa::binary_search_tree::BinarySearchTree;
+use rust_dsa::doubly_linked_list::DubLinkedList;
+use rust_dsa::linked_list::LinkedList;
+use rust_dsa::priority_queue::PriorityQueue;
+use rust_dsa::queue::Queue;
+use rust_dsa::ring_buffer::RingBuffer;
+use rust_dsa::stack::Stack;
+use rust_dsa::tree_map::TreeMap;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt::Debug;
+use std::hash::{Hash, Hasher};
+
+fn hash<T: Hash>(t: &T) -> u64 {
+    let mut h = DefaultHasher::new();
+    t.hash(&mut h);
+    h.finish()
+}
+
+fn samples() -> Vec<Vec<i32>> {
+    vec![
+        vec![],
+        vec![1],
+        vec![1, 2],
+        vec![2, 1],
+        vec![1, 2, 3],
+        vec![1, 2, 2, 3],
+        vec![3, 2, 1],
+        vec![0, 5, -3, 5],
+    ]
+}
+
+/// Builds every sample as both `C` and its std model `M`, then checks that
+/// collecting, Default, ==, partial_cmp and Hash all agree with the model.
+/// `drain` and `drain_model` empty each one the way the collection is meant to be read.
+fn check<A, C, M>(samples: &[Vec<A>], drain: fn(C) -> Vec<A>, drain_model: fn(M) -> Vec<A>)
+where
+    A: Clone + PartialEq + Debug,
+    C: FromIterator<A> + Default + PartialEq + PartialOrd + Hash,
+    M: FromIterator<A> + PartialEq + PartialOrd,
+{
+    assert_eq!(Vec::<A>::new(), drain(C::default()));
+
+    for xs in samples {
+        let c: C = xs.iter().cloned().collect();
+        let m: M = xs.iter().cloned().collect();
+        assert_eq!(drain_model(m), drain(c), "collecting {xs:?}");
+
+        for ys in samples {
+            let (a, b): (C, C) = (xs.iter().cloned().collect(), ys.iter().cloned().collect());
+            let (ma, mb): (M, M) = (xs.iter().cloned().collect(), ys.iter().cloned().collect());
+            assert_eq!(ma == mb, a == b, "{xs:?} == {ys:?}");
+            assert_eq!(ma.partial_cmp(&mb), a.partial_cmp(&b), "{xs:?} cmp {ys:?}");
+            if a == b {
+                assert_eq!(hash(&a), hash(&b), "hash of {xs:?} and {ys:?}");
+            }
+        }
+    }
+}
+
+/// `cmp` agrees with the model wherever `C` is totally ordered
+fn check_ord<A, C, M>(samples: &[Vec<A>])
+where
+    A: Clone + Debug,
+    C: FromIterator<A> + Ord,
+    M: FromIterator<A> + Ord,
+{
+    for xs in samples {
+        for ys in samples {
+            let (a, b): (C, C) = (xs.iter().cloned().collect(), ys.iter().cloned().collect());
+            let (ma, mb): (M, M) = (xs.iter().cloned().collect(), ys.iter().cloned().collect());
+            assert_eq!(ma.cmp(&mb), a.cmp(&b), "{xs:?} cmp {ys:?}");
+        }
+    }
+}
+
+/// extending a collected `C` matches doing the same to the model
+fn check_extend<A, C, M>(samples: &[Vec<A>], drain: fn(C) -> Vec<A>, drain_model: fn(M) -> Vec<A>)
+where
+    A: Clone + PartialEq + Debug,
+    C: FromIterator<A> + Extend<A>,
+    M: FromIterator<A> + Extend<A>,
+{
+    for xs in samples {
+        for ys in samples {
+            let mut c: C = xs.iter().cloned().collect();
+            let mut m: M = xs.iter().cloned().collect();
+            c.extend(ys.iter().cloned());
+            m.extend(ys.iter().cloned());
+            assert_eq!(drain_model(m), drain(c), "{xs:?} extended with {ys:?}");
+        }
+    }
+}
+
+/// the std model for a BinarySearchTree: a Vec kept sorted
+#[derive(PartialEq, PartialOrd)]
+struct SortedVec(Vec<i32>);
+
+impl FromIterator<i32> for SortedVec {
+    fn from_iter<I: IntoIterator<Item = i32>>(iter: I) -> Self {
+        let mut s = SortedVec(vec![]);
+        s.extend(iter);
+        s
+    }
+}
+
+impl Extend<i32> for SortedVec {
+    fn extend<I: IntoIterator<Item = i32>>(&mut self, iter: I) {
+        self.0.extend(iter);
+        self.0.sort();
+    }
+}
+
+/// the std model for a RingBuffer: a VecDeque filled from the front, like `push_front`
+#[derive(PartialEq, PartialOrd)]
+struct FrontFilled(VecDeque<i32>);
+
+impl FromIterator<i32> for FrontFilled {
+    fn from_iter<I: IntoIterator<Item = i32>>(iter: I) -> Self {
+        let mut d = VecDeque::new();
+        for v in iter {
+            d.push_front(v);
+        }
+        FrontFilled(d)
+    }
+}
+
+fn pop_all<T>(pop: impl FnMut() -> Option<T>) -> Vec<T> {
+    std::iter::from_fn(pop).collect()
+}
+
+#[test]
+fn stack_matches_vec() {
+    let drain: fn(Stack<i32>) -> Vec<i32> = |mut s| pop_all(|| s.pop());
+    let drain_model: fn(Vec<i32>) -> Vec<i32> = |mut v| pop_all(|| v.pop());
+    check(&samples(), drain, drain_model);
+    check_ord::<_, Stack<i32>, Vec<i32>>(&samples());
+    check_extend(&samples(), drain, drain_model);
+}
+
+#[test]
+fn queue_matches_vec_deque() {
+    let drain: fn(Queue<i32>) -> Vec<i32> = |mut q| pop_all(|| q.deque());
+    let drain_model: fn(VecDeque<i32>) -> Vec<i32> = |mut d| pop_all(|| d.pop_front());
+    check(&samples(), drain, drain_model);
+    check_ord::<_, Queue<i32>, VecDeque<i32>>(&samples());
+    check_extend(&samples(), drain, drain_model);
+}
+
+#[test]
+fn linked_lists_match_std_linked_list() {
+    type Std = std::collections::LinkedList<i32>;
+    let drain_model: fn(Std) -> Vec<i32> = |mut l| pop_all(|| l.pop_front());
+
+    let drain: fn(LinkedList<i32>) -> Vec<i32> = |mut l| pop_all(|| l.pop_front());
+    check(&samples(), drain, drain_model);
+    check_ord::<_, LinkedList<i32>, Std>(&samples());
+    check_extend(&samples(), drain, drain_model);
+
+    let drain: fn(DubLinkedList<i32>) -> Vec<i32> = |mut l| pop_all(|| l.pop_front());
+    check(&samples(), drain, drain_model);
+    check_ord::<_, DubLinkedList<i32>, Std>(&samples());
+    check_extend(&samples(), drain, drain_model);
+}
+
+#[test]
+fn ring_buffer_matches_front_filled_vec_deque() {
+    let drain: fn(RingBuffer<i32>) -> Vec<i32> = |mut r| pop_all(|| r.pop_back());
+    let drain_model: fn(FrontFilled) -> Vec<i32> = |mut d| pop_all(|| d.0.pop_back());
+    check(&samples(), drain, drain_model);
+
+    // capacity plays no part in equality
+    let mut small = RingBuffer::new(3);
+    small.extend([1, 2, 3]);
+    let big: RingBuffer<i32> = [1, 2, 3].into_iter().collect();
+    assert_eq!(small, big);
+    assert_eq!(hash(&small), hash(&big));
+
+    // extending a full buffer overwrites the oldest items
+    let mut r: RingBuffer<i32> = [1, 2, 3].into_iter().collect();
+    r.extend([4, 5]);
+    assert_eq!(vec![3, 4, 5], pop_all(|| r.pop_back()));
+}
+
+#[test]
+fn array_list_matches_vec() {
+    let drain: fn(ArrayList<i32>) -> Vec<i32> = |mut l| pop_all(|| l.pop());
+    let drain_model: fn(Vec<i32>) -> Vec<i32> = |mut v| pop_all(|| v.pop());
+    check(&samples(), drain, drain_model);
+    check_ord::<_, ArrayList<i32>, Vec<i32>>(&samples());
+    check_extend(&samples(), drain, drain_model);
+}
+
+#[test]
+fn binary_search_tree_matches_sorted_vec() {
+    let drain: fn(BinarySearchTree<i32>) -> Vec<i32> = |t| t.into_iter().collect();
+    let drain_model: fn(SortedVec) -> Vec<i32> = |s| s.0;
+    check(&samples(), drain, drain_model);
+    check_extend(&samples(), drain, drain_model);
+
+    // equality ignores shape and balancing mode
+    let mut plain = BinarySearchTree::new();
+    plain.extend([1, 2, 3]);
+    let avl: BinarySearchTree<i32> = [3, 1, 2].into_iter().collect();
+    assert_eq!(plain, avl);
+    assert_eq!(hash(&plain), hash(&avl));
+}
+
+#[test]
+fn priority_queue_matches_sorted_vec() {
+    let drain: fn(PriorityQueue<i32>) -> Vec<i32> = |mut q| pop_all(|| q.pop());
+    let drain_model: fn(SortedVec) -> Vec<i32> = |s| s.0;
+    check(&samples(), drain, drain_model);
+    check_extend(&samples(), drain, drain_model);
+}
+
+#[test]
+fn tree_map_matches_btree_map() {
+    type Entry = (i32, char);
+    let samples: Vec<Vec<Entry>> = vec![
+        vec![],
+        vec![(1, 'a')],
+        vec![(1, 'b')],
+        vec![(1, 'a'), (2, 'b')],
+        vec![(2, 'b'), (1, 'a')],
+        vec![(1, 'a'), (1, 'z'), (3, 'c')],
+        vec![(3, 'c')],
+    ];
+    let drain: fn(TreeMap<i32, char>) -> Vec<Entry> = |m| m.into_iter().collect();
+    let drain_model: fn(BTreeMap<i32, char>) -> Vec<Entry> = |m| m.into_iter().collect();
+    check(&samples, drain, drain_model);
+    check_ord::<_, TreeMap<i32, char>, BTreeMap<i32, char>>(&samples);
+    check_extend(&samples, drain, drain_model);
+}
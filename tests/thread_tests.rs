@@ -46,5 +46,4 @@ fn thread_tests() {
     });
     let ll = handle.join().unwrap();
     assert_eq!(Some(&45), ll.get(0));
-    assert!(true);
 }